@@ -0,0 +1,40 @@
+#define BOOST_TEST_MODULE example
+#include <boost/test/unit_test.hpp>
+
+#include <stdexcept>
+
+BOOST_AUTO_TEST_SUITE(Foo)
+
+BOOST_AUTO_TEST_CASE(Foo1) {
+  BOOST_TEST(false);
+}
+
+BOOST_AUTO_TEST_CASE(Foo2) {
+  BOOST_TEST(true);
+}
+
+BOOST_AUTO_TEST_SUITE_END()
+
+BOOST_AUTO_TEST_SUITE(Bar)
+
+BOOST_AUTO_TEST_CASE(Bar1) {
+  BOOST_TEST(true);
+}
+
+BOOST_AUTO_TEST_CASE(Bar2, *boost::unit_test::disabled()) {
+  BOOST_TEST(true);
+}
+
+BOOST_AUTO_TEST_SUITE(Baz)
+
+BOOST_AUTO_TEST_CASE(Baz1) {
+  throw std::runtime_error("unexpected");
+}
+
+BOOST_AUTO_TEST_SUITE_END()
+
+BOOST_AUTO_TEST_SUITE_END()
+
+BOOST_AUTO_TEST_CASE(Qux1) {
+  BOOST_TEST(1 + 1 == 2);
+}
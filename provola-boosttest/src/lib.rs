@@ -0,0 +1,198 @@
+use provola_core::test::xunit::FullyQualifiedTestCase;
+use provola_core::test_runners::TestRunnerOpt;
+use provola_core::{
+    run_process, AvailableTests, CoreReport, Error, Executable, ProcessOpt, TempReport,
+};
+use std::path::Path;
+
+pub mod report;
+
+fn add_list_argv(mut argv: Vec<String>) -> Vec<String> {
+    argv.push("--list_content".to_string());
+    argv
+}
+
+fn add_run_argv(mut argv: Vec<String>, log_path: &Path, report_path: &Path) -> Vec<String> {
+    argv.push("--log_format=XML".to_string());
+    argv.push("--log_level=test_suite".to_string());
+    argv.push(format!("--log_sink={}", log_path.display()));
+    argv.push("--report_format=XML".to_string());
+    argv.push("--report_level=detailed".to_string());
+    argv.push(format!("--report_sink={}", report_path.display()));
+    argv
+}
+
+/// `--list_content` prints the tree of test units, indented by 4 spaces for
+/// each level. Enabled test units end with `*`. A test unit is a test suite
+/// when the following one is nested inside it.
+fn parse_available_tests(s: &str) -> AvailableTests {
+    let units: Vec<(usize, &str)> = s
+        .lines()
+        .filter(|x| !x.trim().is_empty())
+        .map(|x| {
+            let level = (x.len() - x.trim_start().len()) / 4;
+            let name = x.trim().trim_end_matches('*');
+            (level, name)
+        })
+        .collect();
+
+    let mut tests = AvailableTests::default();
+    let mut path: Vec<&str> = Vec::new();
+
+    for (index, (level, name)) in units.iter().enumerate() {
+        path.truncate(*level);
+
+        let is_test_suite = units
+            .get(index + 1)
+            .is_some_and(|(next_level, _)| next_level > level);
+
+        if is_test_suite {
+            path.push(name);
+        } else if path.is_empty() {
+            tests.push(report::MASTER_TEST_SUITE, *name);
+        } else {
+            tests.push(path.join(report::SEPARATOR), *name);
+        }
+    }
+
+    tests
+}
+
+/// `--run_test` argument to select some test cases, separated by `:`
+fn make_run_test(selected: &[FullyQualifiedTestCase]) -> String {
+    let paths: Vec<String> = selected
+        .iter()
+        .map(|fqtc| {
+            let test_suite = &fqtc.test_suite.0;
+            let test_case = &fqtc.test_case.0;
+
+            if test_suite == report::MASTER_TEST_SUITE {
+                test_case.clone()
+            } else {
+                format!("{}{}{}", test_suite, report::SEPARATOR, test_case)
+            }
+        })
+        .collect();
+
+    format!("--run_test={}", paths.join(":"))
+}
+
+pub struct TestRunner {
+    executable: Executable,
+}
+
+impl TestRunner {
+    fn generate_available_tests(&self) -> Result<AvailableTests, Error> {
+        let argv = add_list_argv((&self.executable).into());
+        // Boost.Test prints the list to stderr
+        let output = run_process(&argv, &ProcessOpt::default())?;
+        Ok(parse_available_tests(&output.err))
+    }
+
+    fn generate_report(&self, opt: &TestRunnerOpt) -> Result<CoreReport, Error> {
+        let log_file = TempReport::new("boosttest_log", "xml");
+        let report_file = TempReport::new("boosttest_report", "xml");
+        let mut argv = add_run_argv((&self.executable).into(), &log_file, &report_file);
+
+        let selected = opt.only.find_selected(|| self.generate_available_tests())?;
+
+        if let Some(selected) = &selected {
+            argv.push(make_run_test(selected));
+        }
+
+        let output = run_process(&argv, &ProcessOpt::default())?;
+
+        if !output.out.is_empty() {
+            log::debug!("{}", output.out);
+        }
+
+        let mut report = report::parse_log(&log_file.read_to_string()?)?;
+
+        // The report is not written when the executable is aborted
+        match report_file
+            .read_to_string()
+            .and_then(|x| report::parse_results(&x))
+        {
+            Ok(results) => report::apply_results(&mut report, &results),
+            Err(e) => log::warn!("{}", e),
+        }
+
+        Ok(report)
+    }
+}
+
+impl From<Executable> for TestRunner {
+    fn from(executable: Executable) -> Self {
+        TestRunner { executable }
+    }
+}
+
+impl provola_core::test_runners::TestRunner for TestRunner {
+    fn run(&self, opt: &TestRunnerOpt) -> Result<provola_core::TestResult, provola_core::Error> {
+        let report = self.generate_report(opt)?;
+        let result = report.into();
+        Ok(result)
+    }
+
+    fn list(&self, _opt: &TestRunnerOpt) -> Result<AvailableTests, Error> {
+        self.generate_available_tests()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::path::PathBuf;
+
+    fn make_exec() -> Executable {
+        let path = PathBuf::from("./examples/data/build/example");
+        Executable::from(path)
+    }
+
+    #[test]
+    fn parse_list_content_output() {
+        let s = std::fs::read_to_string("examples/data/list_content.txt").unwrap();
+        let list = parse_available_tests(&s);
+        insta::assert_debug_snapshot!(&list);
+    }
+
+    #[test]
+    fn run_test_of_nested_test_suite() {
+        let fqtc = FullyQualifiedTestCase::from_test_suite_test_case("Bar/Baz", "Baz1");
+        assert_eq!(make_run_test(&[fqtc]), "--run_test=Bar/Baz/Baz1");
+    }
+
+    #[test]
+    fn run_test_of_master_test_suite() {
+        let fqtc = FullyQualifiedTestCase::from_test_suite_test_case("Master Test Suite", "Qux1");
+        assert_eq!(make_run_test(&[fqtc]), "--run_test=Qux1");
+    }
+
+    #[test]
+    fn run_test_of_many_test_cases() {
+        let selected = [
+            FullyQualifiedTestCase::from_test_suite_test_case("Foo", "Foo1"),
+            FullyQualifiedTestCase::from_test_suite_test_case("Bar/Baz", "Baz1"),
+        ];
+        assert_eq!(make_run_test(&selected), "--run_test=Foo/Foo1:Bar/Baz/Baz1");
+    }
+
+    // Ignored because example must be built first
+    #[ignore]
+    #[test]
+    fn from_valid_executable() {
+        let tr = TestRunner::from(make_exec());
+        let tr: Box<dyn provola_core::test_runners::TestRunner> = Box::new(tr);
+        let tr_opt = TestRunnerOpt::default();
+        assert!(tr.run(&tr_opt).is_ok());
+    }
+
+    // Ignored because example must be built first
+    #[ignore]
+    #[test]
+    fn generate_available_tests_from_valid_executable() {
+        let tr = TestRunner::from(make_exec());
+        let list = tr.generate_available_tests().unwrap();
+        assert_eq!(list.len(), 6);
+    }
+}
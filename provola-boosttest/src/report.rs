@@ -0,0 +1,445 @@
+//! Boost.Test writes test results in two different files: the log
+//! (`--log_format=XML`) with every failed assertion and its location, and
+//! the report (`--report_format=XML`) with the final result of each test
+//! case. Boost.Test can also write JUnit XML (`--logger=JUNIT`).
+
+use provola_core::report::{CoreStatus, SourceLocation};
+use provola_core::test::xunit::FullyQualifiedTestCase;
+use provola_core::{CoreFailure, CoreReport, CoreTestCase, CoreTestSuite, Error};
+use xml::attribute::OwnedAttribute;
+use xml::reader::{EventReader, XmlEvent};
+
+/// Test suite of test cases which are not inside any user defined test
+/// suite. `--list_content` does not show the name of the master test suite,
+/// so we always use the default one.
+pub const MASTER_TEST_SUITE: &str = "Master Test Suite";
+
+/// Boost.Test separates nested test suites with a slash (e.g. in
+/// `--run_test=Bar/Baz/Baz1`)
+pub const SEPARATOR: &str = "/";
+
+fn attribute<'a>(attributes: &'a [OwnedAttribute], name: &str) -> Option<&'a str> {
+    attributes
+        .iter()
+        .find(|x| x.name.local_name == name)
+        .map(|x| x.value.as_str())
+}
+
+/// Boost.Test uses "unknown location" when an exception is thrown outside
+/// of an assertion
+fn make_location(file: Option<&str>, line: Option<&str>) -> Option<SourceLocation> {
+    let file = file.filter(|x| !x.is_empty() && *x != "unknown location")?;
+    let line = line.and_then(|x| x.parse().ok()).filter(|x| *x > 0);
+
+    Some(SourceLocation {
+        file: file.to_string(),
+        line,
+    })
+}
+
+fn suite_name(path: &[String]) -> String {
+    if path.is_empty() {
+        MASTER_TEST_SUITE.to_string()
+    } else {
+        path.join(SEPARATOR)
+    }
+}
+
+fn parse_error(e: xml::reader::Error) -> Error {
+    Error::ReportParseError(Box::new(e))
+}
+
+/// Name of the log entries which are failures
+fn failure_type(element: &str) -> Option<&'static str> {
+    match element {
+        "Error" => Some("error"),
+        "FatalError" => Some("fatal error"),
+        "Exception" => Some("exception"),
+        _ => None,
+    }
+}
+
+#[derive(Default)]
+struct Suites(Vec<CoreTestSuite>);
+
+impl Suites {
+    fn get(&mut self, name: String) -> &mut CoreTestSuite {
+        let index = match self.0.iter().position(|x| x.name == name) {
+            Some(index) => index,
+            None => {
+                self.0.push(CoreTestSuite {
+                    name,
+                    ..Default::default()
+                });
+                self.0.len() - 1
+            }
+        };
+
+        &mut self.0[index]
+    }
+}
+
+/// Parse the XML log. Nested test suites are flattened, using the path
+/// (e.g. `Bar/Baz`) as test suite name.
+pub fn parse_log(s: &str) -> Result<CoreReport, Error> {
+    let mut suites = Suites::default();
+
+    // Path of the current test suite, including the master test suite
+    let mut path: Vec<String> = Vec::new();
+    let mut test_case: Option<CoreTestCase> = None;
+    let mut failure: Option<CoreFailure> = None;
+    // Text is collected only directly inside a failure or a testing time
+    let mut text: Option<String> = None;
+    let mut depth_in_failure = 0;
+
+    for event in EventReader::from_str(s) {
+        match event.map_err(parse_error)? {
+            XmlEvent::StartElement {
+                name, attributes, ..
+            } => {
+                let name = name.local_name.as_str();
+
+                if failure.is_some() {
+                    depth_in_failure += 1;
+                    continue;
+                }
+
+                match (name, &mut test_case) {
+                    ("TestSuite", None) => {
+                        path.push(
+                            attribute(&attributes, "name")
+                                .unwrap_or_default()
+                                .to_string(),
+                        );
+                    }
+                    ("TestCase", None) => {
+                        let skipped = attribute(&attributes, "skipped") == Some("yes");
+
+                        test_case = Some(CoreTestCase {
+                            name: attribute(&attributes, "name")
+                                .unwrap_or_default()
+                                .to_string(),
+                            status: if skipped {
+                                CoreStatus::Skipped
+                            } else {
+                                CoreStatus::Pass
+                            },
+                            ..Default::default()
+                        });
+                    }
+                    ("TestingTime", Some(_)) => {
+                        text = Some(String::default());
+                    }
+                    (name, Some(_)) => {
+                        if let Some(ttype) = failure_type(name) {
+                            let file = attribute(&attributes, "file");
+                            let line = attribute(&attributes, "line");
+
+                            failure = Some(CoreFailure {
+                                ttype: ttype.to_string(),
+                                message: String::default(),
+                                location: make_location(file, line),
+                            });
+
+                            text = Some(String::default());
+                        }
+                    }
+                    _ => {}
+                }
+            }
+            XmlEvent::Characters(s) | XmlEvent::CData(s) => {
+                if let (Some(text), 0) = (&mut text, depth_in_failure) {
+                    text.push_str(&s);
+                }
+            }
+            XmlEvent::EndElement { name } => {
+                let name = name.local_name.as_str();
+
+                if depth_in_failure > 0 {
+                    depth_in_failure -= 1;
+                    continue;
+                }
+
+                if let Some(mut x) = failure.take() {
+                    x.message = text.take().unwrap_or_default().trim().to_string();
+
+                    if let Some(test_case) = &mut test_case {
+                        test_case.status = CoreStatus::Fail;
+                        test_case.failures.push(x);
+                    }
+
+                    continue;
+                }
+
+                match name {
+                    "TestingTime" => {
+                        // Microseconds
+                        let time = text.take().and_then(|x| x.trim().parse().ok());
+
+                        if let Some(test_case) = &mut test_case {
+                            test_case.time = time.map(std::time::Duration::from_micros);
+                        }
+                    }
+                    "TestCase" => {
+                        if let Some(mut x) = test_case.take() {
+                            // Master test suite is not part of the path
+                            let name = suite_name(path.get(1..).unwrap_or_default());
+                            let fqtc = FullyQualifiedTestCase::from_test_suite_test_case(
+                                name.clone(),
+                                x.name.clone(),
+                            );
+                            x.fqtc = Some(fqtc.id);
+                            x.classname = Some(name.clone());
+                            suites.get(name).testcases.push(x);
+                        }
+                    }
+                    "TestSuite" => {
+                        path.pop();
+                    }
+                    _ => {}
+                }
+            }
+            _ => {}
+        }
+    }
+
+    Ok(make_core_report(suites.0))
+}
+
+/// Result of a single test case, as written in the XML report
+#[derive(Debug, PartialEq, Eq)]
+pub struct TestCaseResult {
+    pub test_suite: String,
+    pub test_case: String,
+    pub result: String,
+}
+
+/// Parse the XML report, which has the final result of each test case
+pub fn parse_results(s: &str) -> Result<Vec<TestCaseResult>, Error> {
+    let mut results = Vec::new();
+    let mut path: Vec<String> = Vec::new();
+
+    for event in EventReader::from_str(s) {
+        match event.map_err(parse_error)? {
+            XmlEvent::StartElement {
+                name, attributes, ..
+            } => {
+                let unit_name = attribute(&attributes, "name").unwrap_or_default();
+
+                match name.local_name.as_str() {
+                    "TestSuite" => path.push(unit_name.to_string()),
+                    "TestCase" => results.push(TestCaseResult {
+                        test_suite: suite_name(path.get(1..).unwrap_or_default()),
+                        test_case: unit_name.to_string(),
+                        result: attribute(&attributes, "result")
+                            .unwrap_or_default()
+                            .to_string(),
+                    }),
+                    _ => {}
+                }
+            }
+            XmlEvent::EndElement { name } if name.local_name == "TestSuite" => {
+                path.pop();
+            }
+            _ => {}
+        }
+    }
+
+    Ok(results)
+}
+
+/// The log does not tell when a failure is expected (see
+/// `expected_failures` decorator) or when a test case is aborted without
+/// logging anything, so the final status comes from the report.
+pub fn apply_results(report: &mut CoreReport, results: &[TestCaseResult]) {
+    for test_suite in report.testsuites.iter_mut() {
+        for test_case in test_suite.testcases.iter_mut() {
+            let result = results
+                .iter()
+                .find(|x| x.test_suite == test_suite.name && x.test_case == test_case.name);
+
+            let result = match result {
+                Some(x) => x.result.as_str(),
+                None => continue,
+            };
+
+            match result {
+                "passed" => {
+                    test_case.status = CoreStatus::Pass;
+                    test_case.failures.clear();
+                }
+                "skipped" => {
+                    test_case.status = CoreStatus::Skipped;
+                }
+                result => {
+                    test_case.status = CoreStatus::Fail;
+
+                    if test_case.failures.is_empty() {
+                        test_case.failures.push(CoreFailure {
+                            ttype: result.to_string(),
+                            message: format!("Test case {}", result),
+                            location: None,
+                        });
+                    }
+                }
+            }
+        }
+    }
+
+    let testsuites = std::mem::take(&mut report.testsuites);
+    *report = make_core_report(testsuites);
+}
+
+fn make_core_report(mut testsuites: Vec<CoreTestSuite>) -> CoreReport {
+    for test_suite in testsuites.iter_mut() {
+        let failures = test_suite
+            .testcases
+            .iter()
+            .filter(|x| matches!(x.status, CoreStatus::Fail))
+            .count();
+
+        let skipped = test_suite
+            .testcases
+            .iter()
+            .filter(|x| matches!(x.status, CoreStatus::Skipped))
+            .count();
+
+        let time = test_suite
+            .testcases
+            .iter()
+            .filter_map(|x| x.time)
+            .reduce(|x, y| x + y);
+
+        test_suite.tests = test_suite.testcases.len();
+        test_suite.failures = Some(failures);
+        test_suite.skipped = Some(skipped);
+        test_suite.time = time;
+    }
+
+    let failures = testsuites.iter().filter_map(|x| x.failures).sum();
+    let tests = testsuites.iter().map(|x| x.tests).sum();
+    let time = testsuites
+        .iter()
+        .filter_map(|x| x.time)
+        .reduce(|x, y| x + y);
+
+    CoreReport {
+        name: Some("Boost.Test".to_string()),
+        failures: Some(failures),
+        tests: Some(tests),
+        time,
+        testsuites,
+        ..Default::default()
+    }
+}
+
+/// Boost.Test writes the location inside the failure message, e.g.
+/// `- file   : tests.cpp` and `- line   : 9`
+fn extract_location(message: &str) -> Option<SourceLocation> {
+    let field = |name: &str| {
+        message.lines().find_map(|x| {
+            let (key, value) = x.trim().strip_prefix('-')?.split_once(':')?;
+            Some(value.trim()).filter(|_| key.trim() == name)
+        })
+    };
+
+    make_location(field("file"), field("line"))
+}
+
+/// Parse a JUnit report written by Boost.Test. Every test case is in the
+/// same JUnit test suite (the master test suite), while the path of its
+/// test suite is the classname, separated by dots.
+pub fn parse_junit_report(s: &str) -> Result<CoreReport, Error> {
+    let report = provola_junit::parse_report(s)?;
+    let mut suites = Suites::default();
+
+    for test_suite in report.testsuites {
+        for mut test_case in test_suite.testcases {
+            let path: Vec<String> = test_case
+                .classname
+                .as_deref()
+                .unwrap_or_default()
+                .split('.')
+                .filter(|x| !x.is_empty())
+                .map(String::from)
+                .collect();
+
+            let name = suite_name(&path);
+            let fqtc =
+                FullyQualifiedTestCase::from_test_suite_test_case(name.clone(), &test_case.name);
+            test_case.fqtc = Some(fqtc.id);
+            test_case.classname = Some(name.clone());
+
+            for failure in test_case.failures.iter_mut() {
+                failure.location = extract_location(&failure.message);
+            }
+
+            suites.get(name).testcases.push(test_case);
+        }
+    }
+
+    Ok(make_core_report(suites.0))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn read_example_file(path: &str) -> String {
+        std::fs::read_to_string(path).unwrap()
+    }
+
+    #[test]
+    fn parse_xml_log() {
+        let s = read_example_file("examples/data/test_log.xml");
+        let report = parse_log(&s).unwrap();
+        insta::assert_debug_snapshot!(&report);
+    }
+
+    #[test]
+    fn parse_xml_report() {
+        let s = read_example_file("examples/data/test_report.xml");
+        let results = parse_results(&s).unwrap();
+        insta::assert_debug_snapshot!(&results);
+    }
+
+    #[test]
+    fn apply_results_to_log() {
+        let s = read_example_file("examples/data/test_log.xml");
+        let mut report = parse_log(&s).unwrap();
+        let s = read_example_file("examples/data/test_report.xml");
+        let results = parse_results(&s).unwrap();
+        apply_results(&mut report, &results);
+        assert_eq!(report.tests, Some(6));
+        assert_eq!(report.failures, Some(2));
+    }
+
+    #[test]
+    fn expected_failure_passes() {
+        let s = read_example_file("examples/data/test_log.xml");
+        let mut report = parse_log(&s).unwrap();
+        let results = vec![TestCaseResult {
+            test_suite: "Foo".to_string(),
+            test_case: "Foo1".to_string(),
+            result: "passed".to_string(),
+        }];
+        apply_results(&mut report, &results);
+        let foo1 = &report.testsuites[0].testcases[0];
+        assert!(matches!(foo1.status, CoreStatus::Pass));
+        assert!(foo1.failures.is_empty());
+    }
+
+    #[test]
+    fn convert_junit_report() {
+        let s = read_example_file("examples/data/test_report_junit.xml");
+        let report = parse_junit_report(&s).unwrap();
+        insta::assert_debug_snapshot!(&report);
+    }
+
+    #[test]
+    fn extract_location_from_junit_failure() {
+        let message = "ASSERTION FAILURE:\n- file   : tests.cpp\n- line   : 9\n- message: x";
+        let location = extract_location(message).unwrap();
+        assert_eq!(location.to_string(), "tests.cpp:9");
+    }
+}
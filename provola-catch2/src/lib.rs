@@ -1,5 +1,6 @@
-use provola_core::test_runners::TestRunnerOpt;
-use provola_core::{CoreReport, Error, Executable};
+use provola_core::report::{captured_output, join_outputs};
+use provola_core::test_runners::{Pattern, Selection, TestRunnerOpt};
+use provola_core::{CoreReport, Error, Executable, ProcessOpt};
 use std::time::Duration;
 use subprocess::Popen;
 use subprocess::PopenConfig;
@@ -13,8 +14,82 @@ fn add_arguments(mut argv: Vec<String>) -> Vec<String> {
     argv
 }
 
-fn run_exec(executable: &Executable) -> Result<CoreReport, Error> {
-    let argv = add_arguments(executable.into());
+fn escape_name(s: &str) -> String {
+    s.replace('\\', "\\\\")
+        .replace(',', "\\,")
+        .replace('[', "\\[")
+        .replace(']', "\\]")
+        .replace('"', "\\\"")
+}
+
+/// Catch2 test spec term equivalent to a pattern, if any. Catch2 accepts
+/// wildcards only at the start or at the end of a name.
+fn make_spec_term(pattern: &Pattern) -> Option<String> {
+    match pattern {
+        Pattern::Tag(tag) => Some(format!("[{}]", escape_name(tag))),
+        Pattern::Name(name) => {
+            let start = usize::from(name.starts_with('*'));
+            let end = name.len() - usize::from(name.len() > start && name.ends_with('*'));
+            let inner = &name[start..end];
+
+            if inner.contains(['*', '?']) {
+                None
+            } else {
+                let star = |x: bool| if x { "*" } else { "" };
+                let s = escape_name(inner);
+                Some(format!(
+                    "\"{}{}{}\"",
+                    star(start > 0),
+                    s,
+                    star(end < name.len())
+                ))
+            }
+        }
+        Pattern::Index(_) | Pattern::Id(_) | Pattern::Suite(_) => None,
+    }
+}
+
+/// Catch2 test spec equivalent to a selection, if every pattern can be
+/// translated. Terms separated by spaces are in AND, by commas in OR.
+fn make_test_spec(selection: &Selection) -> Option<String> {
+    let include: Option<Vec<String>> = selection.include.iter().map(make_spec_term).collect();
+    let exclude: Option<Vec<String>> = selection.exclude.iter().map(make_spec_term).collect();
+
+    let exclude: Vec<String> = exclude?.into_iter().map(|x| format!("~{}", x)).collect();
+    let include = include?;
+
+    let spec = if include.is_empty() {
+        exclude.join(" ")
+    } else {
+        include
+            .into_iter()
+            .map(|x| {
+                std::iter::once(x)
+                    .chain(exclude.iter().cloned())
+                    .collect::<Vec<_>>()
+            })
+            .map(|x| x.join(" "))
+            .collect::<Vec<_>>()
+            .join(",")
+    };
+
+    Some(spec)
+}
+
+fn run_exec(
+    executable: &Executable,
+    process: &ProcessOpt,
+    test_spec: Option<String>,
+) -> Result<CoreReport, Error> {
+    let mut argv = add_arguments(executable.into());
+    argv.extend(test_spec);
+    log::debug!("{:?} {:?}", process, argv);
+
+    let env = (!process.env.is_empty()).then(|| {
+        let mut vars = PopenConfig::current_env();
+        vars.extend(process.env.iter().map(|(k, v)| (k.into(), v.into())));
+        vars
+    });
 
     let mut p = Popen::create(
         &argv,
@@ -22,11 +97,13 @@ fn run_exec(executable: &Executable) -> Result<CoreReport, Error> {
             stdin: Redirection::None,
             stdout: Redirection::Pipe,
             stderr: Redirection::Pipe,
+            cwd: process.cwd.as_ref().map(|x| x.as_os_str().to_owned()),
+            env,
             ..Default::default()
         },
     )?;
 
-    let (out, _err) = p.communicate(None)?;
+    let (out, err) = p.communicate(None)?;
 
     // TODO Timeout from configuration
     let timeout = Duration::from_secs(3600);
@@ -41,7 +118,19 @@ fn run_exec(executable: &Executable) -> Result<CoreReport, Error> {
     if let Some(out) = out {
         let rep: report::Report =
             serde_xml_rs::from_str(&out).map_err(|e| Error::ReportParseError(Box::new(e)))?;
-        let core_rep = CoreReport::from(rep);
+        let mut core_rep = CoreReport::from(rep);
+
+        if let Some(cwd) = &process.cwd {
+            core_rep.resolve_locations(cwd);
+        }
+
+        // Output of test cases is captured by Catch2, anything else written
+        // to stderr belongs to the whole executable
+        if let Some(test_suite) = core_rep.testsuites.first_mut() {
+            let err = captured_output(err);
+            test_suite.system_err = join_outputs(test_suite.system_err.take(), err);
+        }
+
         Ok(core_rep)
     } else {
         Err(Error::ReportUnavailable)
@@ -50,18 +139,76 @@ fn run_exec(executable: &Executable) -> Result<CoreReport, Error> {
 
 pub struct TestRunner {
     executable: Executable,
+    process: ProcessOpt,
+}
+
+impl TestRunner {
+    /// Run the executable in the working directory and with the
+    /// environment of `process`
+    pub fn with_process(executable: Executable, process: ProcessOpt) -> Self {
+        Self {
+            executable,
+            process,
+        }
+    }
 }
 
 impl From<Executable> for TestRunner {
     fn from(executable: Executable) -> Self {
-        Self { executable }
+        Self::with_process(executable, ProcessOpt::default())
     }
 }
 
 impl provola_core::test_runners::TestRunner for TestRunner {
-    fn run(&self, _opt: &TestRunnerOpt) -> Result<provola_core::TestResult, provola_core::Error> {
-        let report = run_exec(&self.executable)?;
+    fn run(&self, opt: &TestRunnerOpt) -> Result<provola_core::TestResult, provola_core::Error> {
+        let selection = opt.only.selection();
+        let test_spec = selection.as_ref().and_then(make_test_spec);
+
+        let report = match (selection, test_spec) {
+            (None, _) => run_exec(&self.executable, &self.process, None)?,
+            (Some(_), Some(test_spec)) => {
+                run_exec(&self.executable, &self.process, Some(test_spec))?
+            }
+            // Tests cannot be listed, so they are filtered after running all
+            (Some(selection), None) => {
+                if selection.has_tags() {
+                    log::warn!("Tags cannot be mixed with indexes, ids or suites");
+                }
+
+                let mut report = run_exec(&self.executable, &self.process, None)?;
+                report.retain_selection(&selection);
+                report
+            }
+        };
+
         let result = report.into();
         Ok(result)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_spec_with_tags_and_exclusions() {
+        let selection = Selection::parse(["Foo*", "[fast]", "-*slow", "~[db]"]).unwrap();
+        let spec = make_test_spec(&selection).unwrap();
+        assert_eq!(spec, r#""Foo*" ~"*slow" ~[db],[fast] ~"*slow" ~[db]"#);
+    }
+
+    #[test]
+    fn test_spec_escape_names() {
+        let selection = Selection::parse(["a, b [c]"]).unwrap();
+        let spec = make_test_spec(&selection).unwrap();
+        assert_eq!(spec, r#""a\, b \[c\]""#);
+    }
+
+    #[test]
+    fn test_spec_unavailable_for_inner_wildcards() {
+        let selection = Selection::parse(["Foo*Bar"]).unwrap();
+        assert_eq!(make_test_spec(&selection), None);
+        let selection = Selection::parse(["0"]).unwrap();
+        assert_eq!(make_test_spec(&selection), None);
+    }
+}
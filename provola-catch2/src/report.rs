@@ -1,4 +1,4 @@
-use provola_core::report::CoreStatus;
+use provola_core::report::{captured_output, CoreStatus, SourceLocation};
 use provola_core::test::xunit::FullyQualifiedTestCase;
 use provola_core::CoreFailure;
 use provola_core::CoreReport;
@@ -32,8 +32,8 @@ impl Report {
 
 #[derive(Serialize, Deserialize, Debug, Default)]
 pub struct TestSuite {
-    // // TODO system-err
-    // // TODO system-out
+    // system-out and system-err of the test suite are just the ones of its
+    // test cases, joined together
     pub name: String,
     #[serde(rename = "testcase", default)]
     pub testcases: Vec<TestCase>,
@@ -53,6 +53,10 @@ pub struct TestCase {
     pub time: Duration,
     #[serde(rename = "failure", default)]
     pub failures: Vec<Failure>,
+    #[serde(rename = "system-out")]
+    pub system_out: Option<String>,
+    #[serde(rename = "system-err")]
+    pub system_err: Option<String>,
     #[serde(skip)]
     pub fqtc: Option<FullyQualifiedTestCase>,
 }
@@ -62,6 +66,8 @@ pub struct Failure {
     #[serde(rename = "type")]
     pub ttype: FailureType,
     pub message: Message,
+    #[serde(rename = "$value", default)]
+    pub text: String,
 }
 
 impl From<Report> for CoreReport {
@@ -116,15 +122,29 @@ impl From<TestCase> for CoreTestCase {
             time: parse_duration(&x.time),
             failures: x.failures.into_iter().map(|x| x.into()).collect(),
             fqtc: x.fqtc.map(|x| x.id),
+            system_out: captured_output(x.system_out),
+            system_err: captured_output(x.system_err),
+            repeat: None,
+            location: None,
         }
     }
 }
 
 impl From<Failure> for CoreFailure {
     fn from(x: Failure) -> Self {
+        // The location of the failed assertion is the last line, like
+        // `at tests.cpp:4`
+        let location = x
+            .text
+            .lines()
+            .rev()
+            .find_map(|x| x.trim().strip_prefix("at "))
+            .and_then(SourceLocation::parse);
+
         CoreFailure {
             message: x.message,
             ttype: x.ttype,
+            location,
         }
     }
 }
@@ -148,6 +168,31 @@ mod tests {
         insta::assert_debug_snapshot!(&report);
     }
 
+    #[test]
+    fn captured_output_of_test_cases() {
+        let s = r#"<testsuites>
+  <testsuite name="example">
+    <testcase classname="example.Bar" name="Bar1" time="0.000" status="run">
+      <system-out>
+Connecting to bar
+</system-out>
+    </testcase>
+    <testcase classname="example.Bar" name="Bar2" time="0.000" status="run"/>
+    <system-out/>
+    <system-err/>
+  </testsuite>
+</testsuites>"#;
+
+        let report: Report = serde_xml_rs::from_str(s).unwrap();
+        let report = CoreReport::from(report);
+        let testcases = &report.testsuites[0].testcases;
+        assert_eq!(
+            testcases[0].system_out.as_deref(),
+            Some("Connecting to bar")
+        );
+        assert_eq!(testcases[1].system_out, None);
+    }
+
     #[test]
     fn convert_to_core_report() {
         let report = read_example_file();
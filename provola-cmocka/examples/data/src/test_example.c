@@ -0,0 +1,35 @@
+#include <setjmp.h>
+#include <stdarg.h>
+#include <stddef.h>
+#include <stdint.h>
+
+#include <cmocka.h>
+
+static int add(int a, int b) {
+  return a + b;
+}
+
+static void test_add(void** state) {
+  (void)state;
+  assert_int_equal(3, add(1, 2));
+}
+
+static void test_add_negative(void** state) {
+  (void)state;
+  assert_int_equal(-1, add(-1, -1));
+}
+
+static void test_not_implemented(void** state) {
+  (void)state;
+  skip();
+}
+
+int main(void) {
+  const struct CMUnitTest example[] = {
+      cmocka_unit_test(test_add),
+      cmocka_unit_test(test_add_negative),
+      cmocka_unit_test(test_not_implemented),
+  };
+
+  return cmocka_run_group_tests(example, NULL, NULL);
+}
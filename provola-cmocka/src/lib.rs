@@ -0,0 +1,131 @@
+use provola_core::test_runners::{Selection, TestRunnerOpt};
+use provola_core::{
+    run_process, AvailableTests, CoreReport, Error, Executable, ProcessOpt, TempReport,
+};
+
+pub mod report;
+
+fn make_available_tests(report: &CoreReport) -> AvailableTests {
+    let mut tests = AvailableTests::default();
+
+    for test_suite in report.testsuites.iter() {
+        for test_case in test_suite.testcases.iter() {
+            tests.push(&test_suite.name, &test_case.name);
+        }
+    }
+
+    tests
+}
+
+/// Run an executable built with CMocka
+pub struct TestRunner {
+    executable: Executable,
+}
+
+impl TestRunner {
+    fn generate_report(&self) -> Result<CoreReport, Error> {
+        // CMocka does not overwrite existing files, so the directory must
+        // be empty
+        let report_dir = TempReport::dir("cmocka")?;
+
+        // `%g` is replaced by the name of the group, so each group of tests
+        // is written to its own file
+        let xml_file = report_dir.join("%g.xml");
+        let env = vec![
+            ("CMOCKA_MESSAGE_OUTPUT".to_string(), "xml".to_string()),
+            (
+                "CMOCKA_XML_FILE".to_string(),
+                xml_file.display().to_string(),
+            ),
+        ];
+        let opt = ProcessOpt {
+            env,
+            ..Default::default()
+        };
+        run_process(&Vec::from(&self.executable), &opt)?;
+
+        let pattern = report_dir.join("*.xml");
+        let paths = provola_junit::find_report_files(&pattern.to_string_lossy())?;
+        let mut report = provola_junit::read_report_files(&paths)?;
+        report::add_failure_locations(&mut report);
+
+        Ok(report)
+    }
+
+    fn generate_selected_report(&self, opt: &TestRunnerOpt) -> Result<CoreReport, Error> {
+        let mut report = self.generate_report()?;
+
+        let selection = match opt.only.selection() {
+            Some(selection) => selection,
+            None => return Ok(report),
+        };
+
+        let tests = make_available_tests(&report);
+        let ids: Vec<_> = selection.filter(&tests).iter().map(|x| x.id).collect();
+
+        if ids.is_empty() {
+            return Err(Error::NothingToDo);
+        }
+
+        // CMocka can filter tests only with `cmocka_set_test_filter`, called
+        // by the test executable itself, so all tests are executed and only
+        // the selected one is kept in the report.
+        report.retain_selection(&Selection::from_ids(ids));
+
+        if report.testsuites.is_empty() {
+            return Err(Error::NothingToDo);
+        }
+
+        Ok(report)
+    }
+}
+
+impl From<Executable> for TestRunner {
+    fn from(executable: Executable) -> Self {
+        TestRunner { executable }
+    }
+}
+
+impl provola_core::test_runners::TestRunner for TestRunner {
+    fn run(&self, opt: &TestRunnerOpt) -> Result<provola_core::TestResult, provola_core::Error> {
+        let report = self.generate_selected_report(opt)?;
+        let result = report.into();
+        Ok(result)
+    }
+
+    fn list(&self, _opt: &TestRunnerOpt) -> Result<AvailableTests, Error> {
+        // Tests are known only after running them
+        let report = self.generate_report()?;
+        Ok(make_available_tests(&report))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::path::PathBuf;
+
+    fn make_exec() -> Executable {
+        let path = PathBuf::from("./examples/data/build/example");
+        Executable::from(path)
+    }
+
+    #[test]
+    fn list_tests_from_report() {
+        let s = std::fs::read_to_string("examples/data/test_report.xml").unwrap();
+        let list = make_available_tests(&report::parse_report(&s).unwrap());
+        assert_eq!(list.len(), 3);
+        assert_eq!(list.get(0).unwrap().test_suite.0, "example");
+        assert_eq!(list.get(0).unwrap().test_case.0, "test_add");
+    }
+
+    // Ignored because example must be built first
+    #[ignore]
+    #[test]
+    fn from_valid_executable() {
+        let tr = TestRunner::from(make_exec());
+        let tr: Box<dyn provola_core::test_runners::TestRunner> = Box::new(tr);
+        let tr_opt = TestRunnerOpt::default();
+        assert!(tr.run(&tr_opt).is_ok());
+    }
+}
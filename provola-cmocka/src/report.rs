@@ -0,0 +1,53 @@
+use provola_core::report::SourceLocation;
+use provola_core::{CoreReport, Error};
+
+/// CMocka does not write the location of a failed assertion as an
+/// attribute, but in a line of the failure message, like
+/// `src/test.c:19: error: Failure!`
+fn find_location(message: &str) -> Option<SourceLocation> {
+    message
+        .lines()
+        .filter_map(SourceLocation::split_prefix)
+        .map(|(location, _)| location)
+        .next()
+}
+
+pub(crate) fn add_failure_locations(report: &mut CoreReport) {
+    let failures = report
+        .testsuites
+        .iter_mut()
+        .flat_map(|x| x.testcases.iter_mut())
+        .flat_map(|x| x.failures.iter_mut());
+
+    for failure in failures {
+        if failure.location.is_none() {
+            failure.location = find_location(&failure.message);
+        }
+    }
+}
+
+/// Parse a report written with `CMOCKA_MESSAGE_OUTPUT=xml`
+pub fn parse_report(s: &str) -> Result<CoreReport, Error> {
+    let mut report = provola_junit::parse_report(s)?;
+    add_failure_locations(&mut report);
+    Ok(report)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn convert_xml_report() {
+        let s = std::fs::read_to_string("examples/data/test_report.xml").unwrap();
+        let report = parse_report(&s).unwrap();
+        insta::assert_debug_snapshot!(&report);
+    }
+
+    #[test]
+    fn location_of_failed_assertion() {
+        let message = "0x1 != 0x2\nsrc/test.c:19: error: Failure!";
+        let location = find_location(message).unwrap();
+        assert_eq!(location.to_string(), "src/test.c:19");
+    }
+}
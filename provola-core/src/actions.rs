@@ -1,5 +1,5 @@
 use crate::test_runners::{TestRunner, TestRunnerOpt};
-use crate::{Error, Executable, Language, TestResult};
+use crate::{CoreReport, Error, Executable, Language, Reason, TestEvent, TestResult};
 use std::{convert::TryFrom, io::Read, path::PathBuf};
 
 #[derive(serde::Deserialize, serde::Serialize, Clone, Debug, PartialEq, Eq)]
@@ -56,21 +56,206 @@ impl TryFrom<&TestDataOut> for String {
     }
 }
 
+/// How the actions of a composite action are run
+#[derive(serde::Deserialize, serde::Serialize, Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Schedule {
+    /// One after the other
+    #[default]
+    Sequential,
+    /// All at the same time
+    Parallel,
+}
+
 pub enum Action {
-    BuildTestInputOutput(Language, Source, TestDataIn, TestDataOut),
+    /// Build the source and run it with the input, comparing what it writes
+    /// with the expected output. The last field is the wrapper command of
+    /// the built executable, if not empty.
+    BuildTestInputOutput(Language, Source, TestDataIn, TestDataOut, Vec<String>),
     TestRunner(Box<dyn TestRunner>, TestRunnerOpt),
+    /// Many actions, each one with a name, whose results are merged into a
+    /// single report. Test suites of each action are grouped by its name.
+    Composite(Vec<(String, Action)>, Schedule),
+}
+
+/// Report of an action in a composite action, with its test suites grouped
+/// by its name. An error is a failed test case.
+fn make_group(name: &str, result: Result<TestResult, Error>) -> CoreReport {
+    let result = result.unwrap_or_else(|e| TestResult::Fail(Reason::Generic(e.to_string())));
+    let mut report = result.into_report(name);
+
+    for test_suite in report.testsuites.iter_mut() {
+        test_suite.package = Some(name.to_string());
+    }
+
+    report
+}
+
+/// Events of an action in a composite action, without the start and the end
+/// of its run, which are emitted once for all of them
+fn forward_event(event: TestEvent, events: &mut dyn FnMut(TestEvent)) {
+    if !matches!(event, TestEvent::RunStarted(_) | TestEvent::RunFinished) {
+        events(event);
+    }
+}
+
+/// Run actions at the same time. Events of all the actions are emitted by
+/// the calling thread.
+fn run_parallel(
+    actions: &[(String, Action)],
+    events: &mut dyn FnMut(TestEvent),
+) -> Vec<Result<TestResult, Error>> {
+    let (events_s, events_r) = std::sync::mpsc::channel();
+
+    std::thread::scope(|s| {
+        let handles: Vec<_> = actions
+            .iter()
+            .map(|(_, action)| {
+                let events_s = events_s.clone();
+                s.spawn(move || {
+                    action.run_with_events(&mut |x| events_s.send(x).unwrap_or_default())
+                })
+            })
+            .collect();
+
+        // Events are received until every action is finished
+        drop(events_s);
+        events_r
+            .into_iter()
+            .for_each(|x| forward_event(x, &mut *events));
+
+        handles
+            .into_iter()
+            .map(|handle| {
+                handle
+                    .join()
+                    .unwrap_or_else(|_| Err(Error::GenericError("Action panicked".to_string())))
+            })
+            .collect()
+    })
 }
 
 impl Action {
     pub fn run(&self) -> Result<TestResult, Error> {
+        self.run_with_events(&mut |_| {})
+    }
+
+    /// Run, emitting events while tests are running
+    pub fn run_with_events(&self, events: &mut dyn FnMut(TestEvent)) -> Result<TestResult, Error> {
         match self {
-            Action::BuildTestInputOutput(lang, source, input, output) => {
-                let executable = Some(Executable::try_from((*lang, source))?);
-                let executable = executable.as_ref().ok_or(Error::NoExecutable)?;
-                crate::test::data::test(executable, input, output)
+            Action::BuildTestInputOutput(lang, source, input, output, wrapper) => {
+                let executable = Executable::try_from((*lang, source))?.wrap(wrapper.clone());
+                crate::test::data::test(&executable, input, output)
+            }
+
+            Action::TestRunner(runner, opt) => match &opt.repeat {
+                Some(repeat) => runner.run_repeated(opt, repeat, events),
+                None => runner.run_with_events(opt, events),
+            },
+
+            Action::Composite(actions, schedule) => {
+                events(TestEvent::RunStarted(None));
+
+                let results = match schedule {
+                    Schedule::Sequential => actions
+                        .iter()
+                        .map(|(_, action)| {
+                            action.run_with_events(&mut |x| forward_event(x, &mut *events))
+                        })
+                        .collect(),
+                    Schedule::Parallel => run_parallel(actions, events),
+                };
+
+                events(TestEvent::RunFinished);
+
+                let mut report = CoreReport::default();
+
+                for ((name, _), result) in actions.iter().zip(results) {
+                    report.merge(make_group(name, result));
+                }
+
+                // Names of the reports are the ones of the groups
+                report.name = None;
+                Ok(report.into())
             }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::report::CoreStatus;
+    use crate::CoreTestCase;
+
+    /// Test case `Foo.Foo1`, passing or not
+    struct FakeTestRunner {
+        pass: bool,
+    }
+
+    impl TestRunner for FakeTestRunner {
+        fn run(&self, _opt: &TestRunnerOpt) -> Result<TestResult, Error> {
+            let status = if self.pass {
+                CoreStatus::Pass
+            } else {
+                CoreStatus::Fail
+            };
+
+            let test_case = CoreTestCase {
+                name: "Foo1".to_string(),
+                status,
+                ..Default::default()
+            };
 
-            Action::TestRunner(runner, opt) => runner.run(opt),
+            let mut report = CoreReport::default();
+            report.push_test_case("Foo", test_case);
+            Ok(report.into())
         }
     }
+
+    fn make_action(pass: bool) -> Action {
+        let tr = Box::new(FakeTestRunner { pass });
+        Action::TestRunner(tr, TestRunnerOpt::default())
+    }
+
+    #[test]
+    fn composite_action_groups_reports() {
+        for schedule in [Schedule::Sequential, Schedule::Parallel] {
+            let actions = vec![
+                ("unit".to_string(), make_action(true)),
+                ("integration".to_string(), make_action(false)),
+            ];
+            let action = Action::Composite(actions, schedule);
+
+            let mut events = Vec::new();
+            let result = action.run_with_events(&mut |x| events.push(x)).unwrap();
+            assert!(matches!(result, TestResult::Fail(_)));
+
+            let started = events
+                .iter()
+                .filter(|x| matches!(x, TestEvent::RunStarted(_)))
+                .count();
+            assert_eq!(started, 1);
+
+            let report = result.report().unwrap();
+            assert_eq!(report.tests, Some(2));
+            assert_eq!(report.failures, Some(1));
+            assert_eq!(report.testsuites.len(), 2);
+            assert_eq!(report.testsuites[0].package.as_deref(), Some("unit"));
+            assert_eq!(report.testsuites[1].package.as_deref(), Some("integration"));
+        }
+    }
+
+    #[test]
+    fn result_without_report_is_a_test_case() {
+        let result = TestResult::Fail(Reason::not_expected("foo", "bar"));
+        let report = result.into_report("reverse");
+        assert_eq!(report.failures, Some(1));
+
+        let test_case = &report.testsuites[0].testcases[0];
+        assert_eq!(test_case.name, "reverse");
+        assert_eq!(
+            test_case.failures[0].message,
+            "Expected\n\nbar\n\nActual\n\nfoo"
+        );
+    }
 }
@@ -0,0 +1,398 @@
+//! Code coverage of a run: which lines and branches have been executed by
+//! tests. It can be read from (and written to) LCOV tracefiles, or read from
+//! the JSON written by `gcov --json-format`.
+
+use crate::report::{FilePath, Line};
+use crate::Error;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fmt::Write;
+
+/// How many lines (or branches) have been executed, out of the total
+#[derive(Serialize, Deserialize, Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct Counts {
+    pub covered: usize,
+    pub total: usize,
+}
+
+impl Counts {
+    fn from_hits<'a>(hits: impl Iterator<Item = &'a (Line, u64)>) -> Self {
+        hits.fold(Self::default(), |counts, (_, x)| Counts {
+            covered: counts.covered + usize::from(*x > 0),
+            total: counts.total + 1,
+        })
+    }
+
+    fn add(self, other: Counts) -> Self {
+        Counts {
+            covered: self.covered + other.covered,
+            total: self.total + other.total,
+        }
+    }
+
+    /// Percentage of covered ones, `None` if there are none
+    pub fn percent(&self) -> Option<f32> {
+        (self.total > 0).then(|| 100.0 * self.covered as f32 / self.total as f32)
+    }
+}
+
+impl std::fmt::Display for Counts {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self.percent() {
+            Some(percent) => write!(f, "{:.1}% ({}/{})", percent, self.covered, self.total),
+            None => write!(f, "-"),
+        }
+    }
+}
+
+/// Line and branch counts of a whole run, or of a single file
+#[derive(Serialize, Deserialize, Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct CoverageSummary {
+    pub lines: Counts,
+    pub branches: Counts,
+}
+
+impl CoverageSummary {
+    /// Difference of the percentage of covered lines from another run, if
+    /// both have lines
+    pub fn lines_delta(&self, other: &CoverageSummary) -> Option<f32> {
+        Some(self.lines.percent()? - other.lines.percent()?)
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Default, Clone, PartialEq, Eq)]
+pub struct FileCoverage {
+    pub file: FilePath,
+    /// How many times each line has been executed, sorted by line
+    pub lines: Vec<(Line, u64)>,
+    /// How many times each branch has been taken, with the line of the
+    /// branch, sorted by line
+    pub branches: Vec<(Line, u64)>,
+}
+
+impl FileCoverage {
+    pub fn summary(&self) -> CoverageSummary {
+        CoverageSummary {
+            lines: Counts::from_hits(self.lines.iter()),
+            branches: Counts::from_hits(self.branches.iter()),
+        }
+    }
+
+    /// Add the hits of another run of the same file
+    fn merge(&mut self, other: FileCoverage) {
+        let mut lines: BTreeMap<Line, u64> = self.lines.drain(..).collect();
+
+        for (line, hits) in other.lines {
+            *lines.entry(line).or_default() += hits;
+        }
+
+        self.lines = lines.into_iter().collect();
+
+        // Branches are identified by their line and their position in it
+        let mut branches: BTreeMap<(Line, usize), u64> = BTreeMap::new();
+
+        for x in [std::mem::take(&mut self.branches), other.branches] {
+            let mut previous = None;
+            let mut index = 0;
+
+            for (line, hits) in x {
+                index = if previous == Some(line) { index + 1 } else { 0 };
+                previous = Some(line);
+                *branches.entry((line, index)).or_default() += hits;
+            }
+        }
+
+        self.branches = branches
+            .into_iter()
+            .map(|((line, _), x)| (line, x))
+            .collect();
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Default, Clone, PartialEq)]
+pub struct Coverage {
+    /// Covered files, sorted by path
+    pub files: Vec<FileCoverage>,
+    /// Summary of the previous run, to show how coverage changed
+    #[serde(default)]
+    pub previous: Option<CoverageSummary>,
+}
+
+impl Coverage {
+    fn from_files(files: impl IntoIterator<Item = FileCoverage>) -> Self {
+        let mut coverage = Coverage::default();
+        files.into_iter().for_each(|x| coverage.push(x));
+        coverage
+    }
+
+    /// Add a file, merging it with the same one if already there
+    fn push(&mut self, file: FileCoverage) {
+        match self.files.binary_search_by(|x| x.file.cmp(&file.file)) {
+            Ok(index) => self.files[index].merge(file),
+            Err(index) => self.files.insert(index, file),
+        }
+    }
+
+    pub fn merge(&mut self, other: Coverage) {
+        other.files.into_iter().for_each(|x| self.push(x));
+        self.previous = self.previous.or(other.previous);
+    }
+
+    pub fn summary(&self) -> CoverageSummary {
+        self.files
+            .iter()
+            .map(|x| x.summary())
+            .fold(CoverageSummary::default(), |a, b| CoverageSummary {
+                lines: a.lines.add(b.lines),
+                branches: a.branches.add(b.branches),
+            })
+    }
+
+    /// Difference of the percentage of covered lines from the previous run
+    pub fn lines_delta(&self) -> Option<f32> {
+        self.summary().lines_delta(self.previous.as_ref()?)
+    }
+
+    /// Parse a LCOV tracefile, as written by `llvm-cov export -format=lcov`
+    /// or `lcov --capture`
+    pub fn parse_lcov(s: &str) -> Result<Self, Error> {
+        let invalid = |line: &str| Error::GenericError(format!("Invalid LCOV record: {}", line));
+
+        let mut files = Vec::new();
+        let mut file: Option<FileCoverage> = None;
+
+        for line in s.lines().map(str::trim) {
+            let (key, value) = line.split_once(':').unwrap_or((line, ""));
+            let fields: Vec<&str> = value.split(',').collect();
+
+            match (key, file.as_mut()) {
+                ("SF", _) => {
+                    file = Some(FileCoverage {
+                        file: value.to_string(),
+                        ..Default::default()
+                    })
+                }
+                ("DA", Some(x)) => {
+                    let line = fields[0].parse().map_err(|_| invalid(line))?;
+                    let hits = fields.get(1).and_then(|x| x.parse().ok()).unwrap_or(0);
+                    x.lines.push((line, hits));
+                }
+                ("BRDA", Some(x)) => {
+                    let line = fields[0].parse().map_err(|_| invalid(line))?;
+                    // Taken is `-` when the line has never been executed
+                    let taken = fields.get(3).and_then(|x| x.parse().ok()).unwrap_or(0);
+                    x.branches.push((line, taken));
+                }
+                ("end_of_record", Some(_)) => files.extend(file.take()),
+                _ => {}
+            }
+        }
+
+        Ok(Self::from_files(files))
+    }
+
+    /// Write as a LCOV tracefile
+    pub fn to_lcov(&self) -> String {
+        let mut s = String::new();
+
+        for file in &self.files {
+            let summary = file.summary();
+            writeln!(s, "SF:{}", file.file).unwrap();
+
+            let mut index = 0;
+            let mut last_line = None;
+
+            for (line, taken) in &file.branches {
+                index = if last_line == Some(line) {
+                    index + 1
+                } else {
+                    0
+                };
+                last_line = Some(line);
+                writeln!(s, "BRDA:{},0,{},{}", line, index, taken).unwrap();
+            }
+
+            writeln!(s, "BRF:{}", summary.branches.total).unwrap();
+            writeln!(s, "BRH:{}", summary.branches.covered).unwrap();
+
+            for (line, hits) in &file.lines {
+                writeln!(s, "DA:{},{}", line, hits).unwrap();
+            }
+
+            writeln!(s, "LF:{}", summary.lines.total).unwrap();
+            writeln!(s, "LH:{}", summary.lines.covered).unwrap();
+            writeln!(s, "end_of_record").unwrap();
+        }
+
+        s
+    }
+
+    /// Parse the output of `gcov --json-format --stdout`: a JSON document
+    /// for each object file, one per line
+    pub fn parse_gcov_json(s: &str) -> Result<Self, Error> {
+        #[derive(Deserialize)]
+        struct Object {
+            /// Paths of files are relative to it
+            #[serde(default)]
+            current_working_directory: Option<String>,
+            files: Vec<File>,
+        }
+
+        #[derive(Deserialize)]
+        struct File {
+            file: String,
+            lines: Vec<LineEntry>,
+        }
+
+        #[derive(Deserialize)]
+        struct LineEntry {
+            line_number: Line,
+            count: u64,
+            #[serde(default)]
+            branches: Vec<Branch>,
+        }
+
+        #[derive(Deserialize)]
+        struct Branch {
+            count: u64,
+        }
+
+        let mut files = Vec::new();
+
+        for line in s.lines().filter(|x| !x.trim().is_empty()) {
+            let object: Object = serde_json::from_str(line)
+                .map_err(|e| Error::GenericError(format!("Invalid gcov JSON: {}", e)))?;
+
+            let cwd = object.current_working_directory.unwrap_or_default();
+
+            for file in object.files {
+                let path = std::path::Path::new(&cwd).join(&file.file);
+                let mut x = FileCoverage {
+                    file: path.to_string_lossy().to_string(),
+                    ..Default::default()
+                };
+
+                for line in file.lines {
+                    x.lines.push((line.line_number, line.count));
+                    let branches = line.branches.iter().map(|b| (line.line_number, b.count));
+                    x.branches.extend(branches);
+                }
+
+                files.push(x);
+            }
+        }
+
+        Ok(Self::from_files(files))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const LCOV: &str = "SF:/src/foo.c
+FN:3,foo
+FNDA:1,foo
+BRDA:4,0,0,1
+BRDA:4,0,1,-
+BRF:2
+BRH:1
+DA:3,1
+DA:4,1
+DA:5,0
+LF:3
+LH:2
+end_of_record
+SF:/src/bar.c
+DA:1,4
+end_of_record
+";
+
+    #[test]
+    fn parse_lcov_and_write_it_back() {
+        let coverage = Coverage::parse_lcov(LCOV).unwrap();
+        assert_eq!(coverage.files.len(), 2);
+        assert_eq!(coverage.files[0].file, "/src/bar.c");
+
+        let foo = coverage.files[1].summary();
+        assert_eq!(
+            foo.lines,
+            Counts {
+                covered: 2,
+                total: 3
+            }
+        );
+        assert_eq!(
+            foo.branches,
+            Counts {
+                covered: 1,
+                total: 2
+            }
+        );
+
+        let summary = coverage.summary();
+        assert_eq!(
+            summary.lines,
+            Counts {
+                covered: 3,
+                total: 4
+            }
+        );
+        assert_eq!(summary.lines.to_string(), "75.0% (3/4)");
+
+        let written = coverage.to_lcov();
+        assert_eq!(Coverage::parse_lcov(&written).unwrap(), coverage);
+    }
+
+    #[test]
+    fn merge_branches_by_line_and_position() {
+        let mut file = FileCoverage {
+            file: "foo.c".to_string(),
+            lines: vec![(3, 1)],
+            branches: vec![(3, 1), (3, 0)],
+        };
+        file.merge(FileCoverage {
+            file: "foo.c".to_string(),
+            lines: vec![(3, 1), (7, 1)],
+            branches: vec![(3, 0), (3, 2), (7, 1)],
+        });
+        assert_eq!(file.lines, vec![(3, 2), (7, 1)]);
+        assert_eq!(file.branches, vec![(3, 1), (3, 2), (7, 1)]);
+    }
+
+    #[test]
+    fn parse_gcov_json_and_merge() {
+        let s = r#"{"format_version":"1","gcc_version":"11.2.0","current_working_directory":"/src","files":[{"file":"foo.c","functions":[],"lines":[{"line_number":3,"count":1,"unexecuted_block":false,"function_name":"foo","branches":[{"count":1,"fallthrough":true,"throw":false},{"count":0,"fallthrough":false,"throw":false}]},{"line_number":5,"count":0,"unexecuted_block":true,"function_name":"foo","branches":[]}]}]}
+{"format_version":"1","gcc_version":"11.2.0","current_working_directory":"/src","files":[{"file":"foo.c","functions":[],"lines":[{"line_number":5,"count":2,"unexecuted_block":false,"function_name":"foo","branches":[]}]}]}"#;
+
+        let mut coverage = Coverage::parse_gcov_json(s).unwrap();
+        assert_eq!(coverage.files.len(), 1);
+        assert_eq!(coverage.files[0].file, "/src/foo.c");
+        assert_eq!(coverage.files[0].lines, vec![(3, 1), (5, 2)]);
+
+        let summary = coverage.summary();
+        assert_eq!(
+            summary.lines,
+            Counts {
+                covered: 2,
+                total: 2
+            }
+        );
+        assert_eq!(
+            summary.branches,
+            Counts {
+                covered: 1,
+                total: 2
+            }
+        );
+
+        coverage.previous = Some(CoverageSummary {
+            lines: Counts {
+                covered: 1,
+                total: 2,
+            },
+            ..Default::default()
+        });
+        assert_eq!(coverage.lines_delta(), Some(50.0));
+    }
+}
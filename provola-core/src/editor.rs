@@ -0,0 +1,74 @@
+use crate::report::SourceLocation;
+use crate::Error;
+
+/// Command opening a file in the editor, where `{file}` and `{line}` are
+/// replaced with the location and `$NAME` with environment variables
+pub const DEFAULT_EDITOR_TEMPLATE: &str = "$EDITOR +{line} {file}";
+
+fn expand_var(word: &str, var: &dyn Fn(&str) -> Option<String>) -> Result<Vec<String>, Error> {
+    let name = match word.strip_prefix('$') {
+        Some(name) => name,
+        None => return Ok(vec![word.to_string()]),
+    };
+
+    // A variable like EDITOR may contain arguments too, as in `code -g`
+    let value = var(name).ok_or_else(|| Error::GenericError(format!("{} is not set", name)))?;
+
+    Ok(value.split_whitespace().map(String::from).collect())
+}
+
+/// Arguments of the command opening `location` in the editor, where
+/// variables are read with `var`
+pub fn make_editor_argv(
+    template: &str,
+    location: &SourceLocation,
+    var: &dyn Fn(&str) -> Option<String>,
+) -> Result<Vec<String>, Error> {
+    let file = location.absolute_path().to_string_lossy().to_string();
+    let line = location.line.unwrap_or(1).to_string();
+    let mut argv = Vec::new();
+
+    for word in template.split_whitespace() {
+        for word in expand_var(word, var)? {
+            argv.push(word.replace("{file}", &file).replace("{line}", &line));
+        }
+    }
+
+    if argv.is_empty() {
+        return Err(Error::GenericError("Editor not set".to_string()));
+    }
+
+    Ok(argv)
+}
+
+/// Open `location` in the editor, without waiting for it
+pub fn open_in_editor(template: &str, location: &SourceLocation) -> Result<(), Error> {
+    let argv = make_editor_argv(template, location, &|x| std::env::var(x).ok())?;
+    log::debug!("{:?}", argv);
+    std::process::Command::new(&argv[0])
+        .args(&argv[1..])
+        .spawn()?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn editor_argv_from_template() {
+        let location = SourceLocation {
+            file: "/src/tests.cpp".to_string(),
+            line: Some(12),
+        };
+
+        let var = |x: &str| (x == "EDITOR").then(|| "code -g".to_string());
+        let argv = make_editor_argv("$EDITOR {file}:{line}", &location, &var).unwrap();
+        assert_eq!(argv, vec!["code", "-g", "/src/tests.cpp:12"]);
+
+        let argv = make_editor_argv("vim +{line} {file}", &location, &var).unwrap();
+        assert_eq!(argv, vec!["vim", "+12", "/src/tests.cpp"]);
+
+        assert!(make_editor_argv("$VISUAL {file}", &location, &var).is_err());
+    }
+}
@@ -33,7 +33,11 @@ pub enum Error {
     #[error("feature not available: {0}")]
     TestRunnerFeatureNotAvailable(TestRunnerFeature),
     #[error("cannot parse report: {0}")]
-    ReportParseError(Box<dyn std::error::Error>),
+    ReportParseError(Box<dyn std::error::Error + Send + Sync>),
+    #[error("test runner crashed without writing {}", .0.display())]
+    TestRunnerCrashed(std::path::PathBuf),
+    #[error("invalid test pattern: {0}")]
+    InvalidTestPattern(String),
     #[error("gui is not available")]
     GuiNotAvailable,
     #[error("{0}")]
@@ -0,0 +1,84 @@
+use crate::report::{CoreStatus, Duration};
+use crate::test::xunit::FullyQualifiedTestCase;
+use crate::CoreReport;
+use serde::{Deserialize, Serialize};
+
+/// Something happening while tests are running. Test runners emit events as
+/// soon as they know, so results can be shown before the run is finished.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "type", content = "content")]
+pub enum TestEvent {
+    /// Tests are going to run, with the number of tests, if known
+    RunStarted(Option<usize>),
+    TestStarted(FullyQualifiedTestCase),
+    TestFinished {
+        fqtc: FullyQualifiedTestCase,
+        status: CoreStatus,
+        time: Option<Duration>,
+    },
+    /// Output written by the test runner, usually a line
+    Output(String),
+    RunFinished,
+}
+
+impl TestEvent {
+    /// Events of test cases which are already finished, for test runners
+    /// which know results only at the end
+    pub fn from_report(report: &CoreReport) -> impl Iterator<Item = TestEvent> + '_ {
+        report.testsuites.iter().flat_map(|test_suite| {
+            test_suite.testcases.iter().map(|test_case| {
+                let mut fqtc = FullyQualifiedTestCase::from_test_suite_test_case(
+                    &test_suite.name,
+                    &test_case.name,
+                );
+
+                // The id generated by the test runner is the one to be used
+                if let Some(id) = test_case.fqtc {
+                    fqtc.id = id;
+                }
+
+                TestEvent::TestFinished {
+                    fqtc,
+                    status: test_case.status,
+                    time: test_case.time,
+                }
+            })
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::CoreTestCase;
+
+    #[test]
+    fn events_from_report() {
+        let mut report = CoreReport::default();
+        let test_case = |name: &str, status| CoreTestCase {
+            name: name.to_string(),
+            status,
+            ..Default::default()
+        };
+        report.push_test_case("Foo", test_case("Foo1", CoreStatus::Pass));
+        report.push_test_case("Bar", test_case("Bar1", CoreStatus::Fail));
+        report.push_test_case("Foo", test_case("Foo2", CoreStatus::Pass));
+        assert_eq!(report.failures, Some(1));
+        assert_eq!(report.tests, Some(3));
+
+        let events: Vec<_> = TestEvent::from_report(&report).collect();
+        assert_eq!(events.len(), 3);
+        assert!(matches!(
+            &events[1],
+            TestEvent::TestFinished { fqtc, status: CoreStatus::Pass, .. }
+            if fqtc.to_string() == "Foo.Foo2"
+        ));
+        assert!(matches!(
+            &events[2],
+            TestEvent::TestFinished {
+                status: CoreStatus::Fail,
+                ..
+            }
+        ));
+    }
+}
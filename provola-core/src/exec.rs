@@ -7,6 +7,9 @@ use crate::{build::gen_executable, Language, Source};
 pub enum Executable {
     Simple(PathBuf),
     Multiple(Vec<String>),
+    /// Run through a wrapper command, like `valgrind` or `qemu-aarch64 -L
+    /// sysroot`
+    Wrapped(Vec<String>, Box<Executable>),
 }
 
 impl From<PathBuf> for Executable {
@@ -34,6 +37,11 @@ impl From<&Executable> for Vec<String> {
         match &exec {
             Executable::Simple(path) => vec![path_to_string(path)],
             &Executable::Multiple(x) => x.clone(),
+            Executable::Wrapped(wrapper, exec) => {
+                let mut argv = wrapper.clone();
+                argv.extend(Vec::from(exec.as_ref()));
+                argv
+            }
         }
     }
 }
@@ -44,4 +52,55 @@ impl Executable {
         let argv = vec![interpreter, source];
         Executable::Multiple(argv)
     }
+
+    /// Run through `wrapper`, if not empty. An executable which is already
+    /// wrapped is run by `wrapper` together with its own wrapper.
+    pub fn wrap(self, wrapper: Vec<String>) -> Self {
+        match self {
+            _ if wrapper.is_empty() => self,
+            Executable::Wrapped(inner, exec) => {
+                Executable::Wrapped([wrapper, inner].concat(), exec)
+            }
+            exec => Executable::Wrapped(wrapper, Box::new(exec)),
+        }
+    }
+
+    /// The wrapper command, empty if there is none
+    pub fn wrapper(&self) -> &[String] {
+        match self {
+            Executable::Wrapped(wrapper, _) => wrapper,
+            _ => &[],
+        }
+    }
+
+    /// The executable, without its wrapper
+    pub fn unwrapped(&self) -> &Executable {
+        match self {
+            Executable::Wrapped(_, exec) => exec,
+            _ => self,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn wrapper_is_prepended() {
+        let exec = Executable::from(PathBuf::from("build/tests"));
+        let wrapper = vec!["taskset".to_string(), "-c".to_string(), "2".to_string()];
+        let wrapped = exec.clone().wrap(wrapper.clone());
+        assert_eq!(
+            Vec::from(&wrapped),
+            vec!["taskset", "-c", "2", "build/tests"]
+        );
+        assert_eq!(wrapped.wrapper(), wrapper.as_slice());
+        assert_eq!(wrapped.unwrapped(), &exec);
+        assert_eq!(exec.clone().wrap(vec![]), exec);
+
+        let rewrapped = wrapped.wrap(vec!["env".to_string()]);
+        assert_eq!(rewrapped.wrapper(), ["env", "taskset", "-c", "2"]);
+        assert_eq!(rewrapped.unwrapped(), &exec);
+    }
 }
@@ -1,22 +1,33 @@
 mod actions;
 mod build;
+pub mod coverage;
+pub mod editor;
 mod errors;
+mod event;
 mod exec;
 mod lang;
+mod process;
 pub mod report;
 mod reporter;
 mod result;
+mod temp;
 pub mod test;
 pub mod test_runners;
 mod watch;
 
 pub use actions::Action;
+pub use actions::Schedule;
 pub use actions::Source;
 pub use actions::TestDataIn;
 pub use actions::TestDataOut;
 pub use errors::Error;
+pub use event::TestEvent;
 pub use exec::Executable;
 pub use lang::Language;
+pub use process::{
+    run_process, run_process_streaming, split_command_line, ProcessOpt, ProcessOutput,
+    DEFAULT_TIMEOUT,
+};
 pub use report::CoreFailure;
 pub use report::CoreReport;
 pub use report::CoreTestCase;
@@ -25,6 +36,7 @@ pub use reporter::Error as ReporterError;
 pub use reporter::Reporter;
 pub use result::Reason;
 pub use result::TestResult;
+pub use temp::{unique_name, TempReport};
 pub use test::xunit::AvailableTests;
 pub use watch::ProvolaWatcher as Watcher;
 pub use watch::WatchOptions;
@@ -0,0 +1,306 @@
+use crate::Error;
+use std::io::{BufRead, BufReader, ErrorKind};
+use std::path::PathBuf;
+use std::sync::mpsc::{self, RecvTimeoutError};
+use std::time::{Duration, Instant};
+use subprocess::{ExitStatus, Popen, PopenConfig, Redirection};
+
+/// How long a test runner can run before it is killed
+// TODO Timeout from configuration
+pub const DEFAULT_TIMEOUT: Duration = Duration::from_secs(3600);
+
+/// How a process is run by `run_process`
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct ProcessOpt {
+    /// Working directory, the current one if `None`
+    pub cwd: Option<PathBuf>,
+    /// Variables added to the environment of this process
+    pub env: Vec<(String, String)>,
+    /// `DEFAULT_TIMEOUT` if `None`
+    pub timeout: Option<Duration>,
+}
+
+/// What a process has written to stdout and stderr
+#[derive(Debug, Default, Clone)]
+pub struct ProcessOutput {
+    pub out: String,
+    pub err: String,
+    /// `None` if the process has been killed after the timeout
+    pub exit_code: Option<u32>,
+}
+
+fn to_string(x: Option<Vec<u8>>) -> String {
+    String::from_utf8_lossy(&x.unwrap_or_default()).to_string()
+}
+
+/// Working directory and environment of a process, with stdout captured
+fn popen_config(opt: &ProcessOpt) -> PopenConfig {
+    let env = (!opt.env.is_empty()).then(|| {
+        let mut vars = PopenConfig::current_env();
+        vars.extend(opt.env.iter().map(|(k, v)| (k.into(), v.into())));
+        vars
+    });
+
+    PopenConfig {
+        stdin: Redirection::None,
+        stdout: Redirection::Pipe,
+        cwd: opt.cwd.as_ref().map(|x| x.as_os_str().to_owned()),
+        env,
+        ..Default::default()
+    }
+}
+
+/// Run a process until it exits, capturing its output. Test frameworks
+/// usually exit with an error code when some test fails, so it is not an
+/// error, while being terminated by a signal is. A process still running
+/// after the timeout is killed, and what it has written so far is returned.
+pub fn run_process(argv: &[String], opt: &ProcessOpt) -> Result<ProcessOutput, Error> {
+    log::debug!("{:?} {:?}", opt.env, argv);
+
+    let mut p = Popen::create(
+        argv,
+        PopenConfig {
+            stderr: Redirection::Pipe,
+            ..popen_config(opt)
+        },
+    )?;
+
+    let timeout = opt.timeout.unwrap_or(DEFAULT_TIMEOUT);
+
+    // The time limit applies while reading, since the output is read until
+    // the process closes it
+    let ((out, err), timed_out) = match p.communicate_start(None).limit_time(timeout).read() {
+        Ok(capture) => (capture, false),
+        Err(e) if e.error.kind() == ErrorKind::TimedOut => (e.capture, true),
+        Err(e) => return Err(e.error.into()),
+    };
+
+    let mut output = ProcessOutput {
+        out: to_string(out),
+        err: to_string(err),
+        exit_code: None,
+    };
+
+    if !output.err.is_empty() {
+        log::debug!("{}", output.err);
+    }
+
+    // A process closing its output may still be running
+    let exit_status = if timed_out {
+        None
+    } else {
+        p.wait_timeout(timeout)?
+    };
+
+    match exit_status {
+        Some(ExitStatus::Exited(code)) => {
+            log::debug!("Exit status: {}", code);
+            output.exit_code = Some(code);
+            Ok(output)
+        }
+        Some(_) => Err(Error::GenericError("Invalid exit status".to_string())),
+        None => {
+            log::warn!("Kill subprocess after {:?}", timeout);
+            p.kill()?;
+            p.wait()?;
+            Ok(output)
+        }
+    }
+}
+
+/// Split a command line in arguments, like a POSIX shell does: single and
+/// double quotes group words, a backslash escapes the next character.
+/// Variables and globs are not expanded.
+pub fn split_command_line(s: &str) -> Result<Vec<String>, Error> {
+    let unterminated = || Error::GenericError(format!("Unterminated quote or escape in {}", s));
+
+    let mut args = Vec::new();
+    let mut arg: Option<String> = None;
+    let mut chars = s.chars();
+
+    while let Some(c) = chars.next() {
+        match c {
+            '\'' => {
+                let arg = arg.get_or_insert_with(String::new);
+
+                loop {
+                    match chars.next().ok_or_else(unterminated)? {
+                        '\'' => break,
+                        c => arg.push(c),
+                    }
+                }
+            }
+            '"' => {
+                let arg = arg.get_or_insert_with(String::new);
+
+                loop {
+                    match chars.next().ok_or_else(unterminated)? {
+                        '"' => break,
+                        '\\' => match chars.next().ok_or_else(unterminated)? {
+                            c @ ('"' | '\\' | '$' | '`') => arg.push(c),
+                            '\n' => {}
+                            c => {
+                                arg.push('\\');
+                                arg.push(c);
+                            }
+                        },
+                        c => arg.push(c),
+                    }
+                }
+            }
+            '\\' => match chars.next().ok_or_else(unterminated)? {
+                '\n' => {}
+                c => arg.get_or_insert_with(String::new).push(c),
+            },
+            c if c.is_whitespace() => args.extend(arg.take()),
+            c => arg.get_or_insert_with(String::new).push(c),
+        }
+    }
+
+    args.extend(arg);
+    Ok(args)
+}
+
+/// Run a process until it exits, passing each line it writes to stdout or
+/// stderr to `on_line` as soon as it is written. The exit status is `None`
+/// if the process is still running after the timeout, and it is killed.
+pub fn run_process_streaming(
+    argv: &[String],
+    opt: &ProcessOpt,
+    on_line: &mut dyn FnMut(&str),
+) -> Result<Option<ExitStatus>, Error> {
+    log::debug!("{:?} {:?}", opt.env, argv);
+
+    let mut p = Popen::create(
+        argv,
+        PopenConfig {
+            stderr: Redirection::Merge,
+            ..popen_config(opt)
+        },
+    )?;
+
+    let timeout = opt.timeout.unwrap_or(DEFAULT_TIMEOUT);
+    let deadline = Instant::now() + timeout;
+
+    // Lines are read by another thread, so the time limit applies even
+    // when the process stops writing without exiting
+    let (tx, rx) = mpsc::channel();
+    let stdout = p.stdout.take();
+
+    std::thread::spawn(move || {
+        let mut reader = match stdout {
+            Some(x) => BufReader::new(x),
+            None => return,
+        };
+        let mut line = Vec::new();
+
+        while matches!(reader.read_until(b'\n', &mut line), Ok(n) if n > 0) {
+            let s = String::from_utf8_lossy(&line);
+
+            if tx
+                .send(s.trim_end_matches(['\n', '\r']).to_string())
+                .is_err()
+            {
+                break;
+            }
+
+            line.clear();
+        }
+    });
+
+    let timed_out = loop {
+        match rx.recv_timeout(deadline.saturating_duration_since(Instant::now())) {
+            Ok(line) => on_line(&line),
+            Err(RecvTimeoutError::Disconnected) => break false,
+            Err(RecvTimeoutError::Timeout) => break true,
+        }
+    };
+
+    // A process closing its output may still be running
+    let exit_status = if timed_out {
+        None
+    } else {
+        p.wait_timeout(deadline.saturating_duration_since(Instant::now()))?
+    };
+
+    match exit_status {
+        Some(ExitStatus::Exited(code)) => log::debug!("Exit status: {}", code),
+        Some(x) => log::warn!("Process terminated abnormally: {:?}", x),
+        None => {
+            log::warn!("Kill subprocess after {:?}", timeout);
+            p.kill()?;
+            p.wait()?;
+        }
+    }
+
+    Ok(exit_status)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sh(script: &str) -> Vec<String> {
+        vec!["sh".to_string(), "-c".to_string(), script.to_string()]
+    }
+
+    #[test]
+    fn capture_output_and_environment() {
+        let opt = ProcessOpt {
+            env: vec![("PROVOLA_TEST".to_string(), "foo".to_string())],
+            ..Default::default()
+        };
+        let output = run_process(&sh("echo $PROVOLA_TEST; echo bar >&2; exit 1"), &opt).unwrap();
+        assert_eq!(output.out, "foo\n");
+        assert_eq!(output.err, "bar\n");
+        assert_eq!(output.exit_code, Some(1));
+    }
+
+    #[test]
+    fn kill_after_timeout() {
+        let opt = ProcessOpt {
+            timeout: Some(Duration::from_millis(200)),
+            ..Default::default()
+        };
+        let start = std::time::Instant::now();
+        let output = run_process(&sh("echo foo; sleep 10"), &opt).unwrap();
+        assert_eq!(output.out, "foo\n");
+        assert!(start.elapsed() < Duration::from_secs(5));
+    }
+
+    #[test]
+    fn split_quoted_arguments() {
+        let args = split_command_line(r#"ninja -C 'my build' "a \"b\"" c\ d '' tests"#).unwrap();
+        assert_eq!(
+            args,
+            vec!["ninja", "-C", "my build", "a \"b\"", "c d", "", "tests"]
+        );
+        assert!(split_command_line("  ").unwrap().is_empty());
+        assert!(split_command_line("sh -c 'echo").is_err());
+    }
+
+    #[test]
+    fn stream_lines_of_output() {
+        let mut lines = Vec::new();
+        let argv = sh("echo foo; echo bar >&2; exit 2");
+        let opt = ProcessOpt::default();
+        let exit_status = run_process_streaming(&argv, &opt, &mut |x| lines.push(x.to_string()));
+        assert_eq!(exit_status.unwrap(), Some(ExitStatus::Exited(2)));
+        assert_eq!(lines, vec!["foo", "bar"]);
+    }
+
+    #[test]
+    fn kill_silent_process_after_timeout() {
+        let opt = ProcessOpt {
+            timeout: Some(Duration::from_millis(200)),
+            ..Default::default()
+        };
+        let start = std::time::Instant::now();
+        let mut lines = Vec::new();
+        let argv = sh("echo foo; sleep 10");
+        let exit_status = run_process_streaming(&argv, &opt, &mut |x| lines.push(x.to_string()));
+        assert_eq!(exit_status.unwrap(), None);
+        assert_eq!(lines, vec!["foo"]);
+        assert!(start.elapsed() < Duration::from_secs(5));
+    }
+}
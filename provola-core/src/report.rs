@@ -1,6 +1,9 @@
 use serde::{Deserialize, Serialize};
+use std::path::Path;
 
-use crate::test::xunit::FullyQualifiedTestCaseId;
+use crate::coverage::Coverage;
+use crate::test::xunit::{FullyQualifiedTestCase, FullyQualifiedTestCaseId};
+use crate::test_runners::Selection;
 
 pub type Timestamp = chrono::DateTime<chrono::Utc>;
 pub type Duration = std::time::Duration;
@@ -12,9 +15,13 @@ pub type Package = String;
 pub type ClassName = String;
 pub type FailureType = String;
 pub type Message = String;
+pub type FilePath = String;
+pub type Line = usize;
+pub type Output = String;
 
-#[derive(Serialize, Deserialize, Debug, Clone, Copy)]
+#[derive(Serialize, Deserialize, Debug, Default, Clone, Copy)]
 pub enum CoreStatus {
+    #[default]
     Unknown,
     Pass,
     Fail,
@@ -22,12 +29,6 @@ pub enum CoreStatus {
     Skipped,
 }
 
-impl Default for CoreStatus {
-    fn default() -> Self {
-        CoreStatus::Unknown
-    }
-}
-
 impl From<Option<bool>> for CoreStatus {
     fn from(ok: Option<bool>) -> Self {
         match ok {
@@ -52,6 +53,129 @@ pub struct CoreReport {
     /// The time that was required to process all the rules
     pub time: Option<Duration>,
     pub timestamp: Option<Timestamp>,
+    /// How tests have been repeated, if they have been executed many times
+    pub repeat: Option<RepeatSummary>,
+    /// Lines and branches executed by tests, if collected
+    #[serde(default)]
+    pub coverage: Option<Coverage>,
+}
+
+/// How tests have been executed many times
+#[derive(Serialize, Deserialize, Debug, Default, Clone, PartialEq, Eq)]
+pub struct RepeatSummary {
+    /// How many times tests have been executed
+    pub runs: Count,
+    /// Seed used to run tests in random order, if they have been shuffled
+    pub seed: Option<u32>,
+}
+
+/// Results of a test case executed many times
+#[derive(Serialize, Deserialize, Debug, Default, Clone, PartialEq, Eq)]
+pub struct RepeatStats {
+    pub runs: Count,
+    pub passed: Count,
+    pub failed: Count,
+    pub min: Option<Duration>,
+    pub median: Option<Duration>,
+    pub max: Option<Duration>,
+}
+
+impl RepeatStats {
+    /// Statistics of the status and of the time of each run
+    pub fn new(runs: &[(CoreStatus, Option<Duration>)]) -> Self {
+        let count = |status: CoreStatus| {
+            let d = std::mem::discriminant(&status);
+            runs.iter()
+                .filter(|(x, _)| std::mem::discriminant(x) == d)
+                .count()
+        };
+
+        let mut times: Vec<Duration> = runs.iter().filter_map(|(_, x)| *x).collect();
+        times.sort();
+
+        let median = match times.len() {
+            0 => None,
+            n if n % 2 == 1 => Some(times[n / 2]),
+            n => Some((times[n / 2 - 1] + times[n / 2]) / 2),
+        };
+
+        RepeatStats {
+            runs: runs.len(),
+            passed: count(CoreStatus::Pass),
+            failed: count(CoreStatus::Fail),
+            min: times.first().copied(),
+            median,
+            max: times.last().copied(),
+        }
+    }
+
+    /// A flaky test case both passed and failed
+    pub fn is_flaky(&self) -> bool {
+        self.passed > 0 && self.failed > 0
+    }
+
+    /// Ratio of runs which passed, from 0 to 1
+    pub fn pass_rate(&self) -> f32 {
+        match self.runs {
+            0 => 0.0,
+            runs => self.passed as f32 / runs as f32,
+        }
+    }
+}
+
+impl std::fmt::Display for RepeatStats {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{}/{} passed ({:.0}%)",
+            self.passed,
+            self.runs,
+            self.pass_rate() * 100.0
+        )?;
+
+        if let (Some(min), Some(median), Some(max)) = (self.min, self.median, self.max) {
+            write!(f, " | min {:?} median {:?} max {:?}", min, median, max)?;
+        }
+
+        Ok(())
+    }
+}
+
+/// Captured output, without trailing new lines, if anything has been
+/// written. Reports often have empty elements for test cases which wrote
+/// nothing.
+pub fn captured_output(s: Option<String>) -> Option<Output> {
+    s.map(|x| x.trim_end().to_string())
+        .filter(|x| !x.is_empty())
+}
+
+/// Output of both `a` and `b`, one after the other
+pub fn join_outputs(a: Option<Output>, b: Option<Output>) -> Option<Output> {
+    match (a, b) {
+        (Some(a), Some(b)) => Some(format!("{}\n{}", a, b)),
+        (a, b) => a.or(b),
+    }
+}
+
+fn add_counts(x: Option<Count>, y: Option<Count>) -> Option<Count> {
+    match (x, y) {
+        (None, None) => None,
+        (x, y) => Some(x.unwrap_or(0) + y.unwrap_or(0)),
+    }
+}
+
+fn add_durations(x: Option<Duration>, y: Option<Duration>) -> Option<Duration> {
+    match (x, y) {
+        (None, None) => None,
+        (x, y) => Some(x.unwrap_or_default() + y.unwrap_or_default()),
+    }
+}
+
+fn earliest(x: Option<Timestamp>, y: Option<Timestamp>) -> Option<Timestamp> {
+    match (x, y) {
+        (Some(x), Some(y)) => Some(x.min(y)),
+        (x, y) => x.or(y),
+    }
 }
 
 impl CoreReport {
@@ -61,13 +185,152 @@ impl CoreReport {
             test_suite.testcases.sort_by(|x, y| x.name.cmp(&y.name));
         }
     }
+
+    /// Resolve relative paths of source locations from `dir`, the working
+    /// directory of the test runner
+    pub fn resolve_locations(&mut self, dir: &Path) {
+        let test_cases = self
+            .testsuites
+            .iter_mut()
+            .flat_map(|x| x.testcases.iter_mut());
+
+        for test_case in test_cases {
+            let failures = test_case.failures.iter_mut().map(|x| &mut x.location);
+            let locations = std::iter::once(&mut test_case.location).chain(failures);
+
+            for location in locations.flatten() {
+                location.resolve(dir);
+            }
+        }
+    }
+
+    /// Merge another report into this one. Test suites with the same name
+    /// (and package) are merged together.
+    pub fn merge(&mut self, other: CoreReport) {
+        self.disabled = add_counts(self.disabled, other.disabled);
+        self.errors = add_counts(self.errors, other.errors);
+        self.failures = add_counts(self.failures, other.failures);
+        self.tests = add_counts(self.tests, other.tests);
+        self.time = add_durations(self.time, other.time);
+        self.timestamp = earliest(self.timestamp, other.timestamp);
+        self.name = self.name.take().or(other.name);
+
+        self.coverage = match (self.coverage.take(), other.coverage) {
+            (Some(mut a), Some(b)) => {
+                a.merge(b);
+                Some(a)
+            }
+            (a, b) => a.or(b),
+        };
+
+        for test_suite in other.testsuites {
+            let existing = self
+                .testsuites
+                .iter_mut()
+                .find(|x| x.name == test_suite.name && x.package == test_suite.package);
+
+            match existing {
+                Some(existing) => existing.merge(test_suite),
+                None => self.testsuites.push(test_suite),
+            }
+        }
+    }
+
+    /// Add a test case to a test suite, which is created if missing,
+    /// updating counts
+    pub fn push_test_case(&mut self, test_suite: &str, test_case: CoreTestCase) {
+        let failures = Some(usize::from(matches!(test_case.status, CoreStatus::Fail)));
+
+        self.merge(CoreReport {
+            failures,
+            tests: Some(1),
+            testsuites: vec![CoreTestSuite {
+                name: test_suite.to_string(),
+                failures,
+                tests: 1,
+                testcases: vec![test_case],
+                ..Default::default()
+            }],
+            ..Default::default()
+        });
+    }
+
+    /// Keep only the test case with the given id, updating counts. Used by
+    /// test runners which cannot select a single test case to execute.
+    pub fn retain_test_case(&mut self, id: FullyQualifiedTestCaseId) {
+        self.retain(|_, x| x.fqtc == Some(id));
+    }
+
+    /// Keep only the test cases matching a selection, updating counts. Used
+    /// by test runners which cannot filter test cases natively. Indexes are
+    /// positions in this report.
+    pub fn retain_selection(&mut self, selection: &Selection) {
+        let mut index = 0;
+
+        self.retain(|test_suite, x| {
+            let is_match = selection.is_match(index, test_suite, &x.name, x.fqtc);
+            index += 1;
+            is_match
+        });
+    }
+
+    fn retain(&mut self, mut f: impl FnMut(&str, &CoreTestCase) -> bool) {
+        for test_suite in self.testsuites.iter_mut() {
+            let name = &test_suite.name;
+            test_suite.testcases.retain(|x| f(name, x));
+        }
+
+        self.update_counts();
+    }
+
+    /// Count test cases again, after their status has been changed
+    pub fn update_counts(&mut self) {
+        for test_suite in self.testsuites.iter_mut() {
+            let count = |status: CoreStatus| {
+                let d = std::mem::discriminant(&status);
+                test_suite
+                    .testcases
+                    .iter()
+                    .filter(|x| std::mem::discriminant(&x.status) == d)
+                    .count()
+            };
+
+            let failures = count(CoreStatus::Fail);
+            let skipped = count(CoreStatus::Skipped);
+            test_suite.failures = Some(failures);
+            test_suite.skipped = Some(skipped);
+            test_suite.tests = test_suite.testcases.len();
+        }
+
+        // Errors of a test suite are not about its test cases, so they are
+        // kept as they are
+        self.testsuites
+            .retain(|x| x.tests > 0 || x.errors.unwrap_or(0) > 0);
+        self.failures = Some(self.testsuites.iter().filter_map(|x| x.failures).sum());
+        self.tests = Some(self.testsuites.iter().map(|x| x.tests).sum());
+        self.errors = Some(self.testsuites.iter().filter_map(|x| x.errors).sum());
+    }
+}
+
+impl CoreTestSuite {
+    /// Merge another test suite into this one, appending its test cases
+    pub fn merge(&mut self, other: CoreTestSuite) {
+        self.disabled = add_counts(self.disabled, other.disabled);
+        self.errors = add_counts(self.errors, other.errors);
+        self.failures = add_counts(self.failures, other.failures);
+        self.skipped = add_counts(self.skipped, other.skipped);
+        self.tests += other.tests;
+        self.time = add_durations(self.time, other.time);
+        self.timestamp = earliest(self.timestamp, other.timestamp);
+        self.testcases.extend(other.testcases);
+        self.system_out = join_outputs(self.system_out.take(), other.system_out);
+        self.system_err = join_outputs(self.system_err.take(), other.system_err);
+    }
 }
 
 #[derive(Serialize, Deserialize, Debug, Default, Clone)]
 pub struct CoreTestSuite {
     // TODO properties
-    // TODO system-err
-    // TODO system-out
     pub disabled: Option<Count>,
     pub errors: Option<Count>,
     pub failures: Option<Count>,
@@ -75,6 +338,8 @@ pub struct CoreTestSuite {
     pub id: Option<Id>,
     /// The label of the provider
     pub name: Name,
+    /// Group of test suites, like the action running them in a composite
+    /// action
     pub package: Option<Package>,
     pub skipped: Option<Count>,
     pub testcases: Vec<CoreTestCase>,
@@ -83,6 +348,10 @@ pub struct CoreTestSuite {
     /// The time that was required to process the rules in the provider
     pub time: Option<Duration>,
     pub timestamp: Option<Timestamp>,
+    /// Output written to stdout outside of test cases, if captured
+    pub system_out: Option<Output>,
+    /// Output written to stderr outside of test cases, if captured
+    pub system_err: Option<Output>,
 }
 
 #[derive(Serialize, Deserialize, Debug, Default, Clone)]
@@ -92,8 +361,6 @@ pub struct CoreTestCase {
     // TODO error
     // TODO assertions
     // TODO skipped
-    // TODO system-err
-    // TODO system-out
     pub classname: Option<ClassName>,
     /// The label of the rule
     pub name: Name,
@@ -101,10 +368,253 @@ pub struct CoreTestCase {
     /// The time that was required to process all the applications of this rule
     pub time: Option<Duration>,
     pub failures: Vec<CoreFailure>,
+    /// Output written by the test case to stdout, if captured
+    pub system_out: Option<Output>,
+    /// Output written by the test case to stderr, if captured
+    pub system_err: Option<Output>,
+    /// Results of each run, if the test case has been executed many times
+    pub repeat: Option<RepeatStats>,
+    /// Where the test case is defined, if known
+    pub location: Option<SourceLocation>,
+}
+
+impl CoreTestCase {
+    /// Captured output, stdout first, if anything has been written
+    pub fn output(&self) -> Option<Output> {
+        join_outputs(self.system_out.clone(), self.system_err.clone())
+    }
+
+    /// Id of this test case, calculated from its name if the test runner
+    /// has not given it
+    pub fn id(&self, test_suite: &str) -> FullyQualifiedTestCaseId {
+        self.fqtc.unwrap_or_else(|| {
+            FullyQualifiedTestCase::from_test_suite_test_case(test_suite, &self.name).id
+        })
+    }
 }
 
 #[derive(Serialize, Deserialize, Debug, Default, Clone)]
 pub struct CoreFailure {
     pub ttype: FailureType,
     pub message: Message,
+    /// Where the failure happened (e.g. the failed assertion), if known
+    pub location: Option<SourceLocation>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Default, Clone, PartialEq, Eq)]
+pub struct SourceLocation {
+    pub file: FilePath,
+    pub line: Option<Line>,
+}
+
+impl SourceLocation {
+    /// Parse a location like `file:line`, as printed by GoogleTest
+    pub fn parse(s: &str) -> Option<SourceLocation> {
+        let (file, line) = s.trim().rsplit_once(':')?;
+
+        if file.is_empty() || line.is_empty() || !line.chars().all(|x| x.is_ascii_digit()) {
+            return None;
+        }
+
+        Some(SourceLocation {
+            file: file.to_string(),
+            line: line.parse().ok(),
+        })
+    }
+
+    /// Absolute path of the file, relative paths are resolved from the
+    /// working directory
+    pub fn absolute_path(&self) -> std::path::PathBuf {
+        let path = Path::new(&self.file);
+        std::env::current_dir()
+            .map(|x| x.join(path))
+            .unwrap_or_else(|_| path.to_path_buf())
+    }
+
+    /// A `file://` URL of the file, which terminals and editors can open
+    pub fn url(&self) -> String {
+        let path = self.absolute_path().to_string_lossy().replace('\\', "/");
+        let mut url = "file://".to_string();
+
+        // Windows paths, like `C:/foo.c`, are absolute too
+        if !path.starts_with('/') {
+            url.push('/');
+        }
+
+        for byte in path.bytes() {
+            match byte {
+                b'A'..=b'Z'
+                | b'a'..=b'z'
+                | b'0'..=b'9'
+                | b'-'
+                | b'.'
+                | b'_'
+                | b'~'
+                | b'/'
+                | b':' => url.push(byte as char),
+                _ => url.push_str(&format!("%{:02X}", byte)),
+            }
+        }
+
+        url
+    }
+
+    /// Resolve a relative path from `dir`, the working directory of the
+    /// test runner which reported it
+    pub fn resolve(&mut self, dir: &Path) {
+        let path = Path::new(&self.file);
+
+        if path.is_relative() {
+            self.file = dir.join(path).to_string_lossy().to_string();
+        }
+    }
+
+    /// Split a `file:line:` prefix, as printed by compilers and many test
+    /// frameworks, from the rest of a message
+    pub fn split_prefix(s: &str) -> Option<(SourceLocation, &str)> {
+        for (index, _) in s.match_indices(':') {
+            let tail = &s[index + 1..];
+            let digits = tail.chars().take_while(|x| x.is_ascii_digit()).count();
+
+            if digits == 0 || !tail[digits..].starts_with(':') || index == 0 {
+                continue;
+            }
+
+            let location = SourceLocation {
+                file: s[..index].to_string(),
+                line: tail[..digits].parse().ok(),
+            };
+
+            return Some((location, tail[digits + 1..].trim_start()));
+        }
+
+        None
+    }
+}
+
+impl std::fmt::Display for SourceLocation {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self.line {
+            Some(line) => write!(f, "{}:{}", self.file, line),
+            None => write!(f, "{}", self.file),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn make_report(suite: &str, case: &str, failures: Count) -> CoreReport {
+        CoreReport {
+            failures: Some(failures),
+            tests: Some(1),
+            testsuites: vec![CoreTestSuite {
+                name: suite.to_string(),
+                failures: Some(failures),
+                tests: 1,
+                testcases: vec![CoreTestCase {
+                    name: case.to_string(),
+                    ..Default::default()
+                }],
+                ..Default::default()
+            }],
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn merge_reports_with_different_test_suites() {
+        let mut report = make_report("Foo", "Foo1", 1);
+        report.merge(make_report("Bar", "Bar1", 0));
+        assert_eq!(report.testsuites.len(), 2);
+        assert_eq!(report.tests, Some(2));
+        assert_eq!(report.failures, Some(1));
+        assert_eq!(report.errors, None);
+    }
+
+    #[test]
+    fn merge_reports_with_same_test_suite() {
+        let mut report = make_report("Foo", "Foo1", 1);
+        report.merge(make_report("Foo", "Foo2", 1));
+        assert_eq!(report.testsuites.len(), 1);
+        assert_eq!(report.testsuites[0].testcases.len(), 2);
+        assert_eq!(report.testsuites[0].tests, 2);
+        assert_eq!(report.testsuites[0].failures, Some(2));
+        assert_eq!(report.failures, Some(2));
+    }
+
+    #[test]
+    fn retain_test_case_updates_counts() {
+        let mut report = make_report("Foo", "Foo1", 1);
+        report.merge(make_report("Bar", "Bar1", 0));
+        let id =
+            crate::test::xunit::FullyQualifiedTestCase::from_test_suite_test_case("Bar", "Bar1").id;
+        report.testsuites[1].testcases[0].fqtc = Some(id);
+        report.retain_test_case(id);
+        assert_eq!(report.testsuites.len(), 1);
+        assert_eq!(report.tests, Some(1));
+        assert_eq!(report.failures, Some(0));
+    }
+
+    #[test]
+    fn retain_selection_by_name() {
+        let mut report = make_report("Foo", "Foo1", 1);
+        report.merge(make_report("Bar", "Bar1", 0));
+        report.merge(make_report("Bar", "Bar2", 0));
+        let selection = Selection::parse(["Bar.*", "-Bar2"]).unwrap();
+        report.retain_selection(&selection);
+        assert_eq!(report.testsuites.len(), 1);
+        assert_eq!(report.testsuites[0].testcases[0].name, "Bar1");
+        assert_eq!(report.tests, Some(1));
+    }
+
+    #[test]
+    fn split_location_prefix() {
+        let (location, message) =
+            SourceLocation::split_prefix("C:/foo.c:42: error: 1 != 2").unwrap();
+        assert_eq!(location.file, "C:/foo.c");
+        assert_eq!(location.line, Some(42));
+        assert_eq!(message, "error: 1 != 2");
+    }
+
+    #[test]
+    fn parse_location() {
+        let location = SourceLocation::parse("/src/tests.cpp:4").unwrap();
+        assert_eq!(location.file, "/src/tests.cpp");
+        assert_eq!(location.line, Some(4));
+        assert_eq!(location.url(), "file:///src/tests.cpp");
+        assert!(SourceLocation::parse("Value of: true").is_none());
+        assert!(SourceLocation::parse("tests.cpp:").is_none());
+    }
+
+    #[test]
+    fn url_of_location() {
+        let location = SourceLocation::parse("/src/my tests#1.cpp:4").unwrap();
+        assert_eq!(location.url(), "file:///src/my%20tests%231.cpp");
+    }
+
+    #[test]
+    fn resolve_relative_locations() {
+        let mut report = make_report("Foo", "Foo1", 1);
+        report.testsuites[0].testcases[0].failures = vec![CoreFailure {
+            location: SourceLocation::parse("src/tests.cpp:4"),
+            ..Default::default()
+        }];
+        report.testsuites[0].testcases[0].location = SourceLocation::parse("/src/tests.cpp:2");
+        report.resolve_locations(Path::new("build"));
+
+        let test_case = &report.testsuites[0].testcases[0];
+        let failure_location = test_case.failures[0].location.as_ref().unwrap();
+        assert_eq!(
+            Path::new(&failure_location.file),
+            Path::new("build/src/tests.cpp")
+        );
+        assert_eq!(test_case.location.as_ref().unwrap().file, "/src/tests.cpp");
+    }
+
+    #[test]
+    fn split_location_prefix_without_line() {
+        assert!(SourceLocation::split_prefix("foo.c: error").is_none());
+    }
 }
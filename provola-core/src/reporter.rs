@@ -1,4 +1,4 @@
-use crate::TestResult;
+use crate::{TestEvent, TestResult};
 
 #[derive(thiserror::Error, Debug)]
 pub enum Error {
@@ -7,5 +7,11 @@ pub enum Error {
 }
 
 pub trait Reporter {
+    /// Called for each event while tests are running, before the result
+    /// is reported
+    fn event(&self, _event: &TestEvent) -> Result<(), Error> {
+        Ok(())
+    }
+
     fn report(&self, result: TestResult) -> Result<(), Error>;
 }
@@ -1,14 +1,19 @@
 use std::fmt::Display;
 
-use crate::CoreReport;
+use serde::{Deserialize, Serialize};
 
-#[derive(Debug, Clone)]
+use crate::report::CoreStatus;
+use crate::{CoreFailure, CoreReport, CoreTestCase};
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "outcome", content = "reason")]
 pub enum TestResult {
     Pass(Reason),
     Fail(Reason),
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "kind", content = "content")]
 pub enum Reason {
     Unknown,
     Generic(String),
@@ -34,11 +39,60 @@ impl Reason {
     }
 }
 
+impl TestResult {
+    /// The report of the test runner, if this result comes from it
+    pub fn report(&self) -> Option<&CoreReport> {
+        match self {
+            TestResult::Pass(Reason::Report(report)) | TestResult::Fail(Reason::Report(report)) => {
+                Some(report)
+            }
+            _ => None,
+        }
+    }
+
+    /// The report of this result. A result without a report (e.g. of a data
+    /// test) becomes a report with a single test case, named `name`.
+    pub fn into_report(self, name: &str) -> CoreReport {
+        let (status, reason) = match self {
+            TestResult::Pass(reason) => (CoreStatus::Pass, reason),
+            TestResult::Fail(reason) => (CoreStatus::Fail, reason),
+        };
+
+        let message = match reason {
+            Reason::Report(report) => return report,
+            Reason::Unknown => String::default(),
+            Reason::Generic(description) => description,
+            Reason::NotExpected { actual, expected } => {
+                format!("Expected\n\n{}\n\nActual\n\n{}", expected, actual)
+            }
+        };
+
+        let failures = match status {
+            CoreStatus::Fail => vec![CoreFailure {
+                message,
+                ..Default::default()
+            }],
+            _ => Vec::default(),
+        };
+
+        let mut report = CoreReport::default();
+        let test_case = CoreTestCase {
+            name: name.to_string(),
+            status,
+            failures,
+            ..Default::default()
+        };
+        report.push_test_case(name, test_case);
+        report
+    }
+}
+
 impl From<CoreReport> for TestResult {
     fn from(x: CoreReport) -> Self {
         let failures = x.failures.unwrap_or(0);
+        let errors = x.errors.unwrap_or(0);
         let reason = Reason::from_report(x);
-        if failures == 0 {
+        if failures == 0 && errors == 0 {
             TestResult::Pass(reason)
         } else {
             TestResult::Fail(reason)
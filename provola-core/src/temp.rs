@@ -0,0 +1,115 @@
+use crate::Error;
+use std::ops::Deref;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+/// Temporary paths created by this process, so each one is unique
+static COUNTER: AtomicUsize = AtomicUsize::new(0);
+
+/// A name like `provola_googletest_1234_0`, unique for each call, for
+/// files written by test runners
+pub fn unique_name(prefix: &str) -> String {
+    let n = COUNTER.fetch_add(1, Ordering::Relaxed);
+    format!("provola_{}_{}_{}", prefix, std::process::id(), n)
+}
+
+/// A temporary file, or directory, where a test runner writes its report.
+/// Its path is unique for each run, so runs at the same time (e.g. shards,
+/// or the GUI and the CLI) do not overwrite each other. It is removed when
+/// dropped.
+#[derive(Debug)]
+pub struct TempReport {
+    path: PathBuf,
+}
+
+impl TempReport {
+    /// A file like `provola_googletest_1234_0.json`, in the temporary
+    /// directory. A file left there by a previous process with the same id
+    /// is removed, so it cannot be mistaken for the report of this run.
+    pub fn new(prefix: &str, extension: &str) -> Self {
+        let mut file_name = unique_name(prefix);
+
+        if !extension.is_empty() {
+            file_name.push('.');
+            file_name.push_str(extension);
+        }
+
+        let report = Self {
+            path: std::env::temp_dir().join(file_name),
+        };
+        report.remove();
+        report
+    }
+
+    /// An empty directory, for test runners writing many files
+    pub fn dir(prefix: &str) -> Result<Self, Error> {
+        let report = Self::new(prefix, "");
+        std::fs::create_dir_all(&report.path)?;
+        Ok(report)
+    }
+
+    /// Content of the report. If it is missing, the test runner crashed
+    /// before writing it.
+    pub fn read_to_string(&self) -> Result<String, Error> {
+        std::fs::read_to_string(&self.path).map_err(|e| match e.kind() {
+            std::io::ErrorKind::NotFound => Error::TestRunnerCrashed(self.path.clone()),
+            _ => Error::IoError(e),
+        })
+    }
+
+    fn remove(&self) {
+        let _ = match self.path.is_dir() {
+            true => std::fs::remove_dir_all(&self.path),
+            false => std::fs::remove_file(&self.path),
+        };
+    }
+}
+
+impl Deref for TempReport {
+    type Target = Path;
+
+    fn deref(&self) -> &Path {
+        &self.path
+    }
+}
+
+impl Drop for TempReport {
+    fn drop(&mut self) {
+        self.remove();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn unique_paths_removed_when_dropped() {
+        let a = TempReport::new("test", "json");
+        let b = TempReport::new("test", "json");
+        assert_ne!(*a, *b);
+        assert_eq!(a.extension().unwrap(), "json");
+
+        std::fs::write(&*a, "{}").unwrap();
+        assert_eq!(a.read_to_string().unwrap(), "{}");
+
+        let path = a.to_path_buf();
+        drop(a);
+        assert!(!path.exists());
+    }
+
+    #[test]
+    fn missing_report_is_a_crash() {
+        let report = TempReport::new("test", "xml");
+        assert!(matches!(
+            report.read_to_string(),
+            Err(Error::TestRunnerCrashed(_))
+        ));
+
+        let dir = TempReport::dir("test").unwrap();
+        std::fs::write(dir.join("a.xml"), "").unwrap();
+        let path = dir.to_path_buf();
+        drop(dir);
+        assert!(!path.exists());
+    }
+}
@@ -0,0 +1,72 @@
+use std::path::Path;
+
+/// Test frameworks whose executables can be recognized without running them
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Framework {
+    GoogleTest,
+    Doctest,
+    Catch2,
+    BoostTest,
+    CppUTest,
+    CMocka,
+    Unity,
+}
+
+/// Strings found in executables linked to a test framework, usually the
+/// name of a command line option or of an environment variable. When many
+/// markers are found, the first one wins.
+const MARKERS: &[(Framework, &[u8])] = &[
+    (Framework::GoogleTest, b"--gtest_list_tests"),
+    // doctest must be checked before Catch2, because it has similar options
+    (Framework::Doctest, b"[doctest]"),
+    // Catch2 v2 and v3
+    (Framework::Catch2, b"--list-test-names-only"),
+    (Framework::Catch2, b"--list-reporters"),
+    (Framework::BoostTest, b"BOOST_TEST_LOG_LEVEL"),
+    (Framework::CppUTest, b"cpputest_"),
+    (Framework::CMocka, b"CMOCKA_MESSAGE_OUTPUT"),
+    (Framework::Unity, b"UnityBegin"),
+];
+
+fn contains(haystack: &[u8], needle: &[u8]) -> bool {
+    haystack.windows(needle.len()).any(|x| x == needle)
+}
+
+/// The test framework an executable is linked to, from the strings it
+/// embeds. The executable is not run.
+pub fn detect_framework(path: &Path) -> Option<Framework> {
+    let data = std::fs::read(path).ok()?;
+
+    MARKERS
+        .iter()
+        .find(|(_, marker)| contains(&data, marker))
+        .map(|(framework, _)| *framework)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn detect(name: &str, data: &[u8]) -> Option<Framework> {
+        let path = std::env::temp_dir().join(name);
+        std::fs::write(&path, data).unwrap();
+        let framework = detect_framework(&path);
+        std::fs::remove_file(&path).unwrap();
+        framework
+    }
+
+    #[test]
+    fn detect_from_executable_strings() {
+        let framework = detect("provola_framework_gtest", b"\x7fELF\0--gtest_list_tests\0");
+        assert_eq!(framework, Some(Framework::GoogleTest));
+        let framework = detect("provola_framework_catch2", b"\x7fELF\0--list-reporters\0");
+        assert_eq!(framework, Some(Framework::Catch2));
+        let framework = detect(
+            "provola_framework_doctest",
+            b"\x7fELF\0[doctest]\0--list-reporters\0",
+        );
+        assert_eq!(framework, Some(Framework::Doctest));
+        let framework = detect("provola_framework_unknown", b"\x7fELF\0--help\0");
+        assert_eq!(framework, None);
+    }
+}
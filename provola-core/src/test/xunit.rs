@@ -3,17 +3,17 @@ use std::collections::BTreeMap;
 use std::hash::{Hash, Hasher};
 use std::iter::Enumerate;
 
-use serde::{Deserialize, Serialize};
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
 
 use crate::{CoreReport, CoreTestCase, CoreTestSuite};
 
-#[derive(Debug, Hash, PartialEq, Eq, PartialOrd, Ord, Clone)]
+#[derive(Debug, Hash, PartialEq, Eq, PartialOrd, Ord, Clone, Serialize, Deserialize)]
 pub struct TestSuite(pub String);
 
-#[derive(Debug, Hash, Clone)]
+#[derive(Debug, Hash, Clone, Serialize, Deserialize)]
 pub struct TestCase(pub String);
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct FullyQualifiedTestCase {
     pub test_suite: TestSuite,
     pub test_case: TestCase,
@@ -65,15 +65,57 @@ impl std::fmt::Display for FullyQualifiedTestCase {
     }
 }
 
-#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
 pub struct FullyQualifiedTestCaseId(u64);
 
+/// Written as printed, since JSON readers may lose the precision of large
+/// numbers (e.g. JavaScript ones, above 2^53)
+impl Serialize for FullyQualifiedTestCaseId {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(self)
+    }
+}
+
+/// Numbers are read too, as written by previous versions
+impl<'de> Deserialize<'de> for FullyQualifiedTestCaseId {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        #[derive(Deserialize)]
+        #[serde(untagged)]
+        enum Id {
+            String(String),
+            Number(u64),
+        }
+
+        match Id::deserialize(deserializer)? {
+            Id::String(s) => s.parse().map_err(serde::de::Error::custom),
+            Id::Number(x) => Ok(FullyQualifiedTestCaseId(x)),
+        }
+    }
+}
+
 impl std::fmt::Display for FullyQualifiedTestCaseId {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         write!(f, "{:016X}", self.0)
     }
 }
 
+/// Parse an id as printed, 16 hexadecimal digits
+impl std::str::FromStr for FullyQualifiedTestCaseId {
+    type Err = crate::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let invalid = || crate::Error::InvalidTestPattern(s.to_string());
+
+        if s.len() != 16 {
+            return Err(invalid());
+        }
+
+        u64::from_str_radix(s, 16)
+            .map(FullyQualifiedTestCaseId)
+            .map_err(|_| invalid())
+    }
+}
+
 #[derive(Default, Debug)]
 pub struct AvailableTests {
     list: Vec<FullyQualifiedTestCase>,
@@ -87,7 +129,7 @@ impl AvailableTests {
         self.list.push(fqtc.clone());
         self.map
             .entry(fqtc.test_suite.clone())
-            .or_insert_with(Vec::default)
+            .or_default()
             .push(fqtc);
     }
 
@@ -99,7 +141,7 @@ impl AvailableTests {
         self.list.len()
     }
 
-    pub fn iter(&self) -> std::slice::Iter<FullyQualifiedTestCase> {
+    pub fn iter(&self) -> std::slice::Iter<'_, FullyQualifiedTestCase> {
         self.list.iter()
     }
 
@@ -117,7 +159,7 @@ impl AvailableTests {
 
     pub fn test_suites(
         &self,
-    ) -> std::collections::btree_map::Iter<TestSuite, Vec<FullyQualifiedTestCase>> {
+    ) -> std::collections::btree_map::Iter<'_, TestSuite, Vec<FullyQualifiedTestCase>> {
         self.map.iter()
     }
 }
@@ -153,6 +195,20 @@ impl From<&AvailableTests> for CoreReport {
     }
 }
 
+impl From<&CoreReport> for AvailableTests {
+    fn from(report: &CoreReport) -> Self {
+        let mut available_tests = AvailableTests::default();
+
+        for test_suite in report.testsuites.iter() {
+            for test_case in test_suite.testcases.iter() {
+                available_tests.push(&test_suite.name, &test_case.name);
+            }
+        }
+
+        available_tests
+    }
+}
+
 #[cfg(test)]
 mod test {
     use super::*;
@@ -166,6 +222,17 @@ mod test {
         assert_eq!(id.to_string(), "48730E17FEB9A107");
     }
 
+    #[test]
+    fn id_as_string() {
+        let fqtc = FullyQualifiedTestCase::from_test_suite_test_case("foo", "bar");
+        let s = serde_json::to_string(&fqtc.id).unwrap();
+        assert_eq!(s, "\"48730E17FEB9A107\"");
+        let id: FullyQualifiedTestCaseId = serde_json::from_str(&s).unwrap();
+        assert_eq!(id, fqtc.id);
+        let id: FullyQualifiedTestCaseId = serde_json::from_str("5220531889273151751").unwrap();
+        assert_eq!(id, fqtc.id);
+    }
+
     #[test]
     fn push_test_suite_test_case() {
         let mut available_tests = AvailableTests::default();
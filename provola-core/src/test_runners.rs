@@ -1,13 +1,72 @@
-use crate::test::xunit::{AvailableTests, FullyQualifiedTestCaseId};
-use crate::{Error, TestResult};
+use crate::test::xunit::{AvailableTests, FullyQualifiedTestCase, FullyQualifiedTestCaseId};
+use crate::{Error, TestEvent, TestResult};
 use serde::{Deserialize, Serialize};
+use std::path::PathBuf;
 use strum_macros::Display;
 
-pub trait TestRunner {
+mod build;
+mod coverage;
+mod last_failed;
+mod repeat;
+mod selection;
+
+pub use build::{make_build_failed_report, parse_diagnostics, BuildCommand, BuildTestRunner};
+pub use coverage::{CoverageOpt, CoverageTestRunner, CoverageTool};
+pub use last_failed::{LastFailed, RerunFailed, RerunFailedTestRunner};
+pub use repeat::{run_repeatedly, Repeat, Repetitions};
+pub use selection::{glob_match, Pattern, Selection};
+
+/// Where what is remembered between runs is saved, relative to the working
+/// directory, like `.provola/last_failed-<hash>.json`. Each test runner,
+/// identified by `key` (e.g. its command), has its own file, so actions of
+/// a session do not overwrite each other.
+pub fn state_path(name: &str, key: &str) -> PathBuf {
+    PathBuf::from(".provola").join(format!("{}-{:016X}.json", name, fnv1a(key)))
+}
+
+/// 64 bit FNV-1a hash. Unlike the hasher of the standard library, it gives
+/// the same result with every Rust release, so state files are found again.
+fn fnv1a(s: &str) -> u64 {
+    s.bytes().fold(0xcbf2_9ce4_8422_2325, |hash, x| {
+        (hash ^ x as u64).wrapping_mul(0x0000_0100_0000_01b3)
+    })
+}
+
+pub trait TestRunner: Send + Sync {
     fn run(&self, _opt: &TestRunnerOpt) -> Result<TestResult, Error> {
         Err(Error::TestRunnerFeatureNotAvailable(TestRunnerFeature::Run))
     }
 
+    /// Run tests, emitting events while they are running. Test runners which
+    /// cannot stream results emit them all when tests are finished.
+    fn run_with_events(
+        &self,
+        opt: &TestRunnerOpt,
+        events: &mut dyn FnMut(TestEvent),
+    ) -> Result<TestResult, Error> {
+        events(TestEvent::RunStarted(None));
+
+        let result = self.run(opt);
+
+        if let Some(report) = result.as_ref().ok().and_then(|x| x.report()) {
+            TestEvent::from_report(report).for_each(&mut *events);
+        }
+
+        events(TestEvent::RunFinished);
+        result
+    }
+
+    /// Run tests many times, collecting statistics of each test case. Test
+    /// runners which can repeat tests natively run them all at once.
+    fn run_repeated(
+        &self,
+        opt: &TestRunnerOpt,
+        repeat: &Repeat,
+        events: &mut dyn FnMut(TestEvent),
+    ) -> Result<TestResult, Error> {
+        run_repeatedly(self, opt, repeat, events)
+    }
+
     fn list(&self, _opt: &TestRunnerOpt) -> Result<AvailableTests, Error> {
         Err(Error::TestRunnerFeatureNotAvailable(
             TestRunnerFeature::List,
@@ -24,17 +83,52 @@ pub enum TestRunnerFeature {
 #[derive(Default, Clone, Debug, Deserialize, Serialize, PartialEq, Eq)]
 pub struct TestRunnerOpt {
     pub only: Only,
+    /// Run tests many times, instead of once
+    #[serde(default)]
+    pub repeat: Option<Repeat>,
 }
 
-#[derive(Clone, Debug, Deserialize, Serialize, PartialEq, Eq)]
+#[derive(Default, Clone, Debug, Deserialize, Serialize, PartialEq, Eq)]
 pub enum Only {
     SingleByIndex(usize),
     SingleByFqtc(FullyQualifiedTestCaseId),
+    Selection(Selection),
+    #[default]
     All,
 }
 
-impl Default for Only {
-    fn default() -> Self {
-        Only::All
+impl Only {
+    /// Selection equivalent to this option, `None` if all tests are selected
+    pub fn selection(&self) -> Option<Selection> {
+        match self {
+            Only::SingleByIndex(index) => Some(Selection {
+                include: vec![Pattern::Index(*index)],
+                exclude: vec![],
+            }),
+            Only::SingleByFqtc(id) => Some(Selection::from_ids([*id])),
+            Only::Selection(selection) => Some(selection.clone()),
+            Only::All => None,
+        }
+    }
+
+    /// Test cases selected among the available ones, which are generated
+    /// only if needed. Returns `None` if all tests are selected.
+    pub fn find_selected(
+        &self,
+        generate_available_tests: impl FnOnce() -> Result<AvailableTests, Error>,
+    ) -> Result<Option<Vec<FullyQualifiedTestCase>>, Error> {
+        let selection = match self.selection() {
+            Some(selection) => selection,
+            None => return Ok(None),
+        };
+
+        let tests = generate_available_tests()?;
+        let selected: Vec<_> = selection.filter(&tests).into_iter().cloned().collect();
+
+        if selected.is_empty() {
+            Err(Error::NothingToDo)
+        } else {
+            Ok(Some(selected))
+        }
     }
 }
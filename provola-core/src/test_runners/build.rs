@@ -0,0 +1,366 @@
+use super::{Repeat, TestRunner, TestRunnerOpt};
+use crate::report::{CoreStatus, SourceLocation};
+use crate::test::xunit::{AvailableTests, FullyQualifiedTestCase};
+use crate::{
+    run_process, split_command_line, CoreFailure, CoreReport, CoreTestCase, Error, ProcessOpt,
+    TestEvent, TestResult,
+};
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+
+/// Name of the test case used to report a build failure
+pub const BUILD_FAILED: &str = "[build failed]";
+
+/// How the test executable is built from its sources
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize, Serialize)]
+pub enum BuildCommand {
+    /// Any command, like `ninja -C build tests`
+    Command(Vec<String>),
+    /// A CMake build directory, built with `cmake --build`
+    CMake(PathBuf),
+    /// A Cargo project directory, built with `cargo build --tests`
+    Cargo(PathBuf),
+    /// A directory with a Makefile, built with `make`
+    Make(PathBuf),
+}
+
+impl BuildCommand {
+    /// A command line, split in arguments like a shell does
+    pub fn parse(s: &str) -> Result<Self, Error> {
+        let argv = split_command_line(s)?;
+
+        if argv.is_empty() {
+            return Err(Error::GenericError("Empty build command".to_string()));
+        }
+
+        Ok(BuildCommand::Command(argv))
+    }
+
+    /// Build of a project, detected from the files in its directory
+    pub fn detect(dir: &Path) -> Option<Self> {
+        let dir = dir.to_path_buf();
+
+        if dir.join("CMakeCache.txt").exists() {
+            Some(BuildCommand::CMake(dir))
+        } else if dir.join("Cargo.toml").exists() {
+            Some(BuildCommand::Cargo(dir))
+        } else if dir.join("Makefile").exists() {
+            Some(BuildCommand::Make(dir))
+        } else {
+            None
+        }
+    }
+
+    /// Arguments and working directory of the build. Output is piped, so
+    /// compilers print diagnostics without colors.
+    fn argv(&self) -> (Vec<String>, Option<PathBuf>) {
+        let dir_arg = |x: &Path| x.to_string_lossy().to_string();
+
+        match self {
+            BuildCommand::Command(argv) => (argv.clone(), None),
+            BuildCommand::CMake(dir) => (
+                vec!["cmake".to_string(), "--build".to_string(), dir_arg(dir)],
+                None,
+            ),
+            BuildCommand::Cargo(dir) => {
+                let argv = ["cargo", "build", "--tests", "--color=never"];
+                (argv.map(String::from).to_vec(), Some(dir.clone()))
+            }
+            BuildCommand::Make(dir) => (
+                vec!["make".to_string(), "-C".to_string(), dir_arg(dir)],
+                None,
+            ),
+        }
+    }
+
+    /// Directory containing the sources, which must be watched for changes
+    pub fn source_dir(&self) -> PathBuf {
+        match self {
+            BuildCommand::Command(_) => PathBuf::from("."),
+            BuildCommand::CMake(dir) => cmake_home_directory(dir).unwrap_or_else(|| dir.clone()),
+            BuildCommand::Cargo(dir) | BuildCommand::Make(dir) => dir.clone(),
+        }
+    }
+
+    /// Directories written by the build, which may be inside the sources.
+    /// Where a plain command or `make` write is not known.
+    pub fn output_dirs(&self) -> Vec<PathBuf> {
+        match self {
+            BuildCommand::Command(_) | BuildCommand::Make(_) => vec![],
+            BuildCommand::CMake(dir) => vec![dir.clone()],
+            BuildCommand::Cargo(dir) => vec![dir.join("target")],
+        }
+    }
+
+    /// Run the build, with additional environment variables. If it fails,
+    /// the result is a report with its diagnostics.
+    pub fn run(&self, env: &[(String, String)]) -> Result<Option<CoreReport>, Error> {
+        let (argv, cwd) = self.argv();
+        log::info!("Running {}", self);
+
+        let opt = ProcessOpt {
+            cwd,
+            env: env.to_vec(),
+            ..Default::default()
+        };
+        let output = run_process(&argv, &opt)?;
+
+        let message = match output.exit_code {
+            Some(0) => return Ok(None),
+            Some(code) => format!("Build failed with exit code {}", code),
+            None => "Build killed after the timeout".to_string(),
+        };
+
+        let s = output.out + &output.err;
+        Ok(Some(make_build_failed_report(&message, &s)))
+    }
+}
+
+impl std::fmt::Display for BuildCommand {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            BuildCommand::Command(argv) => write!(f, "{}", argv.join(" ")),
+            BuildCommand::CMake(dir) => write!(f, "cmake --build {}", dir.display()),
+            BuildCommand::Cargo(dir) => write!(f, "cargo build --tests in {}", dir.display()),
+            BuildCommand::Make(dir) => write!(f, "make -C {}", dir.display()),
+        }
+    }
+}
+
+/// Source directory of a CMake build directory, as written in its cache
+fn cmake_home_directory(build_dir: &Path) -> Option<PathBuf> {
+    let cache = std::fs::read_to_string(build_dir.join("CMakeCache.txt")).ok()?;
+    cache
+        .lines()
+        .find_map(|x| x.strip_prefix("CMAKE_HOME_DIRECTORY:INTERNAL="))
+        .map(PathBuf::from)
+}
+
+/// Split an optional column, like `12: `, from the start of a message
+fn skip_column(s: &str) -> &str {
+    let digits = s.chars().take_while(|x| x.is_ascii_digit()).count();
+
+    match s[digits..].strip_prefix(':') {
+        Some(rest) if digits > 0 => rest.trim_start(),
+        _ => s,
+    }
+}
+
+fn make_error(message: &str, location: SourceLocation) -> CoreFailure {
+    CoreFailure {
+        ttype: "error".to_string(),
+        message: message.to_string(),
+        location: Some(location),
+    }
+}
+
+/// Errors printed by compilers, like GCC and Clang (`file:line:col: error:
+/// message`) or rustc (`error: message`, followed by ` --> file:line:col`).
+/// Warnings and notes are ignored.
+pub fn parse_diagnostics(s: &str) -> Vec<CoreFailure> {
+    let mut errors = Vec::new();
+    // A rustc error, waiting for its location
+    let mut pending: Option<&str> = None;
+
+    for line in s.lines() {
+        // Like `error[E0425]: cannot find value`, but not a file name like
+        // `error_handler.c`
+        if let Some(message) = line.strip_prefix("error") {
+            if message.starts_with([':', '[']) {
+                pending = message.split_once(": ").map(|(_, x)| x);
+                continue;
+            }
+        }
+
+        if let Some(location) = line.trim_start().strip_prefix("--> ") {
+            let location = SourceLocation::split_prefix(location);
+
+            if let (Some(message), Some((location, _))) = (pending.take(), location) {
+                errors.push(make_error(message, location));
+            }
+
+            continue;
+        }
+
+        if let Some((location, rest)) = SourceLocation::split_prefix(line) {
+            let rest = skip_column(rest);
+            let message = rest
+                .strip_prefix("error: ")
+                .or_else(|| rest.strip_prefix("fatal error: "));
+
+            if let Some(message) = message {
+                errors.push(make_error(message, location));
+            }
+        }
+    }
+
+    errors
+}
+
+/// Report of a failed build, with a test case failed with its diagnostics
+pub fn make_build_failed_report(message: &str, output: &str) -> CoreReport {
+    let mut failures = parse_diagnostics(output);
+
+    if failures.is_empty() {
+        failures.push(CoreFailure {
+            ttype: "error".to_string(),
+            message: message.to_string(),
+            location: None,
+        });
+    }
+
+    let fqtc = FullyQualifiedTestCase::from_test_suite_test_case("build", BUILD_FAILED);
+    let test_case = CoreTestCase {
+        fqtc: Some(fqtc.id),
+        name: BUILD_FAILED.to_string(),
+        status: CoreStatus::Fail,
+        failures,
+        system_out: crate::report::captured_output(Some(output.to_string())),
+        ..Default::default()
+    };
+
+    let mut report = CoreReport {
+        name: Some("build".to_string()),
+        ..Default::default()
+    };
+    report.push_test_case("build", test_case);
+    report
+}
+
+type MakeTestRunner = Box<dyn Fn() -> Result<Box<dyn TestRunner>, Error> + Send + Sync>;
+
+/// Build the test executable before running tests. The test runner is made
+/// only when the build succeeds, because many test runners need the
+/// executable to exist (e.g. to list available tests).
+pub struct BuildTestRunner {
+    build: BuildCommand,
+    make_inner: MakeTestRunner,
+    /// Environment variables of the build, like `CFLAGS`
+    env: Vec<(String, String)>,
+}
+
+impl BuildTestRunner {
+    pub fn new(
+        build: BuildCommand,
+        make_inner: impl Fn() -> Result<Box<dyn TestRunner>, Error> + Send + Sync + 'static,
+    ) -> Self {
+        Self {
+            build,
+            make_inner: Box::new(make_inner),
+            env: Vec::new(),
+        }
+    }
+
+    pub fn with_env(mut self, env: Vec<(String, String)>) -> Self {
+        self.env = env;
+        self
+    }
+
+    /// The test runner, if the build succeeds, or the report of the failed
+    /// build
+    fn build(&self) -> Result<Result<Box<dyn TestRunner>, CoreReport>, Error> {
+        match self.build.run(&self.env)? {
+            Some(report) => Ok(Err(report)),
+            None => Ok(Ok((self.make_inner)()?)),
+        }
+    }
+}
+
+impl TestRunner for BuildTestRunner {
+    fn run(&self, opt: &TestRunnerOpt) -> Result<TestResult, Error> {
+        self.run_with_events(opt, &mut |_| {})
+    }
+
+    fn run_with_events(
+        &self,
+        opt: &TestRunnerOpt,
+        events: &mut dyn FnMut(TestEvent),
+    ) -> Result<TestResult, Error> {
+        match self.build()? {
+            Ok(inner) => inner.run_with_events(opt, events),
+            Err(report) => Ok(report.into()),
+        }
+    }
+
+    fn run_repeated(
+        &self,
+        opt: &TestRunnerOpt,
+        repeat: &Repeat,
+        events: &mut dyn FnMut(TestEvent),
+    ) -> Result<TestResult, Error> {
+        match self.build()? {
+            Ok(inner) => inner.run_repeated(opt, repeat, events),
+            Err(report) => Ok(report.into()),
+        }
+    }
+
+    fn list(&self, opt: &TestRunnerOpt) -> Result<AvailableTests, Error> {
+        match self.build()? {
+            Ok(inner) => inner.list(opt),
+            Err(_) => Err(Error::BuildFailed(self.build.to_string())),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_compiler_errors() {
+        let s = r#"[ 50%] Building CXX object CMakeFiles/tests.dir/src/tests.cpp.o
+/src/tests.cpp: In function 'void foo()':
+/src/tests.cpp:4:5: error: 'bar' was not declared in this scope
+    4 |     bar();
+      |     ^~~
+/src/tests.cpp:8:1: warning: no return statement
+src/foo.c:12: fatal error: foo.h: No such file or directory
+error_handler.c:4:5: error: 'x' undeclared
+error[E0425]: cannot find value `x` in this scope
+ --> src/lib.rs:2:5
+  |
+2 |     x
+  |     ^ not found in this scope
+error: could not compile `foo` due to previous error"#;
+
+        let errors = parse_diagnostics(s);
+        assert_eq!(errors.len(), 4);
+        assert_eq!(errors[0].message, "'bar' was not declared in this scope");
+        assert_eq!(
+            errors[0].location.as_ref().unwrap().to_string(),
+            "/src/tests.cpp:4"
+        );
+        assert_eq!(errors[1].message, "foo.h: No such file or directory");
+        assert_eq!(errors[1].location.as_ref().unwrap().line, Some(12));
+        assert_eq!(errors[2].message, "'x' undeclared");
+        assert_eq!(errors[2].location.as_ref().unwrap().file, "error_handler.c");
+        assert_eq!(errors[3].message, "cannot find value `x` in this scope");
+        assert_eq!(errors[3].location.as_ref().unwrap().file, "src/lib.rs");
+    }
+
+    #[test]
+    fn output_dirs_of_build() {
+        let cargo = BuildCommand::Cargo(PathBuf::from("."));
+        assert_eq!(cargo.source_dir(), PathBuf::from("."));
+        assert_eq!(cargo.output_dirs(), vec![PathBuf::from("./target")]);
+        let make = BuildCommand::Make(PathBuf::from("."));
+        assert!(make.output_dirs().is_empty());
+    }
+
+    #[test]
+    fn failed_build_is_failed_result() {
+        let build = BuildCommand::parse("sh -c false").unwrap();
+        let runner = BuildTestRunner::new(build, || unreachable!());
+        let result = runner.run(&TestRunnerOpt::default()).unwrap();
+        assert!(matches!(result, TestResult::Fail(_)));
+
+        let report = result.report().unwrap();
+        let test_case = &report.testsuites[0].testcases[0];
+        assert_eq!(test_case.name, BUILD_FAILED);
+        assert_eq!(
+            test_case.failures[0].message,
+            "Build failed with exit code 1"
+        );
+        assert!(runner.list(&TestRunnerOpt::default()).is_err());
+    }
+}
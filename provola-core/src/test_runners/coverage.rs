@@ -0,0 +1,434 @@
+use super::{state_path, BuildCommand, Repeat, TestRunner, TestRunnerOpt};
+use crate::coverage::{Coverage, CoverageSummary};
+use crate::test::xunit::AvailableTests;
+use crate::{Error, Executable, TempReport, TestEvent, TestResult};
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use strum_macros::{Display, EnumString};
+
+/// How the test executable is instrumented, and how its coverage is read
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Display, EnumString, Deserialize, Serialize)]
+pub enum CoverageTool {
+    /// Clang (`-fprofile-instr-generate -fcoverage-mapping`) and rustc (`-C
+    /// instrument-coverage`), read with llvm-profdata and llvm-cov
+    Llvm,
+    /// GCC (`--coverage`), read with gcov
+    Gcov,
+}
+
+impl CoverageTool {
+    /// Environment of the build, with the flags instrumenting the executable.
+    /// They are used by Make and Cargo, while CMake reads them only when the
+    /// build directory is configured.
+    pub fn build_env(&self) -> Vec<(String, String)> {
+        let vars: &[(&str, &str)] = match self {
+            CoverageTool::Llvm => &[
+                ("CFLAGS", "-fprofile-instr-generate -fcoverage-mapping"),
+                ("CXXFLAGS", "-fprofile-instr-generate -fcoverage-mapping"),
+                ("LDFLAGS", "-fprofile-instr-generate"),
+                ("RUSTFLAGS", "-C instrument-coverage"),
+            ],
+            CoverageTool::Gcov => &[
+                ("CFLAGS", "--coverage"),
+                ("CXXFLAGS", "--coverage"),
+                ("LDFLAGS", "--coverage"),
+            ],
+        };
+
+        vars.iter()
+            .map(|(k, v)| (k.to_string(), v.to_string()))
+            .collect()
+    }
+
+    /// Compiler flags, any of which instruments C and C++ code
+    fn compiler_flags(&self) -> &'static [&'static str] {
+        match self {
+            CoverageTool::Llvm => &["-fprofile-instr-generate"],
+            CoverageTool::Gcov => &["--coverage", "-fprofile-arcs"],
+        }
+    }
+
+    /// Check if a CMake build directory has been configured with flags
+    /// instrumenting the executable
+    pub fn is_cmake_configured(&self, build_dir: &Path) -> bool {
+        let cache = match std::fs::read_to_string(build_dir.join("CMakeCache.txt")) {
+            Ok(cache) => cache,
+            Err(_) => return false,
+        };
+
+        cache
+            .lines()
+            .filter(|x| x.starts_with("CMAKE_C") && x.contains("_FLAGS"))
+            .any(|x| self.compiler_flags().iter().any(|flag| x.contains(flag)))
+    }
+
+    /// Warn if the build does not instrument the executable. CMake ignores
+    /// the flags in the environment once the build directory is configured.
+    pub fn check_build(&self, build: &BuildCommand) {
+        if let BuildCommand::CMake(dir) = build {
+            if !self.is_cmake_configured(dir) {
+                log::warn!(
+                    "{} is not configured with {}, the executable may not be instrumented",
+                    dir.display(),
+                    self.compiler_flags()[0]
+                );
+            }
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize, Serialize)]
+pub struct CoverageOpt {
+    pub tool: CoverageTool,
+    /// Where a LCOV tracefile is written after each run
+    #[serde(default)]
+    pub lcov: Option<PathBuf>,
+}
+
+fn load_summary(path: &Path) -> Option<CoverageSummary> {
+    let s = std::fs::read_to_string(path).ok()?;
+    serde_json::from_str(&s)
+        .map_err(|e| log::warn!("Ignoring {}: {}", path.display(), e))
+        .ok()
+}
+
+fn save_summary(path: &Path, summary: &CoverageSummary) -> Result<(), Error> {
+    if let Some(dir) = path.parent().filter(|x| !x.as_os_str().is_empty()) {
+        std::fs::create_dir_all(dir)?;
+    }
+
+    let s = serde_json::to_string(summary).map_err(|e| Error::GenericError(e.to_string()))?;
+    std::fs::write(path, s)?;
+    Ok(())
+}
+
+/// Files in `dir` (and its subdirectories) with the given extension
+fn find_files(dir: &Path, extension: &str) -> Vec<PathBuf> {
+    let entries = match std::fs::read_dir(dir) {
+        Ok(entries) => entries,
+        Err(_) => return Vec::new(),
+    };
+
+    let mut files = Vec::new();
+
+    for path in entries.filter_map(|x| x.ok()).map(|x| x.path()) {
+        if path.is_dir() {
+            files.extend(find_files(&path, extension));
+        } else if path.extension().is_some_and(|x| x == extension) {
+            files.push(path);
+        }
+    }
+
+    files
+}
+
+fn remove_files(dir: &Path, extension: &str) {
+    for path in find_files(dir, extension) {
+        let _ = std::fs::remove_file(path);
+    }
+}
+
+/// `.gcda` files written by an executable instrumented by GCC. Their paths
+/// are embedded in the executable, as null terminated strings.
+fn find_gcda_files(executable: &Path) -> Vec<PathBuf> {
+    let data = match std::fs::read(executable) {
+        Ok(data) => data,
+        Err(_) => return Vec::new(),
+    };
+
+    let is_printable = |x: &u8| (0x20..0x7f).contains(x);
+
+    data.split(|x| *x == 0)
+        .filter(|x| x.ends_with(b".gcda"))
+        .map(|x| {
+            let start = x
+                .iter()
+                .rposition(|x| !is_printable(x))
+                .map_or(0, |i| i + 1);
+            String::from_utf8_lossy(&x[start..]).to_string()
+        })
+        .map(PathBuf::from)
+        .collect()
+}
+
+/// Standard output of a tool, which must succeed
+fn run_tool(mut cmd: Command) -> Result<String, Error> {
+    log::debug!("{:?}", cmd);
+    let output = cmd.output()?;
+
+    if !output.status.success() {
+        let err = String::from_utf8_lossy(&output.stderr);
+        return Err(Error::GenericError(format!(
+            "{:?} failed: {}",
+            cmd,
+            err.trim()
+        )));
+    }
+
+    Ok(String::from_utf8_lossy(&output.stdout).to_string())
+}
+
+/// Collect the code coverage of each run. The report of the run gets the
+/// coverage of the executable, with the summary of the previous run.
+pub struct CoverageTestRunner {
+    inner: Box<dyn TestRunner>,
+    tool: CoverageTool,
+    /// The instrumented executable, with LLVM
+    executable: PathBuf,
+    /// Temporary directory where LLVM profiles are written while tests are
+    /// running, with LLVM
+    profiles: Option<TempReport>,
+    /// `.gcda` files written while tests are running, with gcov
+    gcda: Vec<PathBuf>,
+    lcov: Option<PathBuf>,
+    last: PathBuf,
+}
+
+impl CoverageTestRunner {
+    /// With LLVM, the executable is run with `LLVM_PROFILE_FILE` set to a
+    /// temporary directory, one profile for each process. With gcov,
+    /// `.gcda` files are written next to object files, at the paths
+    /// embedded in the executable.
+    pub fn new(
+        opt: CoverageOpt,
+        exec: Executable,
+        make_inner: impl FnOnce(Executable) -> Result<Box<dyn TestRunner>, Error>,
+    ) -> Result<Self, Error> {
+        let executable = Vec::from(exec.unwrapped())
+            .first()
+            .map(PathBuf::from)
+            .ok_or(Error::NoExecutable)?;
+
+        let (exec, profiles, gcda) = match opt.tool {
+            CoverageTool::Llvm => {
+                let dir = TempReport::dir("coverage")?;
+                let profile_file = dir.join("%p-%m.profraw");
+                let env = format!("LLVM_PROFILE_FILE={}", profile_file.display());
+                let exec = exec.wrap(vec!["env".to_string(), env]);
+                (exec, Some(dir), vec![])
+            }
+            CoverageTool::Gcov => {
+                let gcda = find_gcda_files(&executable);
+                (exec, None, gcda)
+            }
+        };
+
+        let last = state_path("coverage", &executable.to_string_lossy());
+
+        Ok(Self {
+            inner: make_inner(exec)?,
+            tool: opt.tool,
+            executable,
+            profiles,
+            gcda,
+            lcov: opt.lcov,
+            last,
+        })
+    }
+
+    /// Remove profiles of previous runs, so each run has its own coverage
+    fn clear(&self) {
+        if let Some(dir) = &self.profiles {
+            remove_files(dir, "profraw");
+        }
+
+        for path in &self.gcda {
+            let _ = std::fs::remove_file(path);
+        }
+    }
+
+    fn collect_llvm(&self) -> Result<Coverage, Error> {
+        let dir = self.profiles.as_deref().unwrap_or_else(|| Path::new("."));
+        let profiles = find_files(dir, "profraw");
+
+        if profiles.is_empty() {
+            return Err(Error::GenericError(
+                "No profile has been written, is the executable instrumented?".to_string(),
+            ));
+        }
+
+        let merged = dir.join("merged.profdata");
+        let mut cmd = Command::new("llvm-profdata");
+        cmd.args(["merge", "-sparse"])
+            .args(&profiles)
+            .arg("-o")
+            .arg(&merged);
+        run_tool(cmd)?;
+
+        let mut cmd = Command::new("llvm-cov");
+        cmd.args(["export", "-format=lcov"])
+            .arg(format!("-instr-profile={}", merged.display()))
+            .arg(&self.executable);
+        Coverage::parse_lcov(&run_tool(cmd)?)
+    }
+
+    fn collect_gcov(&self) -> Result<Coverage, Error> {
+        let gcda: Vec<_> = self.gcda.iter().filter(|x| x.exists()).collect();
+
+        if gcda.is_empty() {
+            return Err(Error::GenericError(
+                "No .gcda file has been written, is the executable instrumented?".to_string(),
+            ));
+        }
+
+        let mut cmd = Command::new("gcov");
+        cmd.args(["--json-format", "--stdout", "--branch-probabilities"])
+            .args(&gcda);
+        Coverage::parse_gcov_json(&run_tool(cmd)?)
+    }
+
+    fn collect(&self) -> Result<Coverage, Error> {
+        let mut coverage = match self.tool {
+            CoverageTool::Llvm => self.collect_llvm()?,
+            CoverageTool::Gcov => self.collect_gcov()?,
+        };
+
+        coverage.previous = load_summary(&self.last);
+        save_summary(&self.last, &coverage.summary())?;
+
+        if let Some(path) = &self.lcov {
+            std::fs::write(path, coverage.to_lcov())?;
+        }
+
+        Ok(coverage)
+    }
+
+    /// Coverage is not essential: if it cannot be collected, tests results
+    /// are reported anyway
+    fn add_coverage(&self, result: TestResult) -> TestResult {
+        let coverage = match self.collect() {
+            Ok(coverage) => coverage,
+            Err(e) => {
+                log::warn!("Cannot collect coverage: {}", e);
+                return result;
+            }
+        };
+
+        let mut report = result.into_report("tests");
+        report.coverage = Some(coverage);
+        report.into()
+    }
+}
+
+impl TestRunner for CoverageTestRunner {
+    fn run(&self, opt: &TestRunnerOpt) -> Result<TestResult, Error> {
+        self.clear();
+        let result = self.inner.run(opt)?;
+        Ok(self.add_coverage(result))
+    }
+
+    fn run_with_events(
+        &self,
+        opt: &TestRunnerOpt,
+        events: &mut dyn FnMut(TestEvent),
+    ) -> Result<TestResult, Error> {
+        self.clear();
+        let result = self.inner.run_with_events(opt, events)?;
+        Ok(self.add_coverage(result))
+    }
+
+    fn run_repeated(
+        &self,
+        opt: &TestRunnerOpt,
+        repeat: &Repeat,
+        events: &mut dyn FnMut(TestEvent),
+    ) -> Result<TestResult, Error> {
+        self.clear();
+        let result = self.inner.run_repeated(opt, repeat, events)?;
+        Ok(self.add_coverage(result))
+    }
+
+    fn list(&self, opt: &TestRunnerOpt) -> Result<AvailableTests, Error> {
+        self.inner.list(opt)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::report::CoreStatus;
+    use crate::{CoreReport, CoreTestCase, Reason};
+
+    struct FakeTestRunner {
+        profiles: PathBuf,
+    }
+
+    impl TestRunner for FakeTestRunner {
+        fn run(&self, _opt: &TestRunnerOpt) -> Result<TestResult, Error> {
+            std::fs::create_dir_all(&self.profiles)?;
+            std::fs::write(self.profiles.join("foo.gcda"), "")?;
+
+            let mut report = CoreReport::default();
+            let test_case = CoreTestCase {
+                name: "Foo1".to_string(),
+                status: CoreStatus::Pass,
+                ..Default::default()
+            };
+            report.push_test_case("Foo", test_case);
+            Ok(TestResult::Pass(Reason::Report(report)))
+        }
+    }
+
+    #[test]
+    fn llvm_profiles_are_written_to_temp_dir() {
+        let opt = CoverageOpt {
+            tool: CoverageTool::Llvm,
+            lcov: None,
+        };
+        let exec = Executable::from(PathBuf::from("build/tests"));
+        let mut argv = Vec::new();
+        let tr = CoverageTestRunner::new(opt, exec, |exec| {
+            argv = Vec::from(&exec);
+            Ok(Box::new(FakeTestRunner {
+                profiles: PathBuf::default(),
+            }))
+        })
+        .unwrap();
+
+        let profile_file = tr.profiles.as_ref().unwrap().join("%p-%m.profraw");
+        let env = format!("LLVM_PROFILE_FILE={}", profile_file.display());
+        assert_eq!(argv, vec!["env", &env, "build/tests"]);
+        assert_eq!(tr.executable, PathBuf::from("build/tests"));
+    }
+
+    #[test]
+    fn check_cmake_flags() {
+        let dir = std::env::temp_dir().join(crate::unique_name("coverage_cmake"));
+        std::fs::create_dir_all(&dir).unwrap();
+        assert!(!CoverageTool::Gcov.is_cmake_configured(&dir));
+        let cache = "CMAKE_BUILD_TYPE:STRING=Debug\nCMAKE_CXX_FLAGS:STRING=-Wall --coverage\n";
+        std::fs::write(dir.join("CMakeCache.txt"), cache).unwrap();
+        assert!(CoverageTool::Gcov.is_cmake_configured(&dir));
+        assert!(!CoverageTool::Llvm.is_cmake_configured(&dir));
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn missing_coverage_keeps_result() {
+        let dir = std::env::temp_dir().join(crate::unique_name("coverage_test"));
+        let opt = CoverageOpt {
+            tool: CoverageTool::Gcov,
+            lcov: None,
+        };
+        std::fs::create_dir_all(&dir).unwrap();
+        let gcda = dir.join("foo.gcda");
+        let executable = dir.join("tests");
+        let mut data = b"\x7fELF\0\x01\x02".to_vec();
+        data.extend(gcda.to_string_lossy().as_bytes());
+        data.extend(b"\0--help\0");
+        std::fs::write(&executable, data).unwrap();
+
+        let exec = Executable::from(executable);
+        let profiles = dir.clone();
+        let tr = CoverageTestRunner::new(opt, exec, |_| Ok(Box::new(FakeTestRunner { profiles })))
+            .unwrap();
+        assert_eq!(tr.gcda, vec![gcda]);
+
+        // The fake .gcda file cannot be read by gcov (if it is installed)
+        let result = tr.run(&TestRunnerOpt::default()).unwrap();
+        assert!(matches!(result, TestResult::Pass(_)));
+        assert!(result.report().unwrap().coverage.is_none());
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,399 @@
+use super::{state_path, Only, Pattern, Selection, TestRunner, TestRunnerOpt};
+use crate::report::CoreStatus;
+use crate::test::xunit::{AvailableTests, FullyQualifiedTestCase, FullyQualifiedTestCaseId};
+use crate::{CoreReport, Error, TestEvent, TestResult};
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+use strum_macros::{Display, EnumString};
+
+/// How test cases which failed in the last run are executed
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Display, EnumString, Deserialize, Serialize)]
+pub enum RerunFailed {
+    /// Run only failed test cases, then all of them once they pass
+    Only,
+    /// Run failed test cases first, then the others
+    First,
+}
+
+/// Test cases which failed in the last run
+#[derive(Default, Debug, Clone, PartialEq, Eq, Deserialize, Serialize)]
+pub struct LastFailed {
+    ids: Vec<FullyQualifiedTestCaseId>,
+}
+
+fn is_failed(test_case: &crate::CoreTestCase) -> bool {
+    matches!(test_case.status, CoreStatus::Fail) || !test_case.failures.is_empty()
+}
+
+impl LastFailed {
+    /// Load from a file. A missing or invalid file is the same as no failure.
+    pub fn load(path: &Path) -> Self {
+        let s = match std::fs::read_to_string(path) {
+            Ok(s) => s,
+            Err(_) => return Self::default(),
+        };
+
+        serde_json::from_str(&s).unwrap_or_else(|e| {
+            log::warn!("Ignoring {}: {}", path.display(), e);
+            Self::default()
+        })
+    }
+
+    pub fn save(&self, path: &Path) -> Result<(), Error> {
+        if let Some(dir) = path.parent().filter(|x| !x.as_os_str().is_empty()) {
+            std::fs::create_dir_all(dir)?;
+        }
+
+        let s = serde_json::to_string(self).map_err(|e| Error::GenericError(e.to_string()))?;
+        std::fs::write(path, s)?;
+        Ok(())
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.ids.is_empty()
+    }
+
+    pub fn ids(&self) -> &[FullyQualifiedTestCaseId] {
+        &self.ids
+    }
+
+    /// Forget test cases which do not exist anymore
+    pub fn retain_available(&mut self, tests: &AvailableTests) {
+        self.ids.retain(|x| tests.get_by_id(*x).is_some());
+    }
+
+    /// Update with the test cases executed in a run. Test cases which have
+    /// not been executed keep their previous state.
+    pub fn update(&mut self, report: &CoreReport) {
+        for test_suite in report.testsuites.iter() {
+            for test_case in test_suite.testcases.iter() {
+                let id = test_case.fqtc.unwrap_or_else(|| {
+                    FullyQualifiedTestCase::from_test_suite_test_case(
+                        &test_suite.name,
+                        &test_case.name,
+                    )
+                    .id
+                });
+
+                self.ids.retain(|x| *x != id);
+
+                if is_failed(test_case) {
+                    self.ids.push(id);
+                }
+            }
+        }
+    }
+}
+
+/// Run the test cases which failed in the last run before the others, or
+/// instead of them. Failures are remembered in a file, so they survive
+/// restarts. This is done only when all tests are selected: a selection
+/// made by the user is always executed as is.
+pub struct RerunFailedTestRunner {
+    inner: Box<dyn TestRunner>,
+    mode: RerunFailed,
+    path: PathBuf,
+}
+
+impl RerunFailedTestRunner {
+    /// Failures are remembered for each `key`, which identifies the test
+    /// runner (e.g. its command)
+    pub fn new(inner: Box<dyn TestRunner>, mode: RerunFailed, key: &str) -> Self {
+        Self::with_path(inner, mode, state_path("last_failed", key))
+    }
+
+    pub fn with_path(inner: Box<dyn TestRunner>, mode: RerunFailed, path: PathBuf) -> Self {
+        Self { inner, mode, path }
+    }
+
+    fn run_only(
+        &self,
+        opt: &TestRunnerOpt,
+        only: Only,
+        events: &mut dyn FnMut(TestEvent),
+    ) -> Result<TestResult, Error> {
+        let opt = TestRunnerOpt {
+            only,
+            ..opt.clone()
+        };
+
+        // The inner test runner may run many times, but for the caller it
+        // is a single run
+        self.inner.run_with_events(&opt, &mut |x| match x {
+            TestEvent::RunStarted(_) | TestEvent::RunFinished => {}
+            x => events(x),
+        })
+    }
+
+    fn run_failed_first(
+        &self,
+        opt: &TestRunnerOpt,
+        last_failed: &LastFailed,
+        events: &mut dyn FnMut(TestEvent),
+    ) -> Result<TestResult, Error> {
+        let ids = last_failed.ids().iter().copied();
+        let failed = Selection::from_ids(ids.clone());
+
+        let failed_result = match self.run_only(opt, Only::Selection(failed), events) {
+            // Failed test cases do not exist anymore
+            Err(Error::NothingToDo) => return self.run_only(opt, Only::All, events),
+            x => x?,
+        };
+
+        match self.mode {
+            RerunFailed::Only => match failed_result {
+                TestResult::Pass(_) => {
+                    log::info!("Failed tests now pass, running all tests");
+                    self.run_only(opt, Only::All, events)
+                }
+                x => Ok(x),
+            },
+            RerunFailed::First => {
+                let others = Selection {
+                    include: vec![],
+                    exclude: ids.map(Pattern::Id).collect(),
+                };
+
+                let others_result = match self.run_only(opt, Only::Selection(others), events) {
+                    // Every test case failed in the last run
+                    Err(Error::NothingToDo) => return Ok(failed_result),
+                    x => x?,
+                };
+
+                match (failed_result.report(), others_result.report()) {
+                    (Some(failed), Some(others)) => {
+                        let mut report = failed.clone();
+                        report.merge(others.clone());
+                        Ok(report.into())
+                    }
+                    _ => match failed_result {
+                        TestResult::Fail(_) => Ok(failed_result),
+                        _ => Ok(others_result),
+                    },
+                }
+            }
+        }
+    }
+}
+
+impl TestRunner for RerunFailedTestRunner {
+    fn run(&self, opt: &TestRunnerOpt) -> Result<TestResult, Error> {
+        self.run_with_events(opt, &mut |_| {})
+    }
+
+    fn run_with_events(
+        &self,
+        opt: &TestRunnerOpt,
+        events: &mut dyn FnMut(TestEvent),
+    ) -> Result<TestResult, Error> {
+        let mut last_failed = LastFailed::load(&self.path);
+
+        // Test runners which cannot list tests may still run the failed ones
+        if !last_failed.is_empty() {
+            if let Ok(tests) = self.inner.list(opt) {
+                last_failed.retain_available(&tests);
+            }
+        }
+
+        let result = if last_failed.is_empty() || opt.only != Only::All {
+            self.inner.run_with_events(opt, events)?
+        } else {
+            events(TestEvent::RunStarted(None));
+            let result = self.run_failed_first(opt, &last_failed, events);
+            events(TestEvent::RunFinished);
+            result?
+        };
+
+        if let Some(report) = result.report() {
+            last_failed.update(report);
+
+            if let Err(e) = last_failed.save(&self.path) {
+                log::warn!("Cannot save {}: {}", self.path.display(), e);
+            }
+        }
+
+        Ok(result)
+    }
+
+    fn list(&self, opt: &TestRunnerOpt) -> Result<AvailableTests, Error> {
+        self.inner.list(opt)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{CoreFailure, CoreTestCase, CoreTestSuite};
+    use std::sync::{Arc, Mutex};
+
+    /// Test cases `Foo.Foo1`, `Foo.Foo2` and `Foo.Foo3`, where only the
+    /// ones in `failing` fail. Runs are recorded.
+    struct FakeTestRunner {
+        failing: Vec<&'static str>,
+        runs: Arc<Mutex<Vec<Vec<String>>>>,
+    }
+
+    impl TestRunner for FakeTestRunner {
+        fn run(&self, opt: &TestRunnerOpt) -> Result<TestResult, Error> {
+            let mut report = CoreReport {
+                failures: Some(self.failing.len()),
+                testsuites: vec![CoreTestSuite {
+                    name: "Foo".to_string(),
+                    ..Default::default()
+                }],
+                ..Default::default()
+            };
+
+            for name in ["Foo1", "Foo2", "Foo3"] {
+                let fqtc = FullyQualifiedTestCase::from_test_suite_test_case("Foo", name);
+                let failed = self.failing.contains(&name);
+                let failures = match failed {
+                    true => vec![CoreFailure::default()],
+                    false => vec![],
+                };
+
+                report.testsuites[0].testcases.push(CoreTestCase {
+                    fqtc: Some(fqtc.id),
+                    name: name.to_string(),
+                    status: CoreStatus::from(Some(!failed)),
+                    failures,
+                    ..Default::default()
+                });
+            }
+
+            if let Some(selection) = opt.only.selection() {
+                report.retain_selection(&selection);
+            }
+
+            if report.testsuites.is_empty() {
+                return Err(Error::NothingToDo);
+            }
+
+            let names = report.testsuites[0]
+                .testcases
+                .iter()
+                .map(|x| x.name.clone())
+                .collect();
+            self.runs.lock().unwrap().push(names);
+
+            Ok(report.into())
+        }
+    }
+
+    fn make_test_runner(
+        failing: Vec<&'static str>,
+        mode: RerunFailed,
+        name: &str,
+    ) -> (RerunFailedTestRunner, Arc<Mutex<Vec<Vec<String>>>>) {
+        let runs = Arc::new(Mutex::new(Vec::new()));
+        let inner = FakeTestRunner {
+            failing,
+            runs: runs.clone(),
+        };
+        let path = std::env::temp_dir()
+            .join(format!("provola_last_failed_{}", std::process::id()))
+            .join(name);
+        let _ = std::fs::remove_file(&path);
+        let tr = RerunFailedTestRunner::with_path(Box::new(inner), mode, path);
+        (tr, runs)
+    }
+
+    #[test]
+    fn run_failed_first() {
+        let (tr, runs) = make_test_runner(vec!["Foo2"], RerunFailed::First, "first.json");
+        let opt = TestRunnerOpt::default();
+        assert!(matches!(tr.run(&opt), Ok(TestResult::Fail(_))));
+        assert!(matches!(tr.run(&opt), Ok(TestResult::Fail(_))));
+        let runs = runs.lock().unwrap();
+        assert_eq!(runs[0], vec!["Foo1", "Foo2", "Foo3"]);
+        assert_eq!(runs[1], vec!["Foo2"]);
+        assert_eq!(runs[2], vec!["Foo1", "Foo3"]);
+    }
+
+    #[test]
+    fn run_failed_first_is_a_single_run() {
+        let (tr, _) = make_test_runner(vec!["Foo2"], RerunFailed::First, "events.json");
+        let opt = TestRunnerOpt::default();
+        tr.run(&opt).unwrap();
+
+        let mut events = Vec::new();
+        tr.run_with_events(&opt, &mut |x| events.push(x)).unwrap();
+        let started = events
+            .iter()
+            .filter(|x| matches!(x, TestEvent::RunStarted(_)))
+            .count();
+        let finished = events
+            .iter()
+            .filter(|x| matches!(x, TestEvent::RunFinished))
+            .count();
+        assert_eq!((started, finished), (1, 1));
+        assert!(matches!(events.first(), Some(TestEvent::RunStarted(_))));
+        assert!(matches!(events.last(), Some(TestEvent::RunFinished)));
+        assert_eq!(events.len(), 5);
+    }
+
+    #[test]
+    fn run_only_failed_until_they_pass() {
+        let (mut tr, runs) = make_test_runner(vec!["Foo2"], RerunFailed::Only, "only.json");
+        let opt = TestRunnerOpt::default();
+        tr.run(&opt).unwrap();
+        tr.run(&opt).unwrap();
+
+        // Fix Foo2
+        let inner = FakeTestRunner {
+            failing: vec![],
+            runs: runs.clone(),
+        };
+        tr.inner = Box::new(inner);
+        assert!(matches!(tr.run(&opt), Ok(TestResult::Pass(_))));
+        assert!(LastFailed::load(&tr.path).is_empty());
+
+        let runs = runs.lock().unwrap();
+        assert_eq!(runs[1], vec!["Foo2"]);
+        assert_eq!(runs[2], vec!["Foo2"]);
+        assert_eq!(runs[3], vec!["Foo1", "Foo2", "Foo3"]);
+    }
+
+    #[test]
+    fn forget_test_cases_not_available() {
+        let foo1 = FullyQualifiedTestCase::from_test_suite_test_case("Foo", "Foo1");
+        let removed = FullyQualifiedTestCase::from_test_suite_test_case("Foo", "Removed");
+        let mut last_failed = LastFailed {
+            ids: vec![foo1.id, removed.id],
+        };
+        let mut tests = AvailableTests::default();
+        tests.push("Foo", "Foo1");
+        last_failed.retain_available(&tests);
+        assert_eq!(last_failed.ids(), &[foo1.id]);
+    }
+
+    #[test]
+    fn last_failed_path_for_each_test_runner() {
+        let path = state_path("last_failed", "build/tests");
+        assert!(path.starts_with(".provola"));
+        assert_ne!(path, state_path("last_failed", "build/other_tests"));
+        // The hash must not change between builds
+        let path = state_path("last_failed", "a");
+        assert_eq!(
+            path,
+            Path::new(".provola/last_failed-AF63DC4C8601EC8C.json")
+        );
+    }
+
+    #[test]
+    fn last_failed_update_keeps_not_executed() {
+        let (tr, _) = make_test_runner(vec!["Foo1", "Foo3"], RerunFailed::Only, "update.json");
+        let result = tr.inner.run(&TestRunnerOpt::default()).unwrap();
+        let mut last_failed = LastFailed::default();
+        last_failed.update(result.report().unwrap());
+        assert_eq!(last_failed.ids().len(), 2);
+
+        let mut report = result.report().unwrap().clone();
+        report.testsuites[0].testcases.remove(0);
+        report.testsuites[0].testcases[1].status = CoreStatus::Pass;
+        report.testsuites[0].testcases[1].failures.clear();
+        last_failed.update(&report);
+        let foo1 = FullyQualifiedTestCase::from_test_suite_test_case("Foo", "Foo1");
+        assert_eq!(last_failed.ids(), &[foo1.id]);
+    }
+}
@@ -0,0 +1,280 @@
+use super::{TestRunner, TestRunnerOpt};
+use crate::report::{CoreStatus, Duration, RepeatStats, RepeatSummary};
+use crate::test::xunit::FullyQualifiedTestCaseId;
+use crate::{CoreFailure, CoreReport, Error, TestEvent, TestResult};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::time::Instant;
+
+/// Greatest seed accepted by GoogleTest
+const MAX_SEED: u32 = 99999;
+
+/// How many times tests are executed, to find flaky ones
+#[derive(Default, Clone, Debug, Deserialize, Serialize, PartialEq, Eq)]
+pub struct Repeat {
+    /// Maximum number of runs
+    pub count: Option<usize>,
+    /// Stop at the first run with a failure
+    pub until_failure: bool,
+    /// Stop starting new runs after this time
+    pub duration: Option<Duration>,
+    /// Run tests in random order, with this seed. Only test runners which
+    /// can shuffle tests natively support this.
+    pub seed: Option<u32>,
+}
+
+impl Repeat {
+    /// A seed depending on the current time
+    pub fn random_seed() -> u32 {
+        let nanos = std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .map(|x| x.subsec_nanos())
+            .unwrap_or_default();
+
+        nanos % MAX_SEED + 1
+    }
+
+    /// Seed of a run, changed at every run as GoogleTest does
+    pub fn seed_of_run(&self, run: usize) -> Option<u32> {
+        self.seed
+            .map(|x| ((x.max(1) as usize - 1 + run) % MAX_SEED as usize) as u32 + 1)
+    }
+
+    /// Check if another run is needed. Without any limit, tests run once.
+    fn has_next(&self, runs: usize, elapsed: Duration) -> bool {
+        let is_limited = self.count.is_some() || self.duration.is_some() || self.until_failure;
+
+        self.count.is_none_or(|x| runs < x)
+            && self.duration.is_none_or(|x| elapsed < x)
+            && (is_limited || runs == 0)
+    }
+}
+
+/// Results of every run of many test cases
+#[derive(Default)]
+pub struct Repetitions {
+    results: HashMap<FullyQualifiedTestCaseId, Vec<(CoreStatus, Option<Duration>)>>,
+    /// Failures of the last failed run of each test case
+    failures: HashMap<FullyQualifiedTestCaseId, Vec<CoreFailure>>,
+}
+
+impl Repetitions {
+    fn add(&mut self, id: FullyQualifiedTestCaseId, status: CoreStatus, time: Option<Duration>) {
+        self.results.entry(id).or_default().push((status, time));
+    }
+
+    /// Add the result of a test case, for test runners repeating tests
+    /// natively, which report only the last run
+    pub fn add_event(&mut self, event: &TestEvent) {
+        if let TestEvent::TestFinished { fqtc, status, time } = event {
+            self.add(fqtc.id, *status, *time);
+        }
+    }
+
+    /// Add the results of a run
+    pub fn add_report(&mut self, report: &CoreReport) {
+        for test_suite in report.testsuites.iter() {
+            for test_case in test_suite.testcases.iter() {
+                let id = test_case.id(&test_suite.name);
+                self.add(id, test_case.status, test_case.time);
+
+                if !test_case.failures.is_empty() {
+                    self.failures.insert(id, test_case.failures.clone());
+                }
+            }
+        }
+    }
+
+    /// Add statistics to the report of the last run. A test case which
+    /// failed in any run is failed.
+    pub fn finish(self, mut report: CoreReport, summary: RepeatSummary) -> CoreReport {
+        for test_suite in report.testsuites.iter_mut() {
+            for test_case in test_suite.testcases.iter_mut() {
+                let id = test_case.id(&test_suite.name);
+
+                let stats = match self.results.get(&id) {
+                    Some(results) => RepeatStats::new(results),
+                    None => continue,
+                };
+
+                if stats.failed > 0 {
+                    test_case.status = CoreStatus::Fail;
+                }
+
+                if stats.failed > 0 && test_case.failures.is_empty() {
+                    test_case.failures = match self.failures.get(&id) {
+                        Some(failures) => failures.clone(),
+                        None => vec![CoreFailure {
+                            ttype: "flaky".to_string(),
+                            message: format!("Failed {} of {} runs", stats.failed, stats.runs),
+                            location: None,
+                        }],
+                    };
+                }
+
+                test_case.repeat = Some(stats);
+            }
+        }
+
+        report.update_counts();
+        report.repeat = Some(summary);
+        report
+    }
+}
+
+/// Run tests again and again, for test runners which cannot repeat them
+/// natively
+pub fn run_repeatedly<T: TestRunner + ?Sized>(
+    test_runner: &T,
+    opt: &TestRunnerOpt,
+    repeat: &Repeat,
+    events: &mut dyn FnMut(TestEvent),
+) -> Result<TestResult, Error> {
+    let start = Instant::now();
+    let mut repetitions = Repetitions::default();
+    let mut last_result = None;
+    let mut runs = 0;
+
+    while repeat.has_next(runs, start.elapsed()) {
+        let run_opt = TestRunnerOpt {
+            repeat: Some(Repeat {
+                seed: repeat.seed_of_run(runs),
+                ..repeat.clone()
+            }),
+            ..opt.clone()
+        };
+
+        let result = test_runner.run_with_events(&run_opt, events)?;
+        runs += 1;
+
+        if let Some(report) = result.report() {
+            repetitions.add_report(report);
+        }
+
+        let failed = matches!(result, TestResult::Fail(_));
+        last_result = Some(result);
+
+        if failed && repeat.until_failure {
+            log::info!("Failed at run {}", runs);
+            break;
+        }
+    }
+
+    let last_result = last_result.ok_or(Error::NothingToDo)?;
+
+    let summary = RepeatSummary {
+        runs,
+        seed: repeat.seed,
+    };
+
+    match last_result.report() {
+        Some(report) => Ok(repetitions.finish(report.clone(), summary).into()),
+        None => Ok(last_result),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test::xunit::FullyQualifiedTestCase;
+    use crate::CoreTestCase;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    /// Test case `Foo.Foo1` always passes, `Foo.Foo2` fails at the runs in
+    /// `failing`
+    struct FakeTestRunner {
+        failing: Vec<usize>,
+        runs: AtomicUsize,
+    }
+
+    impl TestRunner for FakeTestRunner {
+        fn run(&self, _opt: &TestRunnerOpt) -> Result<TestResult, Error> {
+            let run = self.runs.fetch_add(1, Ordering::Relaxed);
+
+            let mut report = CoreReport::default();
+            let time = Some(Duration::from_millis(10 * (run as u64 + 1)));
+
+            for (name, ok) in [("Foo1", true), ("Foo2", !self.failing.contains(&run))] {
+                let fqtc = FullyQualifiedTestCase::from_test_suite_test_case("Foo", name);
+                let failures = match ok {
+                    true => vec![],
+                    false => vec![CoreFailure {
+                        message: format!("Failed at run {}", run),
+                        ..Default::default()
+                    }],
+                };
+
+                let test_case = CoreTestCase {
+                    fqtc: Some(fqtc.id),
+                    name: name.to_string(),
+                    status: CoreStatus::from(Some(ok)),
+                    time,
+                    failures,
+                    ..Default::default()
+                };
+
+                report.push_test_case("Foo", test_case);
+            }
+
+            Ok(report.into())
+        }
+    }
+
+    fn run(failing: Vec<usize>, repeat: Repeat) -> CoreReport {
+        let tr = FakeTestRunner {
+            failing,
+            runs: AtomicUsize::new(0),
+        };
+        let result = run_repeatedly(&tr, &TestRunnerOpt::default(), &repeat, &mut |_| {});
+        result.unwrap().report().unwrap().clone()
+    }
+
+    #[test]
+    fn find_flaky_tests() {
+        let repeat = Repeat {
+            count: Some(4),
+            ..Default::default()
+        };
+        let report = run(vec![1], repeat);
+        assert_eq!(report.repeat.unwrap().runs, 4);
+        assert_eq!(report.failures, Some(1));
+
+        let foo1 = &report.testsuites[0].testcases[0];
+        let stats = foo1.repeat.as_ref().unwrap();
+        assert!(!stats.is_flaky());
+        assert_eq!(stats.passed, 4);
+        assert_eq!(stats.min, Some(Duration::from_millis(10)));
+        assert_eq!(stats.median, Some(Duration::from_millis(25)));
+        assert_eq!(stats.max, Some(Duration::from_millis(40)));
+
+        // Failed in a run, passed in the last one
+        let foo2 = &report.testsuites[0].testcases[1];
+        assert!(foo2.repeat.as_ref().unwrap().is_flaky());
+        assert!(matches!(foo2.status, CoreStatus::Fail));
+        assert_eq!(foo2.failures[0].message, "Failed at run 1");
+    }
+
+    #[test]
+    fn repeat_until_failure() {
+        let repeat = Repeat {
+            count: Some(10),
+            until_failure: true,
+            ..Default::default()
+        };
+        let report = run(vec![2], repeat);
+        assert_eq!(report.repeat.unwrap().runs, 3);
+    }
+
+    #[test]
+    fn seed_changes_at_every_run() {
+        let repeat = Repeat {
+            seed: Some(MAX_SEED - 1),
+            ..Default::default()
+        };
+        assert_eq!(repeat.seed_of_run(0), Some(MAX_SEED - 1));
+        assert_eq!(repeat.seed_of_run(1), Some(MAX_SEED));
+        assert_eq!(repeat.seed_of_run(2), Some(1));
+        assert!(repeat.has_next(0, Duration::ZERO));
+        assert!(!repeat.has_next(1, Duration::ZERO));
+    }
+}
@@ -0,0 +1,261 @@
+use crate::test::xunit::{AvailableTests, FullyQualifiedTestCase, FullyQualifiedTestCaseId};
+use crate::Error;
+use serde::{Deserialize, Serialize};
+use std::str::FromStr;
+
+/// A pattern matching some test cases
+#[derive(Clone, Debug, Deserialize, Serialize, PartialEq, Eq)]
+pub enum Pattern {
+    /// Index of a test case in the list of available tests
+    Index(usize),
+    /// A single test case. Test cases named like the id, as printed, match
+    /// too, since a name may look like an id.
+    Id(FullyQualifiedTestCaseId),
+    /// All test cases in a test suite
+    Suite(String),
+    /// Glob matching the fully qualified name (`Suite.Case`) or just the
+    /// name of the test case. `*` matches any sequence, `?` any character.
+    Name(String),
+    /// A tag, like Catch2 `[fast]`. Tags are known only by test runners
+    /// supporting them natively: others never match a tag.
+    Tag(String),
+}
+
+/// Check if `s` matches the glob `pattern`, where `*` matches any sequence
+/// of characters and `?` any single character.
+pub fn glob_match(pattern: &str, s: &str) -> bool {
+    let pattern: Vec<char> = pattern.chars().collect();
+    let s: Vec<char> = s.chars().collect();
+
+    let (mut p, mut i) = (0, 0);
+    // Position of the last `*` and of the character it is matching against
+    let mut star: Option<(usize, usize)> = None;
+
+    while i < s.len() {
+        match pattern.get(p) {
+            Some('*') => {
+                star = Some((p, i));
+                p += 1;
+            }
+            Some(&c) if c == '?' || c == s[i] => {
+                p += 1;
+                i += 1;
+            }
+            _ => match star {
+                // Let the last `*` match one more character
+                Some((star_p, star_i)) => {
+                    star = Some((star_p, star_i + 1));
+                    p = star_p + 1;
+                    i = star_i + 1;
+                }
+                None => return false,
+            },
+        }
+    }
+
+    pattern[p..].iter().all(|&x| x == '*')
+}
+
+impl Pattern {
+    /// Check if this pattern matches a test case, given its position in the
+    /// list of available tests, its test suite, its name and its id
+    pub fn is_match(
+        &self,
+        index: usize,
+        test_suite: &str,
+        test_case: &str,
+        id: Option<FullyQualifiedTestCaseId>,
+    ) -> bool {
+        match self {
+            Pattern::Index(x) => *x == index,
+            Pattern::Id(x) => Some(*x) == id || x.to_string().eq_ignore_ascii_case(test_case),
+            Pattern::Suite(x) => x == test_suite,
+            Pattern::Name(x) => {
+                glob_match(x, test_case) || glob_match(x, &format!("{}.{}", test_suite, test_case))
+            }
+            Pattern::Tag(_) => false,
+        }
+    }
+
+    pub fn is_glob(&self) -> bool {
+        matches!(self, Pattern::Name(x) if x.contains(['*', '?']))
+    }
+}
+
+/// Parse a pattern written as `[tag]`, as an index or an id printed by the
+/// list of available tests, or as a glob
+impl FromStr for Pattern {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        if s.is_empty() {
+            return Err(Error::InvalidTestPattern(s.to_string()));
+        }
+
+        if let Some(tag) = s.strip_prefix('[') {
+            return match tag.strip_suffix(']') {
+                Some(tag) if !tag.is_empty() => Ok(Pattern::Tag(tag.to_string())),
+                _ => Err(Error::InvalidTestPattern(s.to_string())),
+            };
+        }
+
+        if let Ok(id) = s.parse() {
+            return Ok(Pattern::Id(id));
+        }
+
+        if let Ok(index) = s.parse() {
+            return Ok(Pattern::Index(index));
+        }
+
+        Ok(Pattern::Name(s.to_string()))
+    }
+}
+
+/// Test cases selected by some patterns
+#[derive(Default, Clone, Debug, Deserialize, Serialize, PartialEq, Eq)]
+pub struct Selection {
+    /// Patterns of test cases to run. If empty, all test cases are included
+    pub include: Vec<Pattern>,
+    /// Patterns of test cases not to run, even if included
+    pub exclude: Vec<Pattern>,
+}
+
+impl Selection {
+    /// Parse patterns, where those starting with `-` or `~` are exclusions
+    pub fn parse<T: AsRef<str>>(patterns: impl IntoIterator<Item = T>) -> Result<Self, Error> {
+        let mut selection = Selection::default();
+
+        for pattern in patterns {
+            let pattern = pattern.as_ref();
+
+            match pattern.strip_prefix(['-', '~']) {
+                Some(x) => selection.exclude.push(x.parse()?),
+                None => selection.include.push(pattern.parse()?),
+            }
+        }
+
+        Ok(selection)
+    }
+
+    /// Select the given test cases. Without any id, every test case is
+    /// selected, like with an empty list of patterns.
+    pub fn from_ids(ids: impl IntoIterator<Item = FullyQualifiedTestCaseId>) -> Self {
+        Selection {
+            include: ids.into_iter().map(Pattern::Id).collect(),
+            exclude: vec![],
+        }
+    }
+
+    pub fn is_match(
+        &self,
+        index: usize,
+        test_suite: &str,
+        test_case: &str,
+        id: Option<FullyQualifiedTestCaseId>,
+    ) -> bool {
+        let is_match = |x: &Pattern| x.is_match(index, test_suite, test_case, id);
+        let included = self.include.is_empty() || self.include.iter().any(is_match);
+        included && !self.exclude.iter().any(is_match)
+    }
+
+    /// Check if a pattern can be evaluated only by the test runner
+    pub fn has_tags(&self) -> bool {
+        let is_tag = |x: &Pattern| matches!(x, Pattern::Tag(_));
+        self.include.iter().any(is_tag) || self.exclude.iter().any(is_tag)
+    }
+
+    /// Available tests matching this selection
+    pub fn filter<'a>(&self, tests: &'a AvailableTests) -> Vec<&'a FullyQualifiedTestCase> {
+        tests
+            .enumerate()
+            .filter(|(index, x)| self.is_match(*index, &x.test_suite.0, &x.test_case.0, Some(x.id)))
+            .map(|(_, x)| x)
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn make_available_tests() -> AvailableTests {
+        let mut tests = AvailableTests::default();
+        tests.push("Foo", "Foo1");
+        tests.push("Foo", "Foo2");
+        tests.push("Bar", "Bar1");
+        tests.push("Bar", "Slow1");
+        tests
+    }
+
+    fn names(tests: Vec<&FullyQualifiedTestCase>) -> Vec<String> {
+        tests.iter().map(|x| x.to_string()).collect()
+    }
+
+    #[test]
+    fn glob() {
+        assert!(glob_match("Foo.*", "Foo.Foo1"));
+        assert!(glob_match("*.Foo?", "Foo.Foo1"));
+        assert!(glob_match("*o*o*", "Foo.Foo1"));
+        assert!(!glob_match("Foo.?", "Foo.Foo1"));
+        assert!(!glob_match("Bar.*", "Foo.Foo1"));
+        assert!(glob_match("", ""));
+    }
+
+    #[test]
+    fn parse_patterns() {
+        assert_eq!("3".parse::<Pattern>().unwrap(), Pattern::Index(3));
+        assert_eq!(
+            "[fast]".parse::<Pattern>().unwrap(),
+            Pattern::Tag("fast".to_string())
+        );
+        assert_eq!(
+            "Foo.*".parse::<Pattern>().unwrap(),
+            Pattern::Name("Foo.*".to_string())
+        );
+        let fqtc = FullyQualifiedTestCase::from_test_suite_test_case("foo", "bar");
+        let s = fqtc.id.to_string();
+        assert_eq!(s.parse::<Pattern>().unwrap(), Pattern::Id(fqtc.id));
+        assert!("[fast".parse::<Pattern>().is_err());
+    }
+
+    #[test]
+    fn select_test_case_named_like_an_id() {
+        let mut tests = make_available_tests();
+        tests.push("Baz", "deadbeefdeadbeef");
+        let selection = Selection::parse(["deadbeefdeadbeef"]).unwrap();
+        let selected = names(selection.filter(&tests));
+        assert_eq!(selected, vec!["Baz.deadbeefdeadbeef"]);
+
+        let id = tests.get(0).unwrap().id.to_string();
+        let selection = Selection::parse([id]).unwrap();
+        let selected = names(selection.filter(&tests));
+        assert_eq!(selected, vec!["Foo.Foo1"]);
+    }
+
+    #[test]
+    fn select_globs_and_exclusions() {
+        let tests = make_available_tests();
+        let selection = Selection::parse(["Foo.*", "Bar.*", "-*.Slow*"]).unwrap();
+        let selected = names(selection.filter(&tests));
+        assert_eq!(selected, vec!["Foo.Foo1", "Foo.Foo2", "Bar.Bar1"]);
+    }
+
+    #[test]
+    fn select_suite_and_index() {
+        let tests = make_available_tests();
+        let selection = Selection {
+            include: vec![Pattern::Suite("Bar".to_string()), Pattern::Index(0)],
+            exclude: vec![Pattern::Name("Bar1".to_string())],
+        };
+        let selected = names(selection.filter(&tests));
+        assert_eq!(selected, vec!["Foo.Foo1", "Bar.Slow1"]);
+    }
+
+    #[test]
+    fn select_all_but_excluded() {
+        let tests = make_available_tests();
+        let selection = Selection::parse(["~Foo.*"]).unwrap();
+        let selected = names(selection.filter(&tests));
+        assert_eq!(selected, vec!["Bar.Bar1", "Bar.Slow1"]);
+    }
+}
@@ -1,17 +1,28 @@
+use crate::test_runners::glob_match;
 use crate::Error;
 use notify::{watcher, DebouncedEvent, INotifyWatcher, RecursiveMode, Watcher};
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 use std::sync::mpsc::{channel, Receiver};
 use std::time::Duration;
 
+/// Files written by provola itself in the working directory, which must
+/// not trigger a new run: what is remembered between runs
+const WRITTEN_BY_RUN: &[&str] = &[".provola/*"];
+
 pub struct WatchOptions {
     pub file: PathBuf,
     pub debounce_time: Duration,
+    /// Files or directories written by a run (e.g. the build directory or
+    /// a report), whose changes are ignored. They can be glob patterns,
+    /// like `cpputest_*.xml`.
+    pub ignore: Vec<PathBuf>,
 }
 
 pub struct ProvolaWatcher {
     rx: Receiver<DebouncedEvent>,
-    _w: INotifyWatcher,
+    w: INotifyWatcher,
+    cwd: PathBuf,
+    ignore: Vec<PathBuf>,
 }
 
 impl TryFrom<WatchOptions> for ProvolaWatcher {
@@ -26,23 +37,64 @@ impl TryFrom<WatchOptions> for ProvolaWatcher {
         w.watch(&opt.file, RecursiveMode::Recursive)
             .map_err(|e| Error::CannotWatch(e.to_string()))?;
 
-        Ok(ProvolaWatcher { rx, _w: w })
+        // Changed paths are absolute
+        let cwd = std::env::current_dir()?;
+        let ignore = opt.ignore.iter().map(|x| cwd.join(x)).collect();
+
+        Ok(ProvolaWatcher { rx, w, cwd, ignore })
     }
 }
 
 impl ProvolaWatcher {
+    /// Watch another file or directory, with the same options
+    pub fn add(&mut self, file: &Path) -> Result<(), Error> {
+        self.w
+            .watch(file, RecursiveMode::Recursive)
+            .map_err(|e| Error::CannotWatch(e.to_string()))
+    }
+
     pub fn rx(&self) -> &Receiver<DebouncedEvent> {
         &self.rx
     }
 
+    fn is_ignored(&self, path: &Path) -> bool {
+        let is_match = |x: &PathBuf| {
+            path.starts_with(x) || glob_match(&x.to_string_lossy(), &path.to_string_lossy())
+        };
+
+        if self.ignore.iter().any(is_match) {
+            return true;
+        }
+
+        let path = path.strip_prefix(&self.cwd).unwrap_or(path);
+        let path = path.to_string_lossy();
+        WRITTEN_BY_RUN.iter().any(|x| glob_match(x, &path))
+    }
+
+    /// Check if an event is a change which must trigger a run
+    fn is_change(&self, event: &DebouncedEvent) -> bool {
+        match event {
+            // Followed by `Write` or `Remove`, when changes are finished
+            DebouncedEvent::NoticeWrite(_) | DebouncedEvent::NoticeRemove(_) => false,
+            DebouncedEvent::Create(x)
+            | DebouncedEvent::Write(x)
+            | DebouncedEvent::Chmod(x)
+            | DebouncedEvent::Remove(x) => !self.is_ignored(x),
+            DebouncedEvent::Rename(x, y) => !self.is_ignored(x) || !self.is_ignored(y),
+            DebouncedEvent::Rescan | DebouncedEvent::Error(_, _) => true,
+        }
+    }
+
     pub fn watch(self, f: &mut dyn FnMut()) -> Result<(), Error> {
         let rx = self.rx();
 
         loop {
             match rx.recv() {
-                Ok(_) => {
+                Ok(event) if self.is_change(&event) => {
+                    log::debug!("{:?}", event);
                     f();
                 }
+                Ok(_) => {}
                 Err(e) => {
                     return Err(Error::CannotWatch(e.to_string()));
                 }
@@ -50,3 +102,27 @@ impl ProvolaWatcher {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn ignore_changes_made_by_run() {
+        let watcher = ProvolaWatcher::try_from(WatchOptions {
+            file: std::env::temp_dir(),
+            debounce_time: Duration::from_secs(1),
+            ignore: vec![PathBuf::from("build"), PathBuf::from("cpputest_*.xml")],
+        })
+        .unwrap();
+
+        let cwd = std::env::current_dir().unwrap();
+        let write = |x: &str| DebouncedEvent::Write(cwd.join(x));
+        assert!(watcher.is_change(&write("src/tests.cpp")));
+        assert!(watcher.is_change(&write("builder.c")));
+        assert!(!watcher.is_change(&write("build/tests")));
+        assert!(!watcher.is_change(&write(".provola/last_failed-0123.json")));
+        assert!(!watcher.is_change(&write("cpputest_1_Foo.xml")));
+        assert!(!watcher.is_change(&DebouncedEvent::NoticeWrite(cwd.join("foo.c"))));
+    }
+}
@@ -0,0 +1,26 @@
+#include <CppUTest/CommandLineTestRunner.h>
+#include <CppUTest/TestHarness.h>
+
+TEST_GROUP(Foo){};
+
+TEST(Foo, Foo1) {
+  CHECK_EQUAL(2, 1 + 1);
+}
+
+TEST(Foo, Foo2) {
+  CHECK_EQUAL(1, 1 + 1);
+}
+
+TEST_GROUP(Bar){};
+
+TEST(Bar, Bar1) {
+  CHECK_TRUE(true);
+}
+
+IGNORE_TEST(Bar, Bar2) {
+  CHECK_TRUE(false);
+}
+
+int main(int argc, char** argv) {
+  return CommandLineTestRunner::RunAllTests(argc, argv);
+}
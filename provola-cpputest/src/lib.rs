@@ -0,0 +1,187 @@
+use provola_core::test::xunit::FullyQualifiedTestCase;
+use provola_core::test_runners::{Selection, TestRunnerOpt};
+use provola_core::{
+    run_process, unique_name, AvailableTests, CoreReport, Error, Executable, ProcessOpt,
+};
+
+pub mod report;
+
+/// Package name passed with `-k`. It is part of the name of report files,
+/// like `cpputest_<package>_<group>.xml`, so a package unique for each run
+/// keeps them apart from files written by other runs.
+fn make_package() -> String {
+    unique_name("cpputest")
+}
+
+/// Report files written in the working directory by any run
+pub const REPORT_FILES: &str = "cpputest_*.xml";
+
+fn report_pattern(package: &str) -> String {
+    format!("cpputest_{}_*.xml", package)
+}
+
+fn add_list_argv(mut argv: Vec<String>) -> Vec<String> {
+    argv.push("-ln".to_string());
+    argv
+}
+
+fn add_run_argv(mut argv: Vec<String>, package: &str) -> Vec<String> {
+    argv.push("-ojunit".to_string());
+    argv.push("-k".to_string());
+    argv.push(package.to_string());
+    argv
+}
+
+/// `-ln` prints `group.name` of each test, separated by spaces
+fn parse_available_tests(s: &str) -> AvailableTests {
+    let mut tests = AvailableTests::default();
+
+    for (group, name) in s.split_whitespace().filter_map(|x| x.split_once('.')) {
+        tests.push(group, name);
+    }
+
+    tests
+}
+
+/// Groups and names of the selected tests. CppUTest runs tests matching
+/// any of the groups and any of the names, so more tests than needed may
+/// be selected.
+fn make_filter_argv(selected: &[FullyQualifiedTestCase]) -> Vec<String> {
+    let mut argv: Vec<String> = Vec::new();
+
+    for (flag, value) in selected
+        .iter()
+        .map(|x| ("-g", &x.test_suite.0))
+        .chain(selected.iter().map(|x| ("-n", &x.test_case.0)))
+    {
+        let is_present = argv.chunks(2).any(|x| x[0] == flag && &x[1] == value);
+
+        if !is_present {
+            argv.push(flag.to_string());
+            argv.push(value.clone());
+        }
+    }
+
+    argv
+}
+
+fn remove_report_files(package: &str) -> Result<(), Error> {
+    for path in provola_junit::find_report_files(&report_pattern(package))? {
+        std::fs::remove_file(path)?;
+    }
+
+    Ok(())
+}
+
+/// Run an executable built with CppUTest. Reports are written by CppUTest
+/// in the working directory, one for each test group.
+pub struct TestRunner {
+    executable: Executable,
+}
+
+impl TestRunner {
+    fn generate_available_tests(&self) -> Result<AvailableTests, Error> {
+        let argv = add_list_argv((&self.executable).into());
+        let out = run_process(&argv, &ProcessOpt::default())?.out;
+        Ok(parse_available_tests(&out))
+    }
+
+    fn generate_report(&self, opt: &TestRunnerOpt) -> Result<CoreReport, Error> {
+        let selected = opt.only.find_selected(|| self.generate_available_tests())?;
+        let package = make_package();
+        let mut argv = add_run_argv((&self.executable).into(), &package);
+
+        if let Some(selected) = &selected {
+            argv.extend(make_filter_argv(selected));
+        }
+
+        run_process(&argv, &ProcessOpt::default())?;
+
+        let paths = provola_junit::find_report_files(&report_pattern(&package))?;
+        let report = provola_junit::read_report_files(&paths);
+        remove_report_files(&package)?;
+
+        let mut report = report?;
+        report::fix_report(&mut report, Some(&package));
+
+        // `-g` and `-n` select every test containing the given names
+        if let Some(selected) = &selected {
+            report.retain_selection(&Selection::from_ids(selected.iter().map(|x| x.id)));
+        }
+
+        Ok(report)
+    }
+}
+
+impl From<Executable> for TestRunner {
+    fn from(executable: Executable) -> Self {
+        TestRunner { executable }
+    }
+}
+
+impl provola_core::test_runners::TestRunner for TestRunner {
+    fn run(&self, opt: &TestRunnerOpt) -> Result<provola_core::TestResult, provola_core::Error> {
+        let report = self.generate_report(opt)?;
+        let result = report.into();
+        Ok(result)
+    }
+
+    fn list(&self, _opt: &TestRunnerOpt) -> Result<AvailableTests, Error> {
+        self.generate_available_tests()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::path::PathBuf;
+
+    fn make_exec() -> Executable {
+        let path = PathBuf::from("./examples/data/build/example");
+        Executable::from(path)
+    }
+
+    #[test]
+    fn parse_list_tests_output() {
+        let s = std::fs::read_to_string("examples/data/list_tests.txt").unwrap();
+        let list = parse_available_tests(&s);
+        assert_eq!(list.len(), 4);
+        assert_eq!(list.get(0).unwrap().test_suite.0, "Bar");
+        assert_eq!(list.get(0).unwrap().test_case.0, "Bar2");
+    }
+
+    #[test]
+    fn filter_test_case() {
+        let fqtc = FullyQualifiedTestCase::from_test_suite_test_case("Foo", "Foo1");
+        assert_eq!(make_filter_argv(&[fqtc]), vec!["-g", "Foo", "-n", "Foo1"]);
+    }
+
+    #[test]
+    fn filter_many_test_cases() {
+        let selected = [
+            FullyQualifiedTestCase::from_test_suite_test_case("Foo", "Foo1"),
+            FullyQualifiedTestCase::from_test_suite_test_case("Foo", "Foo2"),
+        ];
+        let argv = make_filter_argv(&selected);
+        assert_eq!(argv, vec!["-g", "Foo", "-n", "Foo1", "-n", "Foo2"]);
+    }
+
+    // Ignored because example must be built first
+    #[ignore]
+    #[test]
+    fn from_valid_executable() {
+        let tr = TestRunner::from(make_exec());
+        let tr: Box<dyn provola_core::test_runners::TestRunner> = Box::new(tr);
+        let tr_opt = TestRunnerOpt::default();
+        assert!(tr.run(&tr_opt).is_ok());
+    }
+
+    // Ignored because example must be built first
+    #[ignore]
+    #[test]
+    fn generate_available_tests_from_valid_executable() {
+        let tr = TestRunner::from(make_exec());
+        let list = tr.generate_available_tests().unwrap();
+        assert_eq!(list.len(), 4);
+    }
+}
@@ -0,0 +1,65 @@
+use provola_core::report::SourceLocation;
+use provola_core::{CoreFailure, CoreReport, Error};
+
+/// CppUTest writes the location at the beginning of the failure message,
+/// and replaces new lines with `{newline}`
+fn fix_failure(failure: &mut CoreFailure) {
+    let message = failure.message.replace("{newline}", "\n");
+
+    match SourceLocation::split_prefix(&message) {
+        Some((location, rest)) if failure.location.is_none() => {
+            failure.message = rest.to_string();
+            failure.location = Some(location);
+        }
+        _ => failure.message = message,
+    }
+}
+
+/// Remove the package name, added by `-k`, from classnames
+pub(crate) fn fix_report(report: &mut CoreReport, package: Option<&str>) {
+    let testcases = report
+        .testsuites
+        .iter_mut()
+        .flat_map(|x| x.testcases.iter_mut());
+
+    for test_case in testcases {
+        if let (Some(package), Some(classname)) = (package, &test_case.classname) {
+            let prefix = format!("{}.", package);
+
+            if let Some(x) = classname.strip_prefix(&prefix) {
+                test_case.classname = Some(x.to_string());
+            }
+        }
+
+        test_case.failures.iter_mut().for_each(fix_failure);
+    }
+}
+
+/// Parse a report written with `-ojunit`
+pub fn parse_report(s: &str) -> Result<CoreReport, Error> {
+    let mut report = provola_junit::parse_report(s)?;
+    fix_report(&mut report, None);
+    Ok(report)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn convert_junit_report() {
+        let s = std::fs::read_to_string("examples/data/test_report.xml").unwrap();
+        let report = parse_report(&s).unwrap();
+        insta::assert_debug_snapshot!(&report);
+    }
+
+    #[test]
+    fn remove_package_from_classname() {
+        let s = std::fs::read_to_string("examples/data/test_report.xml").unwrap();
+        let mut report = provola_junit::parse_report(&s).unwrap();
+        report.testsuites[0].testcases[0].classname = Some("provola.Foo".to_string());
+        fix_report(&mut report, Some("provola"));
+        let classname = report.testsuites[0].testcases[0].classname.as_deref();
+        assert_eq!(classname, Some("Foo"));
+    }
+}
@@ -0,0 +1,12 @@
+#include <cstring>
+#include <iostream>
+
+int main(int argc, char** argv) {
+  if (argc < 2) {
+    return 1;
+  }
+
+  std::cout << "check " << argv[1] << std::endl;
+
+  return std::strcmp(argv[1], "fail") == 0 ? 1 : 0;
+}
@@ -0,0 +1,9 @@
+#include <gtest/gtest.h>
+
+TEST(Foo, Foo1) {
+  EXPECT_EQ(2, 1 + 1);
+}
+
+TEST(Foo, Foo2) {
+  EXPECT_EQ(1, 1 + 1);
+}
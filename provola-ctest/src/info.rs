@@ -0,0 +1,171 @@
+use provola_core::Error;
+use serde::Deserialize;
+use serde_json::Value;
+
+/// Test suite of tests which set up or clean up fixtures
+pub const FIXTURES_TEST_SUITE: &str = "[fixtures]";
+/// Test suite of tests without labels
+pub const DEFAULT_TEST_SUITE: &str = "ctest";
+
+/// Output of `ctest --show-only=json-v1`
+#[derive(Deserialize, Debug)]
+struct Info {
+    #[serde(default)]
+    tests: Vec<Test>,
+}
+
+#[derive(Deserialize, Debug)]
+struct Test {
+    name: String,
+    #[serde(default)]
+    command: Vec<String>,
+    #[serde(default)]
+    properties: Vec<Property>,
+}
+
+#[derive(Deserialize, Debug)]
+struct Property {
+    name: String,
+    value: Value,
+}
+
+/// A test registered with `add_test`
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct CTest {
+    pub name: String,
+    pub command: Vec<String>,
+    pub labels: Vec<String>,
+    pub fixtures_setup: Vec<String>,
+    pub fixtures_cleanup: Vec<String>,
+    pub fixtures_required: Vec<String>,
+    /// Where the command is run, the build directory if `None`
+    pub working_directory: Option<String>,
+    /// Variables like `NAME=value`, set when the command is run
+    pub environment: Vec<String>,
+}
+
+impl CTest {
+    /// Tests are grouped by their first label. Tests setting up or cleaning
+    /// up fixtures are grouped together, because they are run by CTest
+    /// whenever a test requires their fixtures.
+    pub fn test_suite(&self) -> &str {
+        if self.is_fixture() {
+            FIXTURES_TEST_SUITE
+        } else {
+            self.labels
+                .first()
+                .map(String::as_str)
+                .unwrap_or(DEFAULT_TEST_SUITE)
+        }
+    }
+
+    pub fn is_fixture(&self) -> bool {
+        !self.fixtures_setup.is_empty() || !self.fixtures_cleanup.is_empty()
+    }
+
+    /// Variables of the environment, split in name and value
+    pub fn env(&self) -> Vec<(String, String)> {
+        self.environment
+            .iter()
+            .filter_map(|x| x.split_once('='))
+            .map(|(k, v)| (k.to_string(), v.to_string()))
+            .collect()
+    }
+}
+
+/// List properties can be written as a `;` separated string
+fn strings(value: Value) -> Vec<String> {
+    match value {
+        Value::Array(x) => x
+            .into_iter()
+            .filter_map(|x| x.as_str().map(String::from))
+            .collect(),
+        Value::String(x) => x
+            .split(';')
+            .filter(|x| !x.is_empty())
+            .map(String::from)
+            .collect(),
+        _ => vec![],
+    }
+}
+
+impl From<Test> for CTest {
+    fn from(x: Test) -> Self {
+        let mut test = CTest {
+            name: x.name,
+            command: x.command,
+            ..Default::default()
+        };
+
+        for property in x.properties {
+            match property.name.as_str() {
+                "LABELS" => test.labels = strings(property.value),
+                "FIXTURES_SETUP" => test.fixtures_setup = strings(property.value),
+                "FIXTURES_CLEANUP" => test.fixtures_cleanup = strings(property.value),
+                "FIXTURES_REQUIRED" => test.fixtures_required = strings(property.value),
+                "WORKING_DIRECTORY" => {
+                    test.working_directory = property.value.as_str().map(String::from)
+                }
+                "ENVIRONMENT" => test.environment = strings(property.value),
+                _ => {}
+            }
+        }
+
+        test
+    }
+}
+
+pub fn parse_info(s: &str) -> Result<Vec<CTest>, Error> {
+    let info: Info = serde_json::from_str(s).map_err(|e| Error::ReportParseError(Box::new(e)))?;
+    Ok(info.tests.into_iter().map(CTest::from).collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn read_example_tests() -> Vec<CTest> {
+        let s = std::fs::read_to_string("examples/data/show_only.json").unwrap();
+        parse_info(&s).unwrap()
+    }
+
+    #[test]
+    fn parse_show_only_output() {
+        let tests = read_example_tests();
+        assert_eq!(tests.len(), 5);
+        assert_eq!(tests[2].name, "db_query");
+        assert_eq!(tests[2].labels, vec!["integration", "slow"]);
+        assert_eq!(tests[2].fixtures_required, vec!["db"]);
+        assert_eq!(
+            tests[0].working_directory.as_deref(),
+            Some("/home/user/provola/provola-ctest/examples/data/build")
+        );
+        assert_eq!(
+            tests[0].env(),
+            vec![
+                ("DATA_DIR".to_string(), "/data".to_string()),
+                ("LOG".to_string(), "a=b".to_string()),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_suites_from_labels_and_fixtures() {
+        let tests = read_example_tests();
+        let test_suites: Vec<&str> = tests.iter().map(|x| x.test_suite()).collect();
+        let expected = vec![
+            "unit",
+            FIXTURES_TEST_SUITE,
+            "integration",
+            "integration",
+            FIXTURES_TEST_SUITE,
+        ];
+        assert_eq!(test_suites, expected);
+    }
+
+    #[test]
+    fn labels_as_string() {
+        let labels = strings(Value::String("unit;fast".to_string()));
+        assert_eq!(labels, vec!["unit", "fast"]);
+    }
+}
@@ -0,0 +1,437 @@
+use info::CTest;
+use provola_core::test::framework::{detect_framework, Framework};
+use provola_core::test::xunit::FullyQualifiedTestCase;
+use provola_core::test_runners::{Only, Selection, TestRunnerOpt};
+use provola_core::{
+    run_process, AvailableTests, CoreReport, Error, Executable, ProcessOpt, Reason, TempReport,
+    TestResult,
+};
+use std::path::{Path, PathBuf};
+
+mod info;
+mod report;
+
+const CTEST: &str = "ctest";
+
+type BoxedTestRunner = Box<dyn provola_core::test_runners::TestRunner>;
+
+/// Working directory and environment of a test, as CTest runs it
+fn process_opt(test: &CTest, build_dir: &Path) -> ProcessOpt {
+    let cwd = test
+        .working_directory
+        .as_ref()
+        .map_or_else(|| build_dir.to_path_buf(), PathBuf::from);
+
+    ProcessOpt {
+        cwd: Some(cwd),
+        env: test.env(),
+        ..Default::default()
+    }
+}
+
+/// Only tests running an executable without arguments are drilled into.
+/// Arguments usually select a subset of the tests (e.g. tests added by
+/// `gtest_discover_tests`), and fixtures must be handled by CTest.
+fn make_test_runner(test: &CTest, build_dir: &Path, wrapper: &[String]) -> Option<BoxedTestRunner> {
+    let path = match test.command.as_slice() {
+        [path] => PathBuf::from(path),
+        _ => return None,
+    };
+
+    if test.is_fixture() || !test.fixtures_required.is_empty() {
+        return None;
+    }
+
+    let framework = detect_framework(&path)?;
+    let executable = Executable::from(path).wrap(wrapper.to_vec());
+    log::debug!("{} is a {:?} executable", test.name, framework);
+
+    let process = process_opt(test, build_dir);
+
+    match framework {
+        Framework::GoogleTest => {
+            match provola_googletest::TestRunner::with_process(executable, None, process) {
+                Ok(x) => Some(Box::new(x)),
+                Err(e) => {
+                    log::warn!("Cannot list tests of {}, run by ctest: {}", test.name, e);
+                    None
+                }
+            }
+        }
+        Framework::Catch2 => Some(Box::new(provola_catch2::TestRunner::with_process(
+            executable, process,
+        ))),
+        _ => None,
+    }
+}
+
+fn report_from_result(result: TestResult) -> Result<CoreReport, Error> {
+    match result {
+        TestResult::Pass(Reason::Report(x)) | TestResult::Fail(Reason::Report(x)) => Ok(x),
+        _ => Err(Error::ReportUnavailable),
+    }
+}
+
+fn list_with_test_runner(runner: &BoxedTestRunner) -> Result<AvailableTests, Error> {
+    match runner.list(&TestRunnerOpt::default()) {
+        // Tests are known only after running them
+        Err(Error::TestRunnerFeatureNotAvailable(_)) => {
+            let result = runner.run(&TestRunnerOpt::default())?;
+            let report = report_from_result(result)?;
+            Ok(AvailableTests::from(&report))
+        }
+        x => x,
+    }
+}
+
+fn escape_regex(s: &str) -> String {
+    let mut escaped = String::with_capacity(s.len());
+
+    for c in s.chars() {
+        if "\\.^$|?*+()[]{}".contains(c) {
+            escaped.push('\\');
+        }
+        escaped.push(c);
+    }
+
+    escaped
+}
+
+/// Regular expression for `-R`, matching exactly the given test names
+fn make_tests_regex(names: &[&str]) -> String {
+    let names: Vec<String> = names.iter().map(|x| escape_regex(x)).collect();
+    format!("^({})$", names.join("|"))
+}
+
+/// A test registered in CTest, with the test runner of its executable if
+/// it can be drilled into
+struct Entry {
+    test: CTest,
+    runner: Option<BoxedTestRunner>,
+}
+
+/// Run all the tests registered in a CMake build tree
+pub struct TestRunner {
+    build_dir: PathBuf,
+    /// Additional arguments for ctest, like `-L unit`
+    args: Vec<String>,
+    /// Wrapper of the test executables run by provola, while ctest itself
+    /// and the tests it runs are not wrapped
+    wrapper: Vec<String>,
+}
+
+impl TestRunner {
+    fn ctest_argv(&self) -> Vec<String> {
+        let mut argv = vec![CTEST.to_string()];
+        argv.extend(self.args.iter().cloned());
+        argv
+    }
+
+    fn entries(&self) -> Result<Vec<Entry>, Error> {
+        let mut argv = self.ctest_argv();
+        argv.push("--show-only=json-v1".to_string());
+        let out = run_process(
+            &argv,
+            &ProcessOpt {
+                cwd: Some(self.build_dir.clone()),
+                ..Default::default()
+            },
+        )?
+        .out;
+
+        let entries = info::parse_info(&out)?
+            .into_iter()
+            .map(|test| {
+                let runner = make_test_runner(&test, &self.build_dir, &self.wrapper);
+                Entry { test, runner }
+            })
+            .collect();
+
+        Ok(entries)
+    }
+
+    /// Available tests, with the index of the entry they belong to
+    fn available_tests(
+        &self,
+        entries: &[Entry],
+    ) -> Result<Vec<(usize, FullyQualifiedTestCase)>, Error> {
+        let mut tests = Vec::new();
+
+        for (index, entry) in entries.iter().enumerate() {
+            match &entry.runner {
+                Some(runner) => {
+                    let list = list_with_test_runner(runner)?;
+                    tests.extend(list.iter().map(|x| (index, x.clone())));
+                }
+                None => {
+                    let test_suite = entry.test.test_suite();
+                    let fqtc = FullyQualifiedTestCase::from_test_suite_test_case(
+                        test_suite,
+                        &entry.test.name,
+                    );
+                    tests.push((index, fqtc));
+                }
+            }
+        }
+
+        Ok(tests)
+    }
+
+    fn generate_available_tests(&self) -> Result<AvailableTests, Error> {
+        let entries = self.entries()?;
+        let mut tests = AvailableTests::default();
+
+        for (_, fqtc) in self.available_tests(&entries)? {
+            tests.push(fqtc.test_suite.0, fqtc.test_case.0);
+        }
+
+        Ok(tests)
+    }
+
+    /// Run tests with CTest. Tests setting up and cleaning up fixtures are
+    /// added by CTest when needed.
+    fn run_ctest(&self, entries: &[Entry], names: &[&str]) -> Result<CoreReport, Error> {
+        let report_file = TempReport::new("ctest", "xml");
+
+        let mut argv = self.ctest_argv();
+        argv.push("--output-junit".to_string());
+        argv.push(report_file.to_string_lossy().to_string());
+        argv.push("-R".to_string());
+        argv.push(make_tests_regex(names));
+
+        let out = run_process(
+            &argv,
+            &ProcessOpt {
+                cwd: Some(self.build_dir.clone()),
+                ..Default::default()
+            },
+        )?
+        .out;
+
+        if !out.is_empty() {
+            log::debug!("{}", out);
+        }
+
+        let s = report_file.read_to_string()?;
+
+        let tests: Vec<CTest> = entries.iter().map(|x| x.test.clone()).collect();
+        report::parse_junit_report(&s, &tests)
+    }
+
+    /// Run the selected tests, or all of them. CTest tests are selected by
+    /// name, the others by the test runner of their entry.
+    fn run_selected(
+        &self,
+        entries: &[Entry],
+        selected: Option<&[(usize, FullyQualifiedTestCase)]>,
+    ) -> Result<CoreReport, Error> {
+        let is_selected =
+            |index: usize| selected.is_none_or(|x| x.iter().any(|(i, _)| *i == index));
+
+        let names: Vec<&str> = entries
+            .iter()
+            .enumerate()
+            .filter(|(index, x)| x.runner.is_none() && is_selected(*index))
+            .map(|(_, x)| x.test.name.as_str())
+            .collect();
+
+        let mut report = if names.is_empty() {
+            None
+        } else {
+            Some(self.run_ctest(entries, &names)?)
+        };
+
+        for (index, entry) in entries.iter().enumerate() {
+            let runner = match &entry.runner {
+                Some(runner) if is_selected(index) => runner,
+                _ => continue,
+            };
+
+            let selection = selected.map(|x| {
+                let ids = x.iter().filter(|(i, _)| *i == index).map(|(_, x)| x.id);
+                Selection::from_ids(ids)
+            });
+
+            let opt = TestRunnerOpt {
+                only: selection.clone().map(Only::Selection).unwrap_or_default(),
+                ..Default::default()
+            };
+
+            let mut other = report_from_result(runner.run(&opt)?)?;
+
+            // Not every test runner can select tests
+            if let Some(selection) = &selection {
+                other.retain_selection(selection);
+            }
+
+            match &mut report {
+                Some(report) => report.merge(other),
+                None => report = Some(other),
+            }
+        }
+
+        report.ok_or(Error::NothingToDo)
+    }
+
+    fn generate_report(&self, opt: &TestRunnerOpt) -> Result<CoreReport, Error> {
+        let entries = self.entries()?;
+
+        let selection = match opt.only.selection() {
+            Some(selection) => selection,
+            None => return self.run_selected(&entries, None),
+        };
+
+        let selected: Vec<(usize, FullyQualifiedTestCase)> = self
+            .available_tests(&entries)?
+            .into_iter()
+            .enumerate()
+            .filter(|(index, (_, x))| {
+                selection.is_match(*index, &x.test_suite.0, &x.test_case.0, Some(x.id))
+            })
+            .map(|(_, x)| x)
+            .collect();
+
+        if selected.is_empty() {
+            return Err(Error::NothingToDo);
+        }
+
+        self.run_selected(&entries, Some(&selected))
+    }
+}
+
+/// The first argument is the build directory, the others are passed to
+/// ctest
+impl From<Executable> for TestRunner {
+    fn from(executable: Executable) -> Self {
+        let wrapper = executable.wrapper().to_vec();
+        let mut args = Vec::from(executable.unwrapped()).into_iter();
+        let build_dir = args.next().map(PathBuf::from).unwrap_or_default();
+        let args = args.collect();
+        TestRunner {
+            build_dir,
+            args,
+            wrapper,
+        }
+    }
+}
+
+impl provola_core::test_runners::TestRunner for TestRunner {
+    fn run(&self, opt: &TestRunnerOpt) -> Result<provola_core::TestResult, provola_core::Error> {
+        let report = self.generate_report(opt)?;
+        let result = report.into();
+        Ok(result)
+    }
+
+    fn list(&self, _opt: &TestRunnerOpt) -> Result<AvailableTests, Error> {
+        self.generate_available_tests()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn make_exec() -> Executable {
+        let path = PathBuf::from("./examples/data/build");
+        Executable::from(path)
+    }
+
+    #[test]
+    fn regex_matching_exactly_test_names() {
+        let regex = make_tests_regex(&["db_query", "foo.bar(1)"]);
+        assert_eq!(regex, "^(db_query|foo\\.bar\\(1\\))$");
+    }
+
+    #[test]
+    fn drill_into_catch2_v3_executable() {
+        let path = std::env::temp_dir().join("provola_ctest_catch2_v3");
+        std::fs::write(&path, b"\x7fELF\0--list-reporters\0").unwrap();
+        let test = CTest {
+            name: "unit_tests".to_string(),
+            command: vec![path.to_string_lossy().to_string()],
+            ..Default::default()
+        };
+        let runner = make_test_runner(&test, Path::new("build"), &[]);
+        std::fs::remove_file(&path).unwrap();
+        assert!(runner.is_some());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn run_with_ctest_if_tests_cannot_be_listed() {
+        let path = std::env::temp_dir().join("provola_ctest_crashing_gtest");
+        std::fs::write(&path, b"#!/bin/sh\n# --gtest_list_tests\nkill -SEGV $$\n").unwrap();
+        let mut permissions = std::fs::metadata(&path).unwrap().permissions();
+        std::os::unix::fs::PermissionsExt::set_mode(&mut permissions, 0o755);
+        std::fs::set_permissions(&path, permissions).unwrap();
+        let test = CTest {
+            name: "unit_tests".to_string(),
+            command: vec![path.to_string_lossy().to_string()],
+            ..Default::default()
+        };
+        let runner = make_test_runner(&test, Path::new("build"), &[]);
+        std::fs::remove_file(&path).unwrap();
+        assert!(runner.is_none());
+    }
+
+    #[test]
+    fn do_not_drill_into_tests_with_arguments() {
+        let test = CTest {
+            name: "Foo.Foo1".to_string(),
+            command: vec![
+                "unit_tests".to_string(),
+                "--gtest_filter=Foo.Foo1".to_string(),
+            ],
+            ..Default::default()
+        };
+        assert!(make_test_runner(&test, Path::new("build"), &[]).is_none());
+    }
+
+    #[test]
+    fn process_as_run_by_ctest() {
+        let mut test = CTest {
+            name: "unit_tests".to_string(),
+            command: vec!["unit_tests".to_string()],
+            environment: vec!["DATA_DIR=/data".to_string()],
+            ..Default::default()
+        };
+
+        let process = process_opt(&test, Path::new("build"));
+        assert_eq!(process.cwd, Some(PathBuf::from("build")));
+        assert_eq!(
+            process.env,
+            vec![("DATA_DIR".to_string(), "/data".to_string())]
+        );
+
+        test.working_directory = Some("/data".to_string());
+        let process = process_opt(&test, Path::new("build"));
+        assert_eq!(process.cwd, Some(PathBuf::from("/data")));
+    }
+
+    #[test]
+    fn build_dir_and_ctest_arguments() {
+        let argv = vec!["build".to_string(), "-L".to_string(), "unit".to_string()];
+        let tr = TestRunner::from(Executable::Multiple(argv));
+        assert_eq!(tr.build_dir, PathBuf::from("build"));
+        assert_eq!(tr.ctest_argv(), vec!["ctest", "-L", "unit"]);
+    }
+
+    // Ignored because example must be built first
+    #[ignore]
+    #[test]
+    fn from_valid_build_dir() {
+        let tr = TestRunner::from(make_exec());
+        let tr: Box<dyn provola_core::test_runners::TestRunner> = Box::new(tr);
+        let tr_opt = TestRunnerOpt::default();
+        assert!(tr.run(&tr_opt).is_ok());
+    }
+
+    // Ignored because example must be built first
+    #[ignore]
+    #[test]
+    fn generate_available_tests_from_valid_build_dir() {
+        let tr = TestRunner::from(make_exec());
+        let list = tr.generate_available_tests().unwrap();
+        assert_eq!(list.len(), 6);
+    }
+}
@@ -0,0 +1,81 @@
+use crate::info::{CTest, DEFAULT_TEST_SUITE};
+use provola_core::report::CoreStatus;
+use provola_core::test::xunit::FullyQualifiedTestCase;
+use provola_core::{CoreReport, CoreTestSuite, Error};
+
+/// Parse a report written with `ctest --output-junit`. CTest writes all
+/// tests in a single test suite, named after the site, so they are grouped
+/// again like in the list of available tests.
+pub fn parse_junit_report(s: &str, tests: &[CTest]) -> Result<CoreReport, Error> {
+    let junit = provola_junit::parse_report(s)?;
+    let mut testsuites: Vec<CoreTestSuite> = Vec::new();
+
+    for mut test_case in junit.testsuites.into_iter().flat_map(|x| x.testcases) {
+        let name = tests
+            .iter()
+            .find(|x| x.name == test_case.name)
+            .map(|x| x.test_suite())
+            .unwrap_or(DEFAULT_TEST_SUITE);
+
+        let fqtc = FullyQualifiedTestCase::from_test_suite_test_case(name, &test_case.name);
+        test_case.fqtc = Some(fqtc.id);
+        test_case.classname = Some(name.to_string());
+
+        match testsuites.iter_mut().find(|x| x.name == name) {
+            Some(test_suite) => test_suite.testcases.push(test_case),
+            None => testsuites.push(CoreTestSuite {
+                name: name.to_string(),
+                testcases: vec![test_case],
+                ..Default::default()
+            }),
+        }
+    }
+
+    for test_suite in testsuites.iter_mut() {
+        let count = |status: CoreStatus| {
+            let d = std::mem::discriminant(&status);
+            test_suite
+                .testcases
+                .iter()
+                .filter(|x| std::mem::discriminant(&x.status) == d)
+                .count()
+        };
+
+        test_suite.failures = Some(count(CoreStatus::Fail));
+        test_suite.skipped = Some(count(CoreStatus::Skipped));
+        test_suite.tests = test_suite.testcases.len();
+        test_suite.time = test_suite
+            .testcases
+            .iter()
+            .filter_map(|x| x.time)
+            .reduce(|x, y| x + y);
+    }
+
+    Ok(CoreReport {
+        name: junit.name,
+        failures: Some(testsuites.iter().filter_map(|x| x.failures).sum()),
+        tests: Some(testsuites.iter().map(|x| x.tests).sum()),
+        time: testsuites
+            .iter()
+            .filter_map(|x| x.time)
+            .reduce(|x, y| x + y),
+        timestamp: junit.timestamp,
+        testsuites,
+        ..Default::default()
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::info::parse_info;
+
+    #[test]
+    fn convert_junit_report() {
+        let info = std::fs::read_to_string("examples/data/show_only.json").unwrap();
+        let tests = parse_info(&info).unwrap();
+        let s = std::fs::read_to_string("examples/data/test_report.xml").unwrap();
+        let report = parse_junit_report(&s, &tests).unwrap();
+        insta::assert_debug_snapshot!(&report);
+    }
+}
@@ -0,0 +1,34 @@
+#define DOCTEST_CONFIG_IMPLEMENT_WITH_MAIN
+#include <doctest/doctest.h>
+
+#include <stdexcept>
+
+TEST_SUITE("Foo") {
+  TEST_CASE("Foo1") {
+    CHECK(1 == 2);
+  }
+
+  TEST_CASE("Foo2") {
+    CHECK(true);
+  }
+}
+
+TEST_SUITE("Bar") {
+  TEST_CASE("Bar1") {
+    SUBCASE("Sub1") {
+      CHECK(true);
+    }
+  }
+
+  TEST_CASE("Bar2" * doctest::skip()) {
+    CHECK(true);
+  }
+
+  TEST_CASE("Bar3") {
+    throw std::runtime_error("unexpected");
+  }
+}
+
+TEST_CASE("Qux1") {
+  CHECK(1 + 1 == 2);
+}
@@ -0,0 +1,202 @@
+use provola_core::test::xunit::FullyQualifiedTestCase;
+use provola_core::test_runners::{Selection, TestRunnerOpt};
+use provola_core::{
+    run_process, AvailableTests, CoreReport, Error, Executable, ProcessOpt, TempReport,
+};
+use std::path::Path;
+
+pub mod report;
+
+fn add_run_argv(mut argv: Vec<String>, report_path: &Path) -> Vec<String> {
+    argv.push("--reporters=xml".to_string());
+    argv.push("--duration=true".to_string());
+    argv.push(format!("--out={}", report_path.display()));
+    argv
+}
+
+/// Filters are comma separated lists of wildcard patterns
+fn escape_filter(s: &str) -> String {
+    s.replace(',', "\\,")
+}
+
+/// Names printed by `--list-test-cases` and `--list-test-suites`, between
+/// the header and the summary
+fn parse_list(s: &str) -> Vec<String> {
+    s.lines()
+        .filter(|x| !x.starts_with("[doctest]") && !x.starts_with("====="))
+        .filter(|x| !x.trim().is_empty())
+        .map(String::from)
+        .collect()
+}
+
+pub struct TestRunner {
+    executable: Executable,
+}
+
+impl TestRunner {
+    fn list_with_argv(&self, args: &[String]) -> Result<Vec<String>, Error> {
+        let mut argv: Vec<String> = (&self.executable).into();
+        argv.extend(args.iter().cloned());
+        let out = run_process(&argv, &ProcessOpt::default())?.out;
+        Ok(parse_list(&out))
+    }
+
+    /// doctest does not tell the test suite of each test case, so test
+    /// cases are listed once for each test suite
+    fn generate_available_tests(&self) -> Result<AvailableTests, Error> {
+        let mut tests = AvailableTests::default();
+        let mut in_test_suite = Vec::new();
+
+        for test_suite in self.list_with_argv(&["--list-test-suites".to_string()])? {
+            let args = [
+                "--list-test-cases".to_string(),
+                format!("--test-suite={}", escape_filter(&test_suite)),
+            ];
+
+            for test_case in self.list_with_argv(&args)? {
+                tests.push(&test_suite, &test_case);
+                in_test_suite.push(test_case);
+            }
+        }
+
+        for test_case in self.list_with_argv(&["--list-test-cases".to_string()])? {
+            if !in_test_suite.contains(&test_case) {
+                tests.push(report::DEFAULT_TEST_SUITE, test_case);
+            }
+        }
+
+        Ok(tests)
+    }
+
+    fn generate_report(&self, opt: &TestRunnerOpt) -> Result<CoreReport, Error> {
+        let report_file = TempReport::new("doctest", "xml");
+        let mut argv = add_run_argv((&self.executable).into(), &report_file);
+
+        let selected = opt.only.find_selected(|| self.generate_available_tests())?;
+
+        if let Some(selected) = &selected {
+            argv.extend(make_filter_argv(selected));
+        }
+
+        run_process(&argv, &ProcessOpt::default())?;
+
+        let s = report_file.read_to_string()?;
+
+        let mut report = report::parse_xml_report(&s)?;
+
+        if let Some(selected) = &selected {
+            report.retain_selection(&Selection::from_ids(selected.iter().map(|x| x.id)));
+        }
+
+        Ok(report)
+    }
+}
+
+/// doctest runs tests matching any of the test cases and any of the test
+/// suites, so more tests than needed may be selected
+fn make_filter_argv(selected: &[FullyQualifiedTestCase]) -> Vec<String> {
+    let join = |names: Vec<&String>| {
+        let mut names: Vec<String> = names.into_iter().map(|x| escape_filter(x)).collect();
+        names.dedup();
+        names.join(",")
+    };
+
+    let test_cases = join(selected.iter().map(|x| &x.test_case.0).collect());
+    let mut argv = vec![format!("--test-case={}", test_cases)];
+
+    // Test cases outside of any test suite would be excluded by a filter
+    let in_test_suite = selected
+        .iter()
+        .all(|x| x.test_suite.0 != report::DEFAULT_TEST_SUITE);
+
+    if in_test_suite {
+        let test_suites = join(selected.iter().map(|x| &x.test_suite.0).collect());
+        argv.push(format!("--test-suite={}", test_suites));
+    }
+
+    argv
+}
+
+impl From<Executable> for TestRunner {
+    fn from(executable: Executable) -> Self {
+        TestRunner { executable }
+    }
+}
+
+impl provola_core::test_runners::TestRunner for TestRunner {
+    fn run(&self, opt: &TestRunnerOpt) -> Result<provola_core::TestResult, provola_core::Error> {
+        let report = self.generate_report(opt)?;
+        let result = report.into();
+        Ok(result)
+    }
+
+    fn list(&self, _opt: &TestRunnerOpt) -> Result<AvailableTests, Error> {
+        self.generate_available_tests()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::path::PathBuf;
+
+    fn make_exec() -> Executable {
+        let path = PathBuf::from("./examples/data/build/example");
+        Executable::from(path)
+    }
+
+    #[test]
+    fn parse_list_test_cases_output() {
+        let s = std::fs::read_to_string("examples/data/list_test_cases.txt").unwrap();
+        assert_eq!(parse_list(&s), vec!["Foo1", "Foo2", "Bar1", "Bar3", "Qux1"]);
+    }
+
+    #[test]
+    fn parse_list_test_suites_output() {
+        let s = std::fs::read_to_string("examples/data/list_test_suites.txt").unwrap();
+        assert_eq!(parse_list(&s), vec!["Foo", "Bar"]);
+    }
+
+    #[test]
+    fn filter_test_case_in_test_suite() {
+        let fqtc = FullyQualifiedTestCase::from_test_suite_test_case("Foo", "Foo1, Foo2");
+        let argv = make_filter_argv(&[fqtc]);
+        assert_eq!(argv, vec!["--test-case=Foo1\\, Foo2", "--test-suite=Foo"]);
+    }
+
+    #[test]
+    fn filter_test_case_without_test_suite() {
+        let fqtc =
+            FullyQualifiedTestCase::from_test_suite_test_case(report::DEFAULT_TEST_SUITE, "Qux1");
+        assert_eq!(make_filter_argv(&[fqtc]), vec!["--test-case=Qux1"]);
+    }
+
+    #[test]
+    fn filter_many_test_cases() {
+        let selected = [
+            FullyQualifiedTestCase::from_test_suite_test_case("Foo", "Foo1"),
+            FullyQualifiedTestCase::from_test_suite_test_case("Bar", "Bar1"),
+        ];
+        let argv = make_filter_argv(&selected);
+        assert_eq!(argv, vec!["--test-case=Foo1,Bar1", "--test-suite=Foo,Bar"]);
+    }
+
+    // Ignored because example must be built first
+    #[ignore]
+    #[test]
+    fn from_valid_executable() {
+        let tr = TestRunner::from(make_exec());
+        let tr: Box<dyn provola_core::test_runners::TestRunner> = Box::new(tr);
+        let tr_opt = TestRunnerOpt::default();
+        assert!(tr.run(&tr_opt).is_ok());
+    }
+
+    // Ignored because example must be built first
+    #[ignore]
+    #[test]
+    fn generate_available_tests_from_valid_executable() {
+        let tr = TestRunner::from(make_exec());
+        let list = tr.generate_available_tests().unwrap();
+        assert_eq!(list.len(), 5);
+    }
+}
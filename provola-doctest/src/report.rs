@@ -0,0 +1,309 @@
+use provola_core::report::{CoreStatus, SourceLocation};
+use provola_core::test::xunit::FullyQualifiedTestCase;
+use provola_core::{CoreFailure, CoreReport, CoreTestCase, CoreTestSuite, Error};
+use xml::attribute::OwnedAttribute;
+use xml::reader::{EventReader, XmlEvent};
+
+/// Test suite of test cases which are not inside any `TEST_SUITE`
+pub const DEFAULT_TEST_SUITE: &str = "[no test suite]";
+
+fn attribute<'a>(attributes: &'a [OwnedAttribute], name: &str) -> Option<&'a str> {
+    attributes
+        .iter()
+        .find(|x| x.name.local_name == name)
+        .map(|x| x.value.as_str())
+}
+
+fn make_location(attributes: &[OwnedAttribute]) -> Option<SourceLocation> {
+    let file = attribute(attributes, "filename")?;
+    let line = attribute(attributes, "line").and_then(|x| x.parse().ok());
+
+    Some(SourceLocation {
+        file: file.to_string(),
+        line,
+    })
+}
+
+fn parse_error(e: xml::reader::Error) -> Error {
+    Error::ReportParseError(Box::new(e))
+}
+
+/// A failed assertion, e.g. `CHECK(1 == 2)`
+#[derive(Default)]
+struct Expression {
+    ttype: String,
+    location: Option<SourceLocation>,
+    original: String,
+    expanded: String,
+}
+
+impl From<Expression> for CoreFailure {
+    fn from(x: Expression) -> Self {
+        let mut message = format!("{}( {} )", x.ttype, x.original);
+
+        if x.expanded != x.original {
+            message.push_str(&format!("\nvalues: {}( {} )", x.ttype, x.expanded));
+        }
+
+        CoreFailure {
+            ttype: x.ttype,
+            message,
+            location: x.location,
+        }
+    }
+}
+
+#[derive(Default)]
+struct Suites(Vec<CoreTestSuite>);
+
+impl Suites {
+    fn get(&mut self, name: &str) -> &mut CoreTestSuite {
+        let index = match self.0.iter().position(|x| x.name == name) {
+            Some(index) => index,
+            None => {
+                self.0.push(CoreTestSuite {
+                    name: name.to_string(),
+                    ..Default::default()
+                });
+                self.0.len() - 1
+            }
+        };
+
+        &mut self.0[index]
+    }
+}
+
+/// Parse the output of `--reporters=xml`
+pub fn parse_xml_report(s: &str) -> Result<CoreReport, Error> {
+    let mut suites = Suites::default();
+
+    let mut test_suite = DEFAULT_TEST_SUITE.to_string();
+    let mut test_case: Option<(CoreTestCase, Option<SourceLocation>)> = None;
+    let mut expression: Option<Expression> = None;
+    let mut message: Option<CoreFailure> = None;
+    let mut text: Option<String> = None;
+
+    for event in EventReader::from_str(s) {
+        match event.map_err(parse_error)? {
+            XmlEvent::StartElement {
+                name, attributes, ..
+            } => match name.local_name.as_str() {
+                "TestSuite" => {
+                    test_suite = attribute(&attributes, "name")
+                        .unwrap_or(DEFAULT_TEST_SUITE)
+                        .to_string();
+                }
+                "TestCase" => {
+                    let skipped = attribute(&attributes, "skipped") == Some("true");
+
+                    let x = CoreTestCase {
+                        name: attribute(&attributes, "name")
+                            .unwrap_or_default()
+                            .to_string(),
+                        status: if skipped {
+                            CoreStatus::Skipped
+                        } else {
+                            CoreStatus::Unknown
+                        },
+                        ..Default::default()
+                    };
+
+                    test_case = Some((x, make_location(&attributes)));
+                }
+                "Expression" if attribute(&attributes, "success") == Some("false") => {
+                    expression = Some(Expression {
+                        ttype: attribute(&attributes, "type")
+                            .unwrap_or_default()
+                            .to_string(),
+                        location: make_location(&attributes),
+                        ..Default::default()
+                    });
+                }
+                "Message" => {
+                    let ttype = attribute(&attributes, "type").unwrap_or_default();
+
+                    // Warnings do not make a test case fail
+                    if ttype.contains("ERROR") {
+                        message = Some(CoreFailure {
+                            ttype: ttype.to_string(),
+                            message: String::default(),
+                            location: make_location(&attributes),
+                        });
+                    }
+                }
+                "Original" | "Expanded" | "Exception" | "Text" => {
+                    text = Some(String::default());
+                }
+                "OverallResultsAsserts" => {
+                    if let Some((x, _)) = &mut test_case {
+                        let success = attribute(&attributes, "test_case_success");
+                        let duration = attribute(&attributes, "duration");
+
+                        x.status = match success {
+                            Some("true") => CoreStatus::Pass,
+                            Some(_) => CoreStatus::Fail,
+                            None => x.status,
+                        };
+
+                        x.time = duration
+                            .and_then(|x| x.parse::<f64>().ok())
+                            .map(std::time::Duration::from_secs_f64);
+                    }
+                }
+                _ => {}
+            },
+            XmlEvent::Characters(s) | XmlEvent::CData(s) => {
+                if let Some(text) = &mut text {
+                    text.push_str(&s);
+                }
+            }
+            XmlEvent::EndElement { name } => {
+                let name = name.local_name.as_str();
+                let s = text.take().map(|x| x.trim().to_string());
+
+                match (name, &mut test_case) {
+                    ("TestSuite", _) => {
+                        test_suite = DEFAULT_TEST_SUITE.to_string();
+                    }
+                    ("Original", _) => {
+                        if let Some(x) = &mut expression {
+                            x.original = s.unwrap_or_default();
+                        }
+                    }
+                    ("Expanded", _) => {
+                        if let Some(x) = &mut expression {
+                            x.expanded = s.unwrap_or_default();
+                        }
+                    }
+                    ("Text", _) => {
+                        if let Some(x) = &mut message {
+                            x.message = s.unwrap_or_default();
+                        }
+                    }
+                    ("Expression", Some((x, _))) => {
+                        if let Some(expression) = expression.take() {
+                            x.failures.push(expression.into());
+                        }
+                    }
+                    ("Message", Some((x, _))) => {
+                        if let Some(message) = message.take() {
+                            x.failures.push(message);
+                        }
+                    }
+                    ("Exception", Some((x, location))) => {
+                        x.failures.push(CoreFailure {
+                            ttype: "exception".to_string(),
+                            message: s.unwrap_or_default(),
+                            location: location.clone(),
+                        });
+                    }
+                    ("TestCase", Some(_)) => {
+                        if let Some((mut x, _)) = test_case.take() {
+                            if let CoreStatus::Unknown = x.status {
+                                x.status = CoreStatus::from(Some(x.failures.is_empty()));
+                            }
+
+                            let fqtc = FullyQualifiedTestCase::from_test_suite_test_case(
+                                &test_suite,
+                                &x.name,
+                            );
+                            x.fqtc = Some(fqtc.id);
+                            x.classname = Some(test_suite.clone());
+                            suites.get(&test_suite).testcases.push(x);
+                        }
+                    }
+                    _ => {}
+                }
+            }
+            _ => {}
+        }
+    }
+
+    Ok(make_core_report(suites.0))
+}
+
+fn make_core_report(mut testsuites: Vec<CoreTestSuite>) -> CoreReport {
+    for test_suite in testsuites.iter_mut() {
+        let failures = test_suite
+            .testcases
+            .iter()
+            .filter(|x| matches!(x.status, CoreStatus::Fail))
+            .count();
+
+        let skipped = test_suite
+            .testcases
+            .iter()
+            .filter(|x| matches!(x.status, CoreStatus::Skipped))
+            .count();
+
+        let time = test_suite
+            .testcases
+            .iter()
+            .filter_map(|x| x.time)
+            .reduce(|x, y| x + y);
+
+        test_suite.tests = test_suite.testcases.len();
+        test_suite.failures = Some(failures);
+        test_suite.skipped = Some(skipped);
+        test_suite.time = time;
+    }
+
+    let failures = testsuites.iter().filter_map(|x| x.failures).sum();
+    let tests = testsuites.iter().map(|x| x.tests).sum();
+    let time = testsuites
+        .iter()
+        .filter_map(|x| x.time)
+        .reduce(|x, y| x + y);
+
+    CoreReport {
+        name: Some("doctest".to_string()),
+        failures: Some(failures),
+        tests: Some(tests),
+        time,
+        testsuites,
+        ..Default::default()
+    }
+}
+
+/// Parse the output of `--reporters=junit`. doctest does not write test
+/// suites in JUnit reports, every test case is in a test suite named after
+/// the executable.
+pub fn parse_junit_report(s: &str) -> Result<CoreReport, Error> {
+    provola_junit::parse_report(s)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn read_example_file(path: &str) -> String {
+        std::fs::read_to_string(path).unwrap()
+    }
+
+    #[test]
+    fn convert_xml_report() {
+        let s = read_example_file("examples/data/test_report.xml");
+        let report = parse_xml_report(&s).unwrap();
+        insta::assert_debug_snapshot!(&report);
+    }
+
+    #[test]
+    fn convert_junit_report() {
+        let s = read_example_file("examples/data/test_report_junit.xml");
+        let report = parse_junit_report(&s).unwrap();
+        assert_eq!(report.tests, Some(5));
+        assert_eq!(report.failures, Some(2));
+    }
+
+    #[test]
+    fn failed_expression_message() {
+        let x = Expression {
+            ttype: "CHECK".to_string(),
+            original: "a == 2".to_string(),
+            expanded: "1 == 2".to_string(),
+            ..Default::default()
+        };
+        let failure = CoreFailure::from(x);
+        assert_eq!(failure.message, "CHECK( a == 2 )\nvalues: CHECK( 1 == 2 )");
+    }
+}
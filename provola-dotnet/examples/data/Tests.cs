@@ -0,0 +1,42 @@
+using System;
+using Xunit;
+using Xunit.Abstractions;
+
+namespace Example
+{
+    public class Tests
+    {
+        private readonly ITestOutputHelper output;
+
+        public Tests(ITestOutputHelper output)
+        {
+            this.output = output;
+        }
+
+        [Fact]
+        public void Foo1()
+        {
+            output.WriteLine("Hello from Foo1");
+            Assert.Equal(2, 1 + 1);
+        }
+
+        [Fact]
+        public void Foo2()
+        {
+            Assert.Equal(1, 1 + 1);
+        }
+
+        [Fact(Skip = "Not implemented")]
+        public void Foo3()
+        {
+        }
+
+        [Theory]
+        [InlineData(1)]
+        [InlineData(2)]
+        public void Bar(int a)
+        {
+            Assert.True(a > 0);
+        }
+    }
+}
@@ -0,0 +1,198 @@
+use provola_core::test::xunit::FullyQualifiedTestCase;
+use provola_core::test_runners::{Selection, TestRunnerOpt};
+use provola_core::{
+    run_process, AvailableTests, CoreReport, Error, Executable, ProcessOpt, TempReport,
+};
+
+pub mod report;
+
+const DOTNET: &str = "dotnet";
+const REPORT_FILE_NAME: &str = "provola.trx";
+
+/// Tests printed by `--list-tests`, after the header
+fn parse_available_tests(s: &str) -> AvailableTests {
+    let mut tests = AvailableTests::default();
+
+    let names = s
+        .lines()
+        .skip_while(|x| !x.starts_with("The following Tests are available:"))
+        .skip(1)
+        .map(str::trim)
+        .filter(|x| !x.is_empty());
+
+    for name in names {
+        let (classname, name) = report::split_test_name(name);
+        tests.push(classname, name);
+    }
+
+    tests
+}
+
+/// `FullyQualifiedName` does not include arguments, so every case of a
+/// parameterized test is selected. Conditions are in OR.
+fn make_filter_argv(selected: &[FullyQualifiedTestCase]) -> Vec<String> {
+    let mut conditions: Vec<String> = Vec::new();
+
+    for fqtc in selected {
+        let classname = &fqtc.test_suite.0;
+        let name = &fqtc.test_case.0;
+        let name = name.split('(').next().unwrap_or(name);
+
+        let condition = if classname.is_empty() {
+            format!("Name={}", name)
+        } else {
+            format!("FullyQualifiedName={}.{}", classname, name)
+        };
+
+        if !conditions.contains(&condition) {
+            conditions.push(condition);
+        }
+    }
+
+    vec!["--filter".to_string(), conditions.join("|")]
+}
+
+/// Run `dotnet test` on a project, a solution or a test assembly
+pub struct TestRunner {
+    targets: Vec<String>,
+    /// Command running dotnet, if any
+    wrapper: Vec<String>,
+}
+
+impl TestRunner {
+    fn dotnet_test_argv(&self) -> Vec<String> {
+        let mut argv = self.wrapper.clone();
+        argv.push(DOTNET.to_string());
+        argv.push("test".to_string());
+        argv.extend(self.targets.iter().cloned());
+        argv
+    }
+
+    fn generate_available_tests(&self) -> Result<AvailableTests, Error> {
+        let mut argv = self.dotnet_test_argv();
+        argv.push("--list-tests".to_string());
+        let out = run_process(&argv, &ProcessOpt::default())?.out;
+        Ok(parse_available_tests(&out))
+    }
+
+    fn generate_report(&self, opt: &TestRunnerOpt) -> Result<CoreReport, Error> {
+        let report_dir = TempReport::dir("dotnet")?;
+        let report_path = report_dir.join(REPORT_FILE_NAME);
+
+        let mut argv = self.dotnet_test_argv();
+        argv.push("--logger".to_string());
+        argv.push(format!("trx;LogFileName={}", REPORT_FILE_NAME));
+        argv.push("--results-directory".to_string());
+        argv.push(report_dir.to_string_lossy().to_string());
+
+        let selected = opt.only.find_selected(|| self.generate_available_tests())?;
+
+        if let Some(selected) = &selected {
+            argv.extend(make_filter_argv(selected));
+        }
+
+        let out = run_process(&argv, &ProcessOpt::default())?.out;
+
+        if !out.is_empty() {
+            log::debug!("{}", out);
+        }
+
+        // The report is not written when the build fails
+        let s = std::fs::read_to_string(&report_path).map_err(|e| match e.kind() {
+            std::io::ErrorKind::NotFound => Error::TestRunnerCrashed(report_path.clone()),
+            _ => Error::IoError(e),
+        })?;
+
+        let mut report = report::parse_report(&s)?;
+
+        if let Some(selected) = &selected {
+            report.retain_selection(&Selection::from_ids(selected.iter().map(|x| x.id)));
+        }
+
+        Ok(report)
+    }
+}
+
+impl From<Executable> for TestRunner {
+    fn from(executable: Executable) -> Self {
+        let targets = Vec::from(executable.unwrapped());
+        let wrapper = executable.wrapper().to_vec();
+        TestRunner { targets, wrapper }
+    }
+}
+
+impl provola_core::test_runners::TestRunner for TestRunner {
+    fn run(&self, opt: &TestRunnerOpt) -> Result<provola_core::TestResult, provola_core::Error> {
+        let report = self.generate_report(opt)?;
+        let result = report.into();
+        Ok(result)
+    }
+
+    fn list(&self, _opt: &TestRunnerOpt) -> Result<AvailableTests, Error> {
+        self.generate_available_tests()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::path::PathBuf;
+
+    fn make_exec() -> Executable {
+        let path = PathBuf::from("./examples/data");
+        Executable::from(path)
+    }
+
+    #[test]
+    fn parse_list_tests_output() {
+        let s = std::fs::read_to_string("examples/data/list_tests.txt").unwrap();
+        let list = parse_available_tests(&s);
+        assert_eq!(list.len(), 5);
+        assert_eq!(list.get(0).unwrap().test_suite.0, "Example.Tests");
+        assert_eq!(list.get(0).unwrap().test_case.0, "Bar(a: 1)");
+    }
+
+    #[test]
+    fn filter_parameterized_test() {
+        let fqtc = FullyQualifiedTestCase::from_test_suite_test_case("Example.Tests", "Bar(a: 1)");
+        let argv = make_filter_argv(&[fqtc]);
+        assert_eq!(
+            argv,
+            vec!["--filter", "FullyQualifiedName=Example.Tests.Bar"]
+        );
+    }
+
+    #[test]
+    fn filter_many_tests() {
+        let selected = [
+            FullyQualifiedTestCase::from_test_suite_test_case("Example.Tests", "Bar(a: 1)"),
+            FullyQualifiedTestCase::from_test_suite_test_case("Example.Tests", "Bar(a: 2)"),
+            FullyQualifiedTestCase::from_test_suite_test_case("", "Foo1"),
+        ];
+        let argv = make_filter_argv(&selected);
+        assert_eq!(
+            argv,
+            vec!["--filter", "FullyQualifiedName=Example.Tests.Bar|Name=Foo1"]
+        );
+    }
+
+    #[test]
+    fn wrapper_runs_dotnet() {
+        let wrapper = vec!["taskset".to_string(), "-c".to_string(), "2".to_string()];
+        let tr = TestRunner::from(make_exec().wrap(wrapper));
+        assert_eq!(
+            tr.dotnet_test_argv(),
+            vec!["taskset", "-c", "2", "dotnet", "test", "./examples/data"]
+        );
+    }
+
+    // Ignored because dotnet must be installed
+    #[ignore]
+    #[test]
+    fn from_valid_project() {
+        let tr = TestRunner::from(make_exec());
+        let tr: Box<dyn provola_core::test_runners::TestRunner> = Box::new(tr);
+        let tr_opt = TestRunnerOpt::default();
+        assert!(tr.run(&tr_opt).is_ok());
+    }
+}
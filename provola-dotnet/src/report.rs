@@ -0,0 +1,301 @@
+use provola_core::report::{CoreStatus, SourceLocation, Timestamp};
+use provola_core::test::xunit::FullyQualifiedTestCase;
+use provola_core::{CoreFailure, CoreReport, CoreTestCase, CoreTestSuite, Error};
+use std::collections::HashMap;
+use std::time::Duration;
+use xml::attribute::OwnedAttribute;
+use xml::reader::{EventReader, XmlEvent};
+
+fn attribute<'a>(attributes: &'a [OwnedAttribute], name: &str) -> Option<&'a str> {
+    attributes
+        .iter()
+        .find(|x| x.name.local_name == name)
+        .map(|x| x.value.as_str())
+}
+
+fn parse_error(e: xml::reader::Error) -> Error {
+    Error::ReportParseError(Box::new(e))
+}
+
+/// Durations are written like `00:00:01.2345678`
+fn parse_duration(s: &str) -> Option<Duration> {
+    let mut fields = s.split(':');
+    let hours: u64 = fields.next()?.parse().ok()?;
+    let minutes: u64 = fields.next()?.parse().ok()?;
+    let seconds: f64 = fields.next()?.parse().ok()?;
+    let seconds = Duration::try_from_secs_f64(seconds).ok()?;
+    Some(Duration::from_secs(hours * 3600 + minutes * 60) + seconds)
+}
+
+fn parse_timestamp(s: &str) -> Option<Timestamp> {
+    chrono::DateTime::parse_from_rfc3339(s)
+        .ok()
+        .map(|x| x.with_timezone(&chrono::Utc))
+}
+
+/// Split a test name like `Namespace.Class.Method(a: 1)` into the class
+/// and the method, with its arguments. Some adapters (e.g. NUnit) use just
+/// the method name, so the class may be empty.
+pub(crate) fn split_test_name(name: &str) -> (&str, &str) {
+    let end = name.find('(').unwrap_or(name.len());
+
+    match name[..end].rfind('.') {
+        Some(index) => (&name[..index], &name[index + 1..]),
+        None => ("", name),
+    }
+}
+
+/// Stack frames with debug symbols look like
+/// `at Example.Tests.Foo2() in /src/Tests.cs:line 26`
+fn find_location(stack_trace: &str) -> Option<SourceLocation> {
+    stack_trace.lines().find_map(|x| {
+        let (_, rest) = x.rsplit_once(" in ")?;
+        let (file, line) = rest.rsplit_once(":line ")?;
+
+        Some(SourceLocation {
+            file: file.to_string(),
+            line: line.trim().parse().ok(),
+        })
+    })
+}
+
+fn parse_status(outcome: &str) -> CoreStatus {
+    match outcome {
+        "Passed" | "PassedButRunAborted" | "Warning" => CoreStatus::Pass,
+        "NotExecuted" | "NotRunnable" | "Disconnected" => CoreStatus::Skipped,
+        "Inconclusive" | "Pending" | "InProgress" => CoreStatus::Ignored,
+        _ => CoreStatus::Fail,
+    }
+}
+
+/// A `UnitTestResult` element
+#[derive(Default)]
+struct UnitTestResult {
+    test_id: String,
+    test_name: String,
+    outcome: String,
+    duration: Option<Duration>,
+    std_out: Option<String>,
+    std_err: Option<String>,
+    message: Option<String>,
+    stack_trace: Option<String>,
+}
+
+impl UnitTestResult {
+    fn failure(&mut self) -> Option<CoreFailure> {
+        if self.message.is_none() && self.stack_trace.is_none() {
+            return None;
+        }
+
+        let ttype = match self.outcome.as_str() {
+            "Failed" => "failure".to_string(),
+            x => x.to_lowercase(),
+        };
+
+        let stack_trace = self.stack_trace.take().unwrap_or_default();
+        let mut message = self.message.take().unwrap_or_default();
+
+        if !stack_trace.is_empty() {
+            message.push('\n');
+            message.push_str(&stack_trace);
+        }
+
+        Some(CoreFailure {
+            ttype,
+            message,
+            location: find_location(&stack_trace),
+        })
+    }
+
+    fn into_core_test_case(mut self, classname: &str) -> CoreTestCase {
+        let failures = self.failure().into_iter().collect();
+        let (_, name) = split_test_name(&self.test_name);
+        let fqtc = FullyQualifiedTestCase::from_test_suite_test_case(classname, name);
+        let status = parse_status(&self.outcome);
+
+        CoreTestCase {
+            fqtc: Some(fqtc.id),
+            classname: Some(classname.to_string()),
+            name: name.to_string(),
+            status,
+            time: self.duration,
+            failures,
+            system_out: self.std_out,
+            system_err: self.std_err,
+            repeat: None,
+            location: None,
+        }
+    }
+}
+
+/// Parse a Visual Studio TRX report, written by `dotnet test --logger trx`
+pub fn parse_report(s: &str) -> Result<CoreReport, Error> {
+    let mut name = None;
+    let mut timestamp = None;
+    let mut results: Vec<UnitTestResult> = Vec::new();
+    // Class of each test, from test definitions
+    let mut classnames: HashMap<String, String> = HashMap::new();
+
+    let mut result: Option<UnitTestResult> = None;
+    let mut definition: Option<String> = None;
+    let mut text: Option<String> = None;
+
+    for event in EventReader::from_str(s) {
+        match event.map_err(parse_error)? {
+            XmlEvent::StartElement {
+                name: element,
+                attributes,
+                ..
+            } => match element.local_name.as_str() {
+                "TestRun" => {
+                    name = attribute(&attributes, "name").map(String::from);
+                }
+                "Times" => {
+                    timestamp = attribute(&attributes, "start").and_then(parse_timestamp);
+                }
+                "UnitTestResult" => {
+                    let get = |name| attribute(&attributes, name).unwrap_or_default().to_string();
+
+                    result = Some(UnitTestResult {
+                        test_id: get("testId"),
+                        test_name: get("testName"),
+                        outcome: get("outcome"),
+                        duration: attribute(&attributes, "duration").and_then(parse_duration),
+                        ..Default::default()
+                    });
+                }
+                "UnitTest" => {
+                    definition = attribute(&attributes, "id").map(String::from);
+                }
+                "TestMethod" => {
+                    let classname = attribute(&attributes, "className");
+
+                    if let (Some(id), Some(classname)) = (&definition, classname) {
+                        classnames.insert(id.clone(), classname.to_string());
+                    }
+                }
+                "StdOut" | "StdErr" | "Message" | "StackTrace" => {
+                    text = Some(String::default());
+                }
+                _ => {}
+            },
+            XmlEvent::Characters(s) | XmlEvent::CData(s) => {
+                if let Some(text) = &mut text {
+                    text.push_str(&s);
+                }
+            }
+            XmlEvent::EndElement { name: element } => {
+                let s = text.take();
+
+                match (element.local_name.as_str(), &mut result) {
+                    ("StdOut", Some(x)) => x.std_out = s,
+                    ("StdErr", Some(x)) => x.std_err = s,
+                    ("Message", Some(x)) => x.message = s,
+                    ("StackTrace", Some(x)) => x.stack_trace = s,
+                    ("UnitTestResult", _) => results.extend(result.take()),
+                    ("UnitTest", _) => definition = None,
+                    _ => {}
+                }
+            }
+            _ => {}
+        }
+    }
+
+    let mut testsuites: Vec<CoreTestSuite> = Vec::new();
+
+    for x in results {
+        let classname = match split_test_name(&x.test_name) {
+            ("", _) => classnames.get(&x.test_id).cloned().unwrap_or_default(),
+            (classname, _) => classname.to_string(),
+        };
+
+        let test_case = x.into_core_test_case(&classname);
+
+        match testsuites.iter_mut().find(|x| x.name == classname) {
+            Some(test_suite) => test_suite.testcases.push(test_case),
+            None => testsuites.push(CoreTestSuite {
+                name: classname,
+                testcases: vec![test_case],
+                ..Default::default()
+            }),
+        }
+    }
+
+    Ok(make_core_report(name, timestamp, testsuites))
+}
+
+fn make_core_report(
+    name: Option<String>,
+    timestamp: Option<Timestamp>,
+    mut testsuites: Vec<CoreTestSuite>,
+) -> CoreReport {
+    for test_suite in testsuites.iter_mut() {
+        let count = |status: CoreStatus| {
+            let d = std::mem::discriminant(&status);
+            test_suite
+                .testcases
+                .iter()
+                .filter(|x| std::mem::discriminant(&x.status) == d)
+                .count()
+        };
+
+        test_suite.failures = Some(count(CoreStatus::Fail));
+        test_suite.skipped = Some(count(CoreStatus::Skipped));
+        test_suite.tests = test_suite.testcases.len();
+        test_suite.time = test_suite
+            .testcases
+            .iter()
+            .filter_map(|x| x.time)
+            .reduce(|x, y| x + y);
+    }
+
+    CoreReport {
+        name,
+        failures: Some(testsuites.iter().filter_map(|x| x.failures).sum()),
+        tests: Some(testsuites.iter().map(|x| x.tests).sum()),
+        time: testsuites
+            .iter()
+            .filter_map(|x| x.time)
+            .reduce(|x, y| x + y),
+        timestamp,
+        testsuites,
+        ..Default::default()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn convert_trx_report() {
+        let s = std::fs::read_to_string("examples/data/test_report.trx").unwrap();
+        let report = parse_report(&s).unwrap();
+        insta::assert_debug_snapshot!(&report);
+    }
+
+    #[test]
+    fn split_test_name_with_arguments() {
+        let (classname, name) = split_test_name("Example.Tests.Bar(s: \"a.b\")");
+        assert_eq!(classname, "Example.Tests");
+        assert_eq!(name, "Bar(s: \"a.b\")");
+    }
+
+    #[test]
+    fn split_test_name_without_class() {
+        assert_eq!(split_test_name("Foo1"), ("", "Foo1"));
+    }
+
+    #[test]
+    fn parse_duration_with_fractional_seconds() {
+        let duration = parse_duration("01:02:03.5000000");
+        assert_eq!(duration, Some(Duration::from_millis(3_723_500)));
+    }
+
+    #[test]
+    fn location_from_stack_trace() {
+        let stack_trace = "   at Example.Tests.Foo2() in /src/Tests.cs:line 26";
+        let location = find_location(stack_trace).unwrap();
+        assert_eq!(location.to_string(), "/src/Tests.cs:26");
+    }
+}
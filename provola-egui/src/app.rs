@@ -1,4 +1,6 @@
-use super::{ActionMessage, ActionSender, FeedbackMessage, FeedbackReceiver, GuiConfig};
+use super::{
+    ActionConfig, ActionMessage, ActionSender, FeedbackMessage, FeedbackReceiver, GuiConfig,
+};
 use crate::tests_explorer;
 use crossbeam_channel::select;
 use eframe::egui::Color32;
@@ -7,14 +9,22 @@ use egui::*;
 
 use provola_core::report::CoreStatus;
 use provola_core::test::xunit::{FullyQualifiedTestCase, TestSuite};
+use provola_core::test_runners::RerunFailed;
 use provola_core::*;
+use provola_testrunners::{detect_test_runner_type, TestRunnerInfo};
 
+use std::path::PathBuf;
 use std::time::Duration;
 
 #[derive(Default)]
 pub struct State {
     last_result: Option<TestResult>,
     available_tests: Option<AvailableTests>,
+    /// Path of the test runner, as edited by the user
+    test_runner_path: String,
+    /// Tests finished so far, while tests are running
+    partial_report: Option<CoreReport>,
+    running_test: Option<FullyQualifiedTestCase>,
 }
 
 pub struct ProvolaGuiApp {
@@ -33,6 +43,13 @@ fn merge(config: &mut GuiConfig, stored_config: GuiConfig) {
     if config.action.is_none() {
         config.action = stored_config.action;
     }
+    if config.rerun_failed.is_none() {
+        config.rerun_failed = stored_config.rerun_failed;
+    }
+
+    if config.editor.is_none() {
+        config.editor = stored_config.editor;
+    }
 }
 
 impl ProvolaGuiApp {
@@ -46,6 +63,12 @@ impl ProvolaGuiApp {
         if let Some(stored_config) = stored_config {
             merge(&mut self.config, stored_config);
         }
+
+        if let Some(ActionConfig::TestRunner(info, _)) = &self.config.action {
+            if let Executable::Simple(path) = info.exec.unwrapped() {
+                self.state.test_runner_path = path.to_string_lossy().to_string();
+            }
+        }
     }
 
     fn send(&mut self, msg: ActionMessage) {
@@ -59,8 +82,12 @@ impl ProvolaGuiApp {
             FeedbackMessage::AvailableTests(tests) => {
                 state.available_tests = Some(tests);
             }
+            FeedbackMessage::Event(event) => {
+                handle_event(state, event);
+            }
             FeedbackMessage::Result(new_result) => {
                 state.last_result = Some(new_result);
+                state.partial_report = None;
             }
             FeedbackMessage::WatchedChanged => {
                 // Avoid running all tests when watch is false.
@@ -88,15 +115,22 @@ impl ProvolaGuiApp {
             },
             default(Duration::from_millis(1)) => {}
         }
+
+        // Running tests may send many events between two frames
+        while let Ok(msg) = self.r.try_recv() {
+            self.handle_message(msg);
+        }
     }
 
     fn action_run_all(&mut self) {
         self.state.last_result = None;
+        self.state.partial_report = None;
         self.send(ActionMessage::RunAll);
     }
 
     fn action_run_selected(&mut self) {
         self.state.last_result = None;
+        self.state.partial_report = None;
         self.send(ActionMessage::RunSelected);
     }
 
@@ -106,6 +140,29 @@ impl ProvolaGuiApp {
         self.send(ActionMessage::ReqAvailableTests);
     }
 
+    /// Use the test runner chosen by the user, detecting its type
+    fn action_open_test_runner(&mut self, config: &mut GuiConfig) {
+        let path = PathBuf::from(self.state.test_runner_path.trim());
+
+        let trt = match detect_test_runner_type(&path) {
+            Some(trt) => trt,
+            None => {
+                // TODO Show error
+                log::error!("Cannot detect test runner type of {}", path.display());
+                return;
+            }
+        };
+
+        log::info!("Detected test runner type: {}", trt);
+
+        if path.exists() {
+            config.watch_path = Some(path.clone());
+        }
+
+        let info = TestRunnerInfo::new(Executable::from(path), trt);
+        config.action = Some(ActionConfig::TestRunner(info, Default::default()));
+    }
+
     fn action_setup(&mut self, frame: &epi::Frame) {
         // This message is needed to setup the working thread, so it knows
         // how app is configured and how to request a UI repaint.
@@ -171,6 +228,17 @@ impl epi::App for ProvolaGuiApp {
             ui.with_layout(Layout::top_down_justified(Align::Min), |ui| {
                 result_info(ui, &self.state.last_result);
 
+                if let Some(fqtc) = &self.state.running_test {
+                    ui.label(format!("Running {}", fqtc));
+                }
+
+                ui.label("Test runner");
+                ui.text_edit_singleline(&mut self.state.test_runner_path);
+
+                if ui.button("Open").clicked() {
+                    self.action_open_test_runner(&mut new_config);
+                }
+
                 if ui.button("Run all").clicked() {
                     self.action_run_all();
                 }
@@ -184,6 +252,10 @@ impl epi::App for ProvolaGuiApp {
                 }
 
                 ui.checkbox(&mut new_config.watch, "Watch");
+
+                rerun_failed_combo(ui, &mut new_config.rerun_failed);
+
+                editor_edit(ui, &mut new_config.editor);
             });
         });
 
@@ -191,17 +263,26 @@ impl epi::App for ProvolaGuiApp {
         CentralPanel::default().show(ctx, |ui| {
             let action_s = self.s.clone();
 
+            let partial_result = || self.state.partial_report.clone().map(TestResult::from);
             let test_result = merge_available_tests_and_result(
-                &self.state.last_result,
+                &self.state.last_result.clone().or_else(partial_result),
                 &self.state.available_tests,
             );
 
-            tests_explorer::show(ui, action_s, test_result.as_ref());
+            let editor = self.config.editor.as_deref();
+            let editor = editor.unwrap_or(editor::DEFAULT_EDITOR_TEMPLATE);
+
+            tests_explorer::show(ui, action_s, editor, test_result.as_ref());
         });
 
         if new_config != self.config {
-            self.send(ActionMessage::UpdateConfig(self.config.clone()));
+            let action_changed = new_config.action != self.config.action;
+            self.send(ActionMessage::UpdateConfig(new_config.clone()));
             self.config = new_config;
+
+            if action_changed {
+                self.action_req_available_tests();
+            }
         }
     }
 }
@@ -218,6 +299,69 @@ impl ProvolaGuiApp {
     }
 }
 
+/// Update the partial report while tests are running
+fn handle_event(state: &mut State, event: TestEvent) {
+    match event {
+        TestEvent::RunStarted(_) => {
+            // A test runner may run tests in many steps, like failed first
+            state.partial_report.get_or_insert_with(Default::default);
+        }
+        TestEvent::TestStarted(fqtc) => {
+            state.running_test = Some(fqtc);
+        }
+        TestEvent::TestFinished { fqtc, status, time } => {
+            let test_case = CoreTestCase {
+                fqtc: Some(fqtc.id),
+                name: fqtc.test_case.0.clone(),
+                status,
+                time,
+                ..Default::default()
+            };
+
+            if let Some(report) = &mut state.partial_report {
+                report.push_test_case(&fqtc.test_suite.0, test_case);
+            }
+
+            state.running_test = None;
+        }
+        TestEvent::Output(_) => {}
+        TestEvent::RunFinished => {
+            state.running_test = None;
+        }
+    }
+}
+
+fn editor_edit(ui: &mut Ui, editor: &mut Option<String>) {
+    let mut template = editor
+        .clone()
+        .unwrap_or_else(|| editor::DEFAULT_EDITOR_TEMPLATE.to_string());
+
+    ui.label("Editor")
+        .on_hover_text("{file} and {line} are replaced with the source location");
+
+    if ui.text_edit_singleline(&mut template).changed() {
+        *editor = Some(template);
+    }
+}
+
+fn rerun_failed_text(mode: Option<RerunFailed>) -> &'static str {
+    match mode {
+        None => "All tests",
+        Some(RerunFailed::Only) => "Failed only",
+        Some(RerunFailed::First) => "Failed first",
+    }
+}
+
+fn rerun_failed_combo(ui: &mut Ui, mode: &mut Option<RerunFailed>) {
+    ComboBox::from_label("Run")
+        .selected_text(rerun_failed_text(*mode))
+        .show_ui(ui, |ui| {
+            for x in [None, Some(RerunFailed::Only), Some(RerunFailed::First)] {
+                ui.selectable_value(mode, x, rerun_failed_text(x));
+            }
+        });
+}
+
 fn result_info(ui: &mut Ui, result: &Option<TestResult>) -> Response {
     let text = text_from_result(result);
     let color = color_from_result(result);
@@ -291,7 +435,7 @@ fn generate_complete_report(partial: &CoreReport, available: &AvailableTests) ->
         if let Some(report_test_suite) = find_test_suite(&mut full, &fqtc.test_suite) {
             // Test suite already exist, but we have to check if we need
             // to add this test case.
-            let report_test_case = find_test_case(&report_test_suite, &fqtc);
+            let report_test_case = find_test_case(report_test_suite, fqtc);
 
             if report_test_case.is_none() {
                 // Test case does not exist in report, we need to add it
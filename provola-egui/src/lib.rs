@@ -7,9 +7,9 @@ use crate::server::Server;
 use crossbeam_channel::{bounded, Receiver, Sender};
 use eframe::epi::backend::RepaintSignal;
 use provola_core::test::xunit::FullyQualifiedTestCaseId;
-use provola_core::test_runners::TestRunnerOpt;
+use provola_core::test_runners::{RerunFailed, RerunFailedTestRunner, TestRunnerOpt};
 use provola_core::*;
-use provola_core::{Action, AvailableTests, Error, TestResult};
+use provola_core::{Action, AvailableTests, Error, TestEvent, TestResult};
 use provola_testrunners::make_test_runner;
 use provola_testrunners::TestRunnerInfo;
 use std::path::PathBuf;
@@ -31,6 +31,7 @@ enum ActionMessage {
 
 enum FeedbackMessage {
     AvailableTests(AvailableTests),
+    Event(TestEvent),
     Result(TestResult),
     WatchedChanged,
     Error(String),
@@ -87,8 +88,9 @@ pub fn run(opt: GuiConfig) -> Result<(), Error> {
 
 #[derive(serde::Deserialize, serde::Serialize, Clone, Debug, PartialEq, Eq)]
 pub enum ActionConfig {
-    BuildTestInputOutput(Language, Source, TestDataIn, TestDataOut),
+    BuildTestInputOutput(Language, Source, TestDataIn, TestDataOut, Vec<String>),
     TestRunner(TestRunnerInfo, TestRunnerOpt),
+    Composite(Vec<(String, ActionConfig)>, Schedule),
 }
 
 #[derive(serde::Deserialize, serde::Serialize, Default, Clone, Debug, PartialEq, Eq)]
@@ -96,6 +98,45 @@ pub struct GuiConfig {
     pub watch_path: Option<PathBuf>,
     pub watch: bool,
     pub action: Option<ActionConfig>,
+    /// Run tests which failed in the last run before, or instead of, the others
+    #[serde(default)]
+    pub rerun_failed: Option<RerunFailed>,
+    /// Command opening a source file, like `$EDITOR +{line} {file}`
+    #[serde(default)]
+    pub editor: Option<String>,
+}
+
+fn make_action(cfg: &ActionConfig, rerun_failed: Option<RerunFailed>) -> Result<Action, Error> {
+    let action = match cfg {
+        ActionConfig::BuildTestInputOutput(lang, source, input, output, wrapper) => {
+            Action::BuildTestInputOutput(
+                *lang,
+                source.clone(),
+                input.clone(),
+                output.clone(),
+                wrapper.clone(),
+            )
+        }
+        ActionConfig::TestRunner(info, tr_opt) => {
+            let mut test_runner = make_test_runner(info.clone())?;
+
+            if let Some(mode) = rerun_failed {
+                let key = Vec::from(&info.exec).join(" ");
+                test_runner = Box::new(RerunFailedTestRunner::new(test_runner, mode, &key));
+            }
+
+            Action::TestRunner(test_runner, tr_opt.clone())
+        }
+        ActionConfig::Composite(actions, schedule) => {
+            let actions = actions
+                .iter()
+                .map(|(name, cfg)| Ok((name.clone(), make_action(cfg, rerun_failed)?)))
+                .collect::<Result<_, Error>>()?;
+            Action::Composite(actions, *schedule)
+        }
+    };
+
+    Ok(action)
 }
 
 impl TryFrom<&GuiConfig> for Action {
@@ -103,17 +144,6 @@ impl TryFrom<&GuiConfig> for Action {
 
     fn try_from(opt: &GuiConfig) -> Result<Self, Error> {
         let action_cfg = opt.action.as_ref().ok_or(Error::NothingToDo)?;
-
-        let action = match action_cfg {
-            ActionConfig::BuildTestInputOutput(lang, source, input, output) => {
-                Action::BuildTestInputOutput(*lang, source.clone(), input.clone(), output.clone())
-            }
-            ActionConfig::TestRunner(info, opt) => {
-                let test_runner = make_test_runner(info.clone());
-                Action::TestRunner(test_runner?, opt.clone())
-            }
-        };
-
-        Ok(action)
+        make_action(action_cfg, opt.rerun_failed)
     }
 }
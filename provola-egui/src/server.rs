@@ -100,6 +100,7 @@ impl Server {
             let watch_opt = WatchOptions {
                 file: w,
                 debounce_time: Duration::from_secs(1),
+                ignore: vec![],
             };
 
             // TODO watch must be stopped when file_to_watch changes
@@ -117,7 +118,9 @@ impl Server {
         let opt = self.opt.as_ref().ok_or(Error::NoResult)?;
 
         let action = Action::try_from(opt)?;
-        let result = action.run()?;
+        let result = action.run_with_events(&mut |event| {
+            self.feedback_s.send(FeedbackMessage::Event(event));
+        })?;
 
         self.feedback_s.send(FeedbackMessage::Result(result));
 
@@ -1,12 +1,19 @@
 use crate::{ActionMessage, ActionSender};
 use eframe::egui::*;
 use provola_core::{
-    report::CoreStatus, CoreFailure, CoreReport, CoreTestCase, CoreTestSuite, Reason, TestResult,
+    coverage::Coverage,
+    report::{CoreStatus, SourceLocation},
+    CoreFailure, CoreReport, CoreTestCase, CoreTestSuite, Reason, TestResult,
 };
 
-pub(crate) fn show(ui: &mut Ui, action_s: ActionSender, test_result: Option<&TestResult>) {
+pub(crate) fn show(
+    ui: &mut Ui,
+    action_s: ActionSender,
+    editor: &str,
+    test_result: Option<&TestResult>,
+) {
     if let Some(test_result) = test_result {
-        show_result(ui, action_s, test_result);
+        show_result(ui, action_s, editor, test_result);
     } else {
         show_no_result(ui);
     }
@@ -17,29 +24,29 @@ fn show_no_result(ui: &mut Ui) {
 }
 
 // TODO Merge test_result/available_tests to show even ignored/disabled tests
-fn show_result(ui: &mut Ui, action_s: ActionSender, test_result: &TestResult) {
+fn show_result(ui: &mut Ui, action_s: ActionSender, editor: &str, test_result: &TestResult) {
     match test_result {
-        TestResult::Pass(result) => show_result_pass(ui, action_s, result),
-        TestResult::Fail(result) => show_result_fail(ui, action_s, result),
+        TestResult::Pass(result) => show_result_pass(ui, action_s, editor, result),
+        TestResult::Fail(result) => show_result_fail(ui, action_s, editor, result),
     }
 }
 
-fn show_result_pass(ui: &mut Ui, action_s: ActionSender, reason: &Reason) {
+fn show_result_pass(ui: &mut Ui, action_s: ActionSender, editor: &str, reason: &Reason) {
     // TODO summary
-    show_reason(ui, action_s, reason);
+    show_reason(ui, action_s, editor, reason);
 }
 
-fn show_result_fail(ui: &mut Ui, action_s: ActionSender, reason: &Reason) {
+fn show_result_fail(ui: &mut Ui, action_s: ActionSender, editor: &str, reason: &Reason) {
     // TODO summary
-    show_reason(ui, action_s, reason);
+    show_reason(ui, action_s, editor, reason);
 }
 
-fn show_reason(ui: &mut Ui, action_s: ActionSender, reason: &Reason) {
+fn show_reason(ui: &mut Ui, action_s: ActionSender, editor: &str, reason: &Reason) {
     match reason {
         Reason::Unknown => show_reason_unknown(ui),
         Reason::Generic(msg) => show_reason_generic(ui, msg),
         Reason::NotExpected { actual, expected } => show_reason_not_expected(ui, actual, expected),
-        Reason::Report(report) => show_reason_report(ui, action_s, report),
+        Reason::Report(report) => show_reason_report(ui, action_s, editor, report),
     }
 }
 
@@ -55,20 +62,81 @@ fn show_reason_not_expected(_ui: &mut Ui, _actual: &str, _expected: &str) {
     // TODO
 }
 
-fn show_reason_report(ui: &mut Ui, action_s: ActionSender, report: &CoreReport) {
+fn show_reason_report(ui: &mut Ui, action_s: ActionSender, editor: &str, report: &CoreReport) {
     if let Some(_name) = &report.name {
         // log::debug!("report: {}", &name);
     }
 
-    for testsuite in &report.testsuites {
-        show_testsuite(ui, action_s.clone(), testsuite);
+    if let Some(repeat) = &report.repeat {
+        let mut text = format!("Repeated {} times", repeat.runs);
+
+        if let Some(seed) = repeat.seed {
+            text.push_str(&format!(" with seed {}", seed));
+        }
+
+        ui.label(text);
+    }
+
+    // Test suites of each action of a composite action are grouped together
+    for group in report.testsuites.chunk_by(|x, y| x.package == y.package) {
+        match &group[0].package {
+            Some(name) => show_group(ui, action_s.clone(), editor, name, group),
+            None => {
+                for testsuite in group {
+                    show_testsuite(ui, action_s.clone(), editor, testsuite);
+                }
+            }
+        }
+    }
+
+    if let Some(coverage) = &report.coverage {
+        show_coverage(ui, coverage);
+    }
+}
+
+fn show_coverage(ui: &mut Ui, coverage: &Coverage) {
+    let summary = coverage.summary();
+    let mut title = format!("Coverage: lines {}", summary.lines);
+
+    if let Some(delta) = coverage.lines_delta() {
+        title.push_str(&format!(" ({:+.1}%)", delta));
     }
+
+    CollapsingHeader::new(title).show(ui, |ui| {
+        for file in &coverage.files {
+            let summary = file.summary();
+            ui.label(format!(
+                "{}: lines {}, branches {}",
+                file.file, summary.lines, summary.branches
+            ));
+        }
+    });
+}
+
+fn show_group(
+    ui: &mut Ui,
+    action_s: ActionSender,
+    editor: &str,
+    name: &str,
+    testsuites: &[CoreTestSuite],
+) {
+    let ok = testsuites.iter().all(|x| x.failures.unwrap_or(0) == 0);
+    let name = symbol_and_name(Some(ok).into(), name);
+
+    CollapsingHeader::new(name)
+        .default_open(true)
+        .show(ui, |ui| {
+            for testsuite in testsuites {
+                show_testsuite(ui, action_s.clone(), editor, testsuite);
+            }
+        });
 }
 
 fn symbol(status: CoreStatus) -> &'static str {
     match status {
         CoreStatus::Pass => "✔",
         CoreStatus::Fail => "✖",
+        CoreStatus::Skipped | CoreStatus::Ignored => "-",
         _ => "?",
     }
 }
@@ -77,6 +145,7 @@ fn from_status_to_color(status: CoreStatus) -> Color32 {
     match status {
         CoreStatus::Pass => Color32::GREEN,
         CoreStatus::Fail => Color32::RED,
+        CoreStatus::Skipped | CoreStatus::Ignored => Color32::YELLOW,
         _ => Color32::LIGHT_GRAY,
     }
 }
@@ -87,7 +156,7 @@ fn symbol_and_name(ok: CoreStatus, name: &str) -> RichText {
     RichText::new(text).color(color)
 }
 
-fn show_testsuite(ui: &mut Ui, action_s: ActionSender, testsuite: &CoreTestSuite) {
+fn show_testsuite(ui: &mut Ui, action_s: ActionSender, editor: &str, testsuite: &CoreTestSuite) {
     let ok = testsuite.failures.map(|x| x == 0);
     let name = symbol_and_name(ok.into(), &testsuite.name);
 
@@ -95,24 +164,55 @@ fn show_testsuite(ui: &mut Ui, action_s: ActionSender, testsuite: &CoreTestSuite
         .default_open(true)
         .show(ui, |ui| {
             for testcase in &testsuite.testcases {
-                show_testcase(ui, action_s.clone(), testcase);
+                show_testcase(ui, action_s.clone(), editor, testcase);
             }
+
+            show_output(ui, "Output", &testsuite.system_out);
+            show_output(ui, "Errors", &testsuite.system_err);
         });
 }
 
-fn show_testcase(ui: &mut Ui, action_s: ActionSender, testcase: &CoreTestCase) {
+fn show_testcase(ui: &mut Ui, action_s: ActionSender, editor: &str, testcase: &CoreTestCase) {
     let status = testcase.status;
-    let name = symbol_and_name(status, &testcase.name);
+    let is_flaky = testcase.repeat.as_ref().is_some_and(|x| x.is_flaky());
+
+    // Flaky tests are failed, but they must be told apart from broken ones
+    let name = if is_flaky {
+        let text = format!("{} {} (flaky)", symbol(status), testcase.name);
+        RichText::new(text).color(Color32::YELLOW)
+    } else {
+        symbol_and_name(status, &testcase.name)
+    };
 
     let res = CollapsingHeader::new(name)
         .default_open(false)
         .show(ui, |ui| {
+            if let Some(stats) = &testcase.repeat {
+                ui.label(stats.to_string());
+            }
+
             for failure in &testcase.failures {
-                show_failure(ui, failure);
+                show_failure(ui, editor, failure);
             }
+
+            show_output(ui, "Output", &testcase.system_out);
+            show_output(ui, "Errors", &testcase.system_err);
         });
 
+    // Where the test case is defined, or where it failed
+    let location = testcase
+        .location
+        .as_ref()
+        .or_else(|| testcase.failures.iter().find_map(|x| x.location.as_ref()));
+
     res.header_response.context_menu(|ui| {
+        if let Some(location) = location {
+            if ui.button("Open in editor").clicked() {
+                ui.close_menu();
+                open_in_editor(editor, location);
+            }
+        }
+
         if ui.button("Enable only this").clicked() {
             let fqtc = testcase.fqtc.unwrap();
             ui.close_menu();
@@ -124,7 +224,32 @@ fn show_testcase(ui: &mut Ui, action_s: ActionSender, testcase: &CoreTestCase) {
     });
 }
 
-fn show_failure(ui: &mut Ui, failure: &CoreFailure) {
+/// Captured stdout or stderr, collapsed because it can be long
+fn show_output(ui: &mut Ui, title: &str, output: &Option<String>) {
+    if let Some(output) = output {
+        CollapsingHeader::new(title)
+            .default_open(false)
+            .show(ui, |ui| {
+                ui.label(RichText::new(output).monospace());
+            });
+    }
+}
+
+fn open_in_editor(editor: &str, location: &SourceLocation) {
+    if let Err(e) = provola_core::editor::open_in_editor(editor, location) {
+        log::error!("Cannot open {}: {}", location, e);
+    }
+}
+
+fn show_failure(ui: &mut Ui, editor: &str, failure: &CoreFailure) {
+    if let Some(location) = &failure.location {
+        let text = RichText::new(location.to_string()).monospace().underline();
+        let link = Label::new(text).sense(Sense::click());
+
+        if ui.add(link).on_hover_text("Open in editor").clicked() {
+            open_in_editor(editor, location);
+        }
+    }
     let msg = &failure.message;
     ui.label(msg);
 }
@@ -0,0 +1,360 @@
+//! Writer of JUnit XML reports, as read by CI servers (e.g. Jenkins and
+//! GitLab). The schema is the one of Ant's JUnit task.
+
+use provola_core::report::{CoreStatus, Duration, Timestamp};
+use provola_core::{CoreReport, CoreTestCase, CoreTestSuite, ReporterError, TestResult};
+use std::io::Write;
+use std::path::PathBuf;
+use xml::writer::{EmitterConfig, EventWriter, XmlEvent};
+
+/// Name of the test suite and test case of results without a report, like
+/// the ones of data tests
+pub const DATA_TEST: &str = "data test";
+
+type Writer<W> = EventWriter<W>;
+
+fn xml_error(e: xml::writer::Error) -> ReporterError {
+    match e {
+        xml::writer::Error::Io(e) => ReporterError::IoError(e),
+        e => ReporterError::IoError(std::io::Error::other(e)),
+    }
+}
+
+/// Characters which are not allowed in XML 1.0, like the escape sequences
+/// of colored output, are replaced
+fn sanitize(s: &str) -> String {
+    let is_valid = |c: char| matches!(c, '\t' | '\n' | '\r') || c >= ' ';
+    s.chars()
+        .map(|c| if is_valid(c) { c } else { '\u{FFFD}' })
+        .collect()
+}
+
+fn format_time(time: Duration) -> String {
+    format!("{:.3}", time.as_secs_f64())
+}
+
+/// Without timezone, as required by the schema
+fn format_timestamp(timestamp: Timestamp) -> String {
+    timestamp.format("%Y-%m-%dT%H:%M:%S").to_string()
+}
+
+fn start<W: Write>(
+    w: &mut Writer<W>,
+    name: &str,
+    attrs: &[(&str, String)],
+) -> Result<(), ReporterError> {
+    let attrs: Vec<_> = attrs.iter().map(|(k, v)| (*k, sanitize(v))).collect();
+    let mut event = XmlEvent::start_element(name);
+
+    for (key, value) in &attrs {
+        event = event.attr(*key, value);
+    }
+
+    w.write(event).map_err(xml_error)
+}
+
+fn end<W: Write>(w: &mut Writer<W>) -> Result<(), ReporterError> {
+    w.write(XmlEvent::end_element()).map_err(xml_error)
+}
+
+fn element<W: Write>(
+    w: &mut Writer<W>,
+    name: &str,
+    attrs: &[(&str, String)],
+    text: Option<&str>,
+) -> Result<(), ReporterError> {
+    start(w, name, attrs)?;
+
+    if let Some(text) = text.filter(|x| !x.is_empty()) {
+        w.write(XmlEvent::characters(&sanitize(text)))
+            .map_err(xml_error)?;
+    }
+
+    end(w)
+}
+
+fn count_status(testcases: &[CoreTestCase], status: CoreStatus) -> usize {
+    let d = std::mem::discriminant(&status);
+    testcases
+        .iter()
+        .filter(|x| std::mem::discriminant(&x.status) == d)
+        .count()
+}
+
+fn write_test_case<W: Write>(
+    w: &mut Writer<W>,
+    test_suite: &CoreTestSuite,
+    test_case: &CoreTestCase,
+) -> Result<(), ReporterError> {
+    let classname = test_case.classname.as_ref().unwrap_or(&test_suite.name);
+    let mut attrs = vec![
+        ("name", test_case.name.clone()),
+        ("classname", classname.clone()),
+    ];
+
+    if let Some(time) = test_case.time {
+        attrs.push(("time", format_time(time)));
+    }
+
+    start(w, "testcase", &attrs)?;
+
+    if let CoreStatus::Skipped | CoreStatus::Ignored = test_case.status {
+        element(w, "skipped", &[], None)?;
+    }
+
+    for failure in &test_case.failures {
+        let message = failure.message.lines().next().unwrap_or_default();
+        let attrs = [
+            ("message", message.to_string()),
+            ("type", failure.ttype.clone()),
+        ];
+        element(w, "failure", &attrs, Some(&failure.message))?;
+    }
+
+    // A failed test case must have a failure, even if its reason is unknown
+    if test_case.failures.is_empty() && matches!(test_case.status, CoreStatus::Fail) {
+        element(w, "failure", &[("message", "Failed".to_string())], None)?;
+    }
+
+    if let Some(output) = &test_case.system_out {
+        element(w, "system-out", &[], Some(output))?;
+    }
+
+    if let Some(output) = &test_case.system_err {
+        element(w, "system-err", &[], Some(output))?;
+    }
+
+    end(w)
+}
+
+fn write_test_suite<W: Write>(
+    w: &mut Writer<W>,
+    test_suite: &CoreTestSuite,
+) -> Result<(), ReporterError> {
+    let testcases = &test_suite.testcases;
+    let failures = test_suite
+        .failures
+        .unwrap_or_else(|| count_status(testcases, CoreStatus::Fail));
+    let skipped = test_suite.skipped.unwrap_or_else(|| {
+        count_status(testcases, CoreStatus::Skipped) + count_status(testcases, CoreStatus::Ignored)
+    });
+
+    let mut attrs = vec![
+        ("name", test_suite.name.clone()),
+        ("tests", test_suite.tests.to_string()),
+        ("failures", failures.to_string()),
+        ("errors", test_suite.errors.unwrap_or(0).to_string()),
+        ("skipped", skipped.to_string()),
+    ];
+
+    if let Some(disabled) = test_suite.disabled {
+        attrs.push(("disabled", disabled.to_string()));
+    }
+
+    if let Some(time) = test_suite.time {
+        attrs.push(("time", format_time(time)));
+    }
+
+    if let Some(timestamp) = test_suite.timestamp {
+        attrs.push(("timestamp", format_timestamp(timestamp)));
+    }
+
+    if let Some(hostname) = &test_suite.hostname {
+        attrs.push(("hostname", hostname.clone()));
+    }
+
+    if let Some(id) = &test_suite.id {
+        attrs.push(("id", id.clone()));
+    }
+
+    if let Some(package) = &test_suite.package {
+        attrs.push(("package", package.clone()));
+    }
+
+    start(w, "testsuite", &attrs)?;
+
+    for test_case in testcases {
+        write_test_case(w, test_suite, test_case)?;
+    }
+
+    if let Some(output) = &test_suite.system_out {
+        element(w, "system-out", &[], Some(output))?;
+    }
+
+    if let Some(output) = &test_suite.system_err {
+        element(w, "system-err", &[], Some(output))?;
+    }
+
+    end(w)
+}
+
+/// A JUnit XML document, with `<testsuites>` as root element
+pub fn to_junit_xml(report: &CoreReport) -> Result<String, ReporterError> {
+    let mut buf = Vec::new();
+    let mut w = EmitterConfig::new()
+        .perform_indent(true)
+        .create_writer(&mut buf);
+
+    let tests: usize = report.testsuites.iter().map(|x| x.tests).sum();
+    let failures = report
+        .failures
+        .unwrap_or_else(|| report.testsuites.iter().filter_map(|x| x.failures).sum());
+
+    let mut attrs = vec![
+        ("tests", report.tests.unwrap_or(tests).to_string()),
+        ("failures", failures.to_string()),
+        ("errors", report.errors.unwrap_or(0).to_string()),
+    ];
+
+    if let Some(name) = &report.name {
+        attrs.insert(0, ("name", name.clone()));
+    }
+
+    if let Some(disabled) = report.disabled {
+        attrs.push(("disabled", disabled.to_string()));
+    }
+
+    if let Some(time) = report.time {
+        attrs.push(("time", format_time(time)));
+    }
+
+    if let Some(timestamp) = report.timestamp {
+        attrs.push(("timestamp", format_timestamp(timestamp)));
+    }
+
+    start(&mut w, "testsuites", &attrs)?;
+
+    for test_suite in &report.testsuites {
+        write_test_suite(&mut w, test_suite)?;
+    }
+
+    end(&mut w)?;
+
+    Ok(String::from_utf8_lossy(&buf).to_string())
+}
+
+/// Results without a report, like the ones of data tests, have no
+/// timestamp, which is required by the schema: it is the current time
+fn set_missing_timestamps(report: &mut CoreReport) {
+    let now = Timestamp::from(std::time::SystemTime::now());
+    report.timestamp.get_or_insert(now);
+
+    for test_suite in &mut report.testsuites {
+        test_suite.timestamp.get_or_insert(now);
+    }
+}
+
+/// Write results to a JUnit XML file, overwritten at every run
+pub struct ThisReporter {
+    path: PathBuf,
+}
+
+impl ThisReporter {
+    pub fn new(path: PathBuf) -> Self {
+        Self { path }
+    }
+}
+
+impl provola_core::Reporter for ThisReporter {
+    fn report(&self, result: TestResult) -> Result<(), ReporterError> {
+        let mut report = result.into_report(DATA_TEST);
+        set_missing_timestamps(&mut report);
+        std::fs::write(&self.path, to_junit_xml(&report)?)?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use provola_core::report::SourceLocation;
+    use provola_core::{CoreFailure, Reason};
+
+    fn make_report() -> CoreReport {
+        let mut report = CoreReport {
+            name: Some("AllTests".to_string()),
+            ..Default::default()
+        };
+
+        let failed = CoreTestCase {
+            name: "Foo1".to_string(),
+            status: CoreStatus::Fail,
+            time: Some(Duration::from_millis(12)),
+            failures: vec![CoreFailure {
+                ttype: "assertion".to_string(),
+                message: "Expected: 1 == 2\nActual: false".to_string(),
+                location: Some(SourceLocation {
+                    file: "tests.cpp".to_string(),
+                    line: Some(12),
+                }),
+            }],
+            system_out: Some("Connecting to \"foo\" <1>\u{1b}[0m".to_string()),
+            ..Default::default()
+        };
+        let skipped = CoreTestCase {
+            name: "Foo2".to_string(),
+            status: CoreStatus::Skipped,
+            ..Default::default()
+        };
+        let passed = CoreTestCase {
+            name: "Bar1".to_string(),
+            status: CoreStatus::Pass,
+            time: Some(Duration::from_millis(3)),
+            ..Default::default()
+        };
+
+        report.push_test_case("Foo", failed);
+        report.push_test_case("Foo", skipped);
+        report.push_test_case("Bar", passed);
+        report.update_counts();
+        report.testsuites[0].timestamp = "2022-01-02T03:04:05Z".parse().ok();
+        report
+    }
+
+    #[test]
+    fn write_report() {
+        let xml = to_junit_xml(&make_report()).unwrap();
+        insta::assert_snapshot!(xml);
+    }
+
+    #[test]
+    fn written_report_can_be_read() {
+        let xml = to_junit_xml(&make_report()).unwrap();
+        let report = provola_junit::parse_report(&xml).unwrap();
+        assert_eq!(report.tests, Some(3));
+        assert_eq!(report.failures, Some(1));
+
+        let foo = &report.testsuites[0];
+        assert_eq!(foo.skipped, Some(1));
+        assert_eq!(foo.timestamp, "2022-01-02T03:04:05Z".parse().ok());
+        assert_eq!(foo.testcases[0].time, Some(Duration::from_millis(12)));
+        assert_eq!(
+            foo.testcases[0].failures[0].message,
+            "Expected: 1 == 2\nActual: false"
+        );
+        assert!(matches!(foo.testcases[1].status, CoreStatus::Skipped));
+    }
+
+    #[test]
+    fn data_test_is_a_test_case() {
+        let result = TestResult::Fail(Reason::not_expected("dcb", "dcba"));
+        let mut report = result.into_report(DATA_TEST);
+        set_missing_timestamps(&mut report);
+        let xml = to_junit_xml(&report).unwrap();
+        let report = provola_junit::parse_report(&xml).unwrap();
+        assert_eq!(report.failures, Some(1));
+        assert_eq!(report.testsuites[0].name, DATA_TEST);
+        assert_eq!(report.testsuites[0].testcases[0].name, DATA_TEST);
+        assert!(report.testsuites[0].timestamp.is_some());
+    }
+
+    #[test]
+    fn sanitize_attributes() {
+        let mut report = make_report();
+        report.testsuites[0].testcases[0].failures[0].ttype = "<\u{1b}[31m>".to_string();
+        let xml = to_junit_xml(&report).unwrap();
+        let report = provola_junit::parse_report(&xml).unwrap();
+        let failure = &report.testsuites[0].testcases[0].failures[0];
+        assert_eq!(failure.ttype, "<\u{FFFD}[31m>");
+    }
+}
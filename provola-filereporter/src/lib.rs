@@ -0,0 +1,4 @@
+mod junit;
+
+pub use junit::to_junit_xml;
+pub use junit::ThisReporter as JUnitReporter;
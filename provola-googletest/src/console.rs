@@ -0,0 +1,132 @@
+use provola_core::report::{CoreStatus, Duration};
+use provola_core::test::xunit::FullyQualifiedTestCase;
+use provola_core::TestEvent;
+
+const RUN: &str = "[ RUN      ] ";
+
+const FINISHED: &[(&str, CoreStatus)] = &[
+    ("[       OK ] ", CoreStatus::Pass),
+    ("[  FAILED  ] ", CoreStatus::Fail),
+    ("[  SKIPPED ] ", CoreStatus::Skipped),
+];
+
+fn parse_name(s: &str) -> FullyQualifiedTestCase {
+    // Value parameterized tests are followed by their parameter
+    let s = s.split(", where ").next().unwrap_or(s);
+    let (test_suite, test_case) = s.split_once('.').unwrap_or(("", s));
+    FullyQualifiedTestCase::from_test_suite_test_case(test_suite, test_case)
+}
+
+/// Split the elapsed time, like ` (12 ms)`, from the end of a line
+fn split_time(s: &str) -> (&str, Option<Duration>) {
+    let time = s
+        .strip_suffix(" ms)")
+        .and_then(|x| x.rsplit_once(" ("))
+        .and_then(|(name, ms)| Some((name, ms.parse().ok()?)));
+
+    match time {
+        Some((name, ms)) => (name, Some(Duration::from_millis(ms))),
+        None => (s, None),
+    }
+}
+
+/// Parse the console output of GoogleTest, a line at a time, so results are
+/// known while tests are running
+#[derive(Default)]
+pub(crate) struct ConsoleParser {
+    running: Option<FullyQualifiedTestCase>,
+}
+
+impl ConsoleParser {
+    pub fn parse_line(&mut self, line: &str) -> TestEvent {
+        if let Some(name) = line.strip_prefix(RUN) {
+            let fqtc = parse_name(name);
+            self.running = Some(fqtc.clone());
+            return TestEvent::TestStarted(fqtc);
+        }
+
+        for (marker, status) in FINISHED {
+            let (name, time) = match line.strip_prefix(marker) {
+                Some(x) => split_time(x),
+                None => continue,
+            };
+
+            // Failed tests are listed again in the summary, without a time
+            let fqtc = parse_name(name);
+
+            if self.running.as_ref().is_some_and(|x| x.id == fqtc.id) {
+                self.running = None;
+
+                return TestEvent::TestFinished {
+                    fqtc,
+                    status: *status,
+                    time,
+                };
+            }
+        }
+
+        TestEvent::Output(line.to_string())
+    }
+
+    /// The test which is running, if any. When the output ends while a test
+    /// is running, that test has crashed.
+    pub fn running(&self) -> Option<&FullyQualifiedTestCase> {
+        self.running.as_ref()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(s: &str) -> Vec<TestEvent> {
+        let mut parser = ConsoleParser::default();
+        s.lines().map(|x| parser.parse_line(x)).collect()
+    }
+
+    #[test]
+    fn parse_console_output() {
+        let s = r#"[==========] Running 2 tests from 1 test suite.
+[ RUN      ] Foo.Foo1
+[       OK ] Foo.Foo1 (0 ms)
+[ RUN      ] Foo.Foo2
+tests.cpp:12: Failure
+[  FAILED  ] Foo.Foo2 (12 ms)
+[  FAILED  ] Foo.Foo2
+[ RUN      ] Bar/0.Bar1/1
+[  FAILED  ] Bar/0.Bar1/1, where GetParam() = 1 (3 ms)"#;
+
+        let events = parse(s);
+        assert_eq!(events.len(), 9);
+        assert!(matches!(&events[0], TestEvent::Output(_)));
+        assert!(matches!(&events[1], TestEvent::TestStarted(x) if x.to_string() == "Foo.Foo1"));
+        assert!(matches!(
+            &events[2],
+            TestEvent::TestFinished {
+                status: CoreStatus::Pass,
+                time: Some(_),
+                ..
+            }
+        ));
+        assert!(matches!(&events[4], TestEvent::Output(x) if x == "tests.cpp:12: Failure"));
+        assert!(matches!(
+            &events[5],
+            TestEvent::TestFinished { fqtc, status: CoreStatus::Fail, time }
+            if fqtc.to_string() == "Foo.Foo2" && *time == Some(Duration::from_millis(12))
+        ));
+        assert!(matches!(&events[6], TestEvent::Output(_)));
+        assert!(matches!(
+            &events[8],
+            TestEvent::TestFinished { fqtc, status: CoreStatus::Fail, .. }
+            if fqtc.test_suite.0 == "Bar/0" && fqtc.test_case.0 == "Bar1/1"
+        ));
+    }
+
+    #[test]
+    fn test_running_when_output_ends() {
+        let mut parser = ConsoleParser::default();
+        parser.parse_line("[ RUN      ] Foo.Foo1");
+        parser.parse_line("Segmentation fault");
+        assert_eq!(parser.running().unwrap().to_string(), "Foo.Foo1");
+    }
+}
@@ -0,0 +1,329 @@
+use crate::shard::Shard;
+use provola_core::report::{captured_output, CoreStatus, SourceLocation};
+use provola_core::test::xunit::{FullyQualifiedTestCase, FullyQualifiedTestCaseId};
+use provola_core::{CoreFailure, CoreReport, CoreTestCase, TestEvent};
+use subprocess::ExitStatus;
+
+/// Name of the most common signals killing a test executable
+fn signal_name(signal: u8) -> Option<&'static str> {
+    match signal {
+        4 => Some("SIGILL"),
+        6 => Some("SIGABRT"),
+        7 => Some("SIGBUS"),
+        8 => Some("SIGFPE"),
+        9 => Some("SIGKILL"),
+        11 => Some("SIGSEGV"),
+        15 => Some("SIGTERM"),
+        _ => None,
+    }
+}
+
+/// Why the executable did not write its report
+pub(crate) fn describe_crash(shard: Shard, exit_status: Option<ExitStatus>) -> String {
+    let mut s = match exit_status {
+        Some(ExitStatus::Signaled(signal)) => match signal_name(signal) {
+            Some(name) => format!("Test runner crashed with signal {}", name),
+            None => format!("Test runner crashed with signal {}", signal),
+        },
+        Some(ExitStatus::Exited(code)) => format!("Test runner exited with code {}", code),
+        _ => "Test runner crashed".to_string(),
+    };
+
+    if shard.total > 1 {
+        s.push_str(&format!(" in shard {} of {}", shard.index, shard.total));
+    }
+
+    s
+}
+
+fn make_test_case(fqtc: &FullyQualifiedTestCase, status: CoreStatus) -> CoreTestCase {
+    CoreTestCase {
+        fqtc: Some(fqtc.id),
+        name: fqtc.test_case.0.clone(),
+        status,
+        ..Default::default()
+    }
+}
+
+/// Output without failures, like `tests.cpp:12: Failure` followed by the
+/// message up to an empty line, which are already in the report
+fn strip_failures(output: &[String]) -> String {
+    let mut in_failure = false;
+    let mut lines = Vec::new();
+
+    for line in output {
+        let is_failure = SourceLocation::split_prefix(line).is_some_and(|(_, x)| x == "Failure");
+
+        if is_failure {
+            in_failure = true;
+        } else if line.is_empty() && in_failure {
+            in_failure = false;
+        } else if !in_failure {
+            lines.push(line.as_str());
+        }
+    }
+
+    lines.join("\n")
+}
+
+fn make_failure(ttype: &str, message: String) -> CoreFailure {
+    match SourceLocation::split_prefix(&message) {
+        Some((location, rest)) => CoreFailure {
+            ttype: ttype.to_string(),
+            message: rest.to_string(),
+            location: Some(location),
+        },
+        None => CoreFailure {
+            ttype: ttype.to_string(),
+            message,
+            location: None,
+        },
+    }
+}
+
+/// Results of the test cases, as known from the console output. They are
+/// kept when the executable crashes before writing its report, otherwise
+/// just the output of each test case is added to the report.
+#[derive(Default)]
+pub(crate) struct ConsoleResults {
+    finished: Vec<(FullyQualifiedTestCase, CoreTestCase)>,
+    /// Output of the running test case
+    output: Vec<String>,
+}
+
+impl ConsoleResults {
+    pub fn add(&mut self, event: &TestEvent) {
+        match event {
+            TestEvent::TestStarted(_) => self.output.clear(),
+            TestEvent::Output(line) => self.output.push(line.clone()),
+            TestEvent::TestFinished { fqtc, status, time } => {
+                let mut test_case = make_test_case(fqtc, *status);
+                test_case.time = *time;
+
+                let output = std::mem::take(&mut self.output);
+
+                if let CoreStatus::Fail = status {
+                    test_case.failures = vec![make_failure("failure", output.join("\n"))];
+                }
+
+                // GoogleTest prints to the console what is written to both
+                // stdout and stderr, they cannot be told apart
+                test_case.system_out = captured_output(Some(strip_failures(&output)));
+
+                // Repeated test cases are reported once, as GoogleTest does
+                self.finished.retain(|(x, _)| x.id != fqtc.id);
+                self.finished.push((fqtc.clone(), test_case));
+            }
+            _ => {}
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.finished.is_empty()
+    }
+
+    pub fn is_finished(&self, id: FullyQualifiedTestCaseId) -> bool {
+        self.finished.iter().any(|(x, _)| x.id == id)
+    }
+
+    /// Tests of the shard which have not run because of the crash
+    pub fn remaining<'a>(
+        &self,
+        tests: &[&'a FullyQualifiedTestCase],
+        crashed: Option<&FullyQualifiedTestCase>,
+    ) -> Vec<&'a FullyQualifiedTestCase> {
+        tests
+            .iter()
+            .filter(|x| !self.is_finished(x.id) && crashed.map(|c| c.id) != Some(x.id))
+            .copied()
+            .collect()
+    }
+
+    /// Add the output of each finished test case to a report
+    pub fn add_output(&self, report: &mut CoreReport) {
+        for test_suite in &mut report.testsuites {
+            for test_case in &mut test_suite.testcases {
+                let id = test_case.id(&test_suite.name);
+                let finished = self.finished.iter().find(|(x, _)| x.id == id);
+
+                if let Some((_, finished)) = finished {
+                    test_case.system_out = finished.system_out.clone();
+                }
+            }
+        }
+    }
+
+    /// Report of the finished test cases and of the crashed one, which is
+    /// failed with the output written before the crash
+    pub fn into_report(
+        self,
+        crashed: Option<&FullyQualifiedTestCase>,
+        message: &str,
+        events: &mut dyn FnMut(TestEvent),
+    ) -> CoreReport {
+        let mut report = CoreReport::default();
+
+        for (fqtc, test_case) in self.finished {
+            report.push_test_case(&fqtc.test_suite.0, test_case);
+        }
+
+        if let Some(fqtc) = crashed {
+            let mut test_case = make_test_case(fqtc, CoreStatus::Fail);
+            let mut failure = make_failure("crash", message.to_string());
+
+            if !self.output.is_empty() {
+                failure.message = format!("{}\n{}", message, self.output.join("\n"));
+            }
+
+            test_case.failures = vec![failure];
+            report.push_test_case(&fqtc.test_suite.0, test_case);
+
+            events(TestEvent::TestFinished {
+                fqtc: fqtc.clone(),
+                status: CoreStatus::Fail,
+                time: None,
+            });
+        }
+
+        report
+    }
+}
+
+/// Report of test cases which did not run because the executable crashed:
+/// all of them are failed
+pub(crate) fn make_crash_report(tests: &[&FullyQualifiedTestCase], message: &str) -> CoreReport {
+    let mut report = CoreReport::default();
+
+    for fqtc in tests {
+        let mut test_case = make_test_case(fqtc, CoreStatus::Fail);
+        test_case.failures = vec![make_failure("crash", message.to_string())];
+        report.push_test_case(&fqtc.test_suite.0, test_case);
+    }
+
+    report
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::console::ConsoleParser;
+    use crate::shard;
+    use crate::tests::make_available_tests;
+
+    #[test]
+    fn keep_results_of_finished_tests() {
+        let s = r#"[==========] Running 3 tests from 1 test suite.
+[ RUN      ] Foo.Foo1
+tests.cpp:12: Failure
+Expected equality
+[  FAILED  ] Foo.Foo1 (1 ms)
+[ RUN      ] Foo.Foo3
+[       OK ] Foo.Foo3 (2 ms)
+[ RUN      ] Bar.Bar1
+Dereferencing null"#;
+
+        let mut parser = ConsoleParser::default();
+        let mut results = ConsoleResults::default();
+        s.lines()
+            .for_each(|line| results.add(&parser.parse_line(line)));
+
+        let shard = Shard { index: 0, total: 1 };
+        let message = describe_crash(shard, Some(ExitStatus::Signaled(11)));
+        assert_eq!(message, "Test runner crashed with signal SIGSEGV");
+
+        let mut events = Vec::new();
+        let report = results.into_report(parser.running(), &message, &mut |x| events.push(x));
+        assert_eq!(report.tests, Some(3));
+        assert_eq!(report.failures, Some(2));
+        assert_eq!(events.len(), 1);
+
+        let foo1 = &report.testsuites[0].testcases[0];
+        assert_eq!(foo1.failures[0].message, "Failure\nExpected equality");
+        assert_eq!(foo1.failures[0].location.as_ref().unwrap().line, Some(12));
+        assert_eq!(foo1.system_out, None);
+
+        let bar1 = &report.testsuites[1].testcases[0];
+        assert_eq!(bar1.failures[0].ttype, "crash");
+        assert_eq!(
+            bar1.failures[0].message,
+            "Test runner crashed with signal SIGSEGV\nDereferencing null"
+        );
+    }
+
+    #[test]
+    fn do_not_run_again_finished_parameterized_tests() {
+        let list = r#"Running main() from gtest_main.cc
+Values/FooTest.
+  Foo/0  # GetParam() = 1
+  Foo/1  # GetParam() = 2
+  Foo/2  # GetParam() = 3
+Bar/0.  # TypeParam = int
+  Bar1"#;
+        let tests = crate::parse_available_tests(list).unwrap();
+        let tests: Vec<_> = tests.iter().collect();
+
+        let s = r#"[ RUN      ] Values/FooTest.Foo/0
+[       OK ] Values/FooTest.Foo/0 (0 ms)
+[ RUN      ] Values/FooTest.Foo/1
+Dereferencing null"#;
+
+        let mut parser = ConsoleParser::default();
+        let mut results = ConsoleResults::default();
+        s.lines()
+            .for_each(|line| results.add(&parser.parse_line(line)));
+
+        let remaining = results.remaining(&tests, parser.running());
+        let remaining: Vec<_> = remaining.iter().map(|x| x.to_string()).collect();
+        assert_eq!(remaining, vec!["Values/FooTest.Foo/2", "Bar/0.Bar1"]);
+    }
+
+    #[test]
+    fn add_output_to_report() {
+        let s = r#"[ RUN      ] Foo.Foo1
+Connecting to foo
+tests.cpp:12: Failure
+Expected equality
+
+Disconnecting from foo
+[  FAILED  ] Foo.Foo1 (1 ms)
+[ RUN      ] Foo.Foo3
+[       OK ] Foo.Foo3 (2 ms)"#;
+
+        let mut parser = ConsoleParser::default();
+        let mut results = ConsoleResults::default();
+        s.lines()
+            .for_each(|line| results.add(&parser.parse_line(line)));
+
+        let mut report = CoreReport::default();
+        for name in ["Foo1", "Foo3"] {
+            let fqtc = FullyQualifiedTestCase::from_test_suite_test_case("Foo", name);
+            report.push_test_case("Foo", make_test_case(&fqtc, CoreStatus::Pass));
+        }
+
+        results.add_output(&mut report);
+        let testcases = &report.testsuites[0].testcases;
+        assert_eq!(
+            testcases[0].system_out.as_deref(),
+            Some("Connecting to foo\nDisconnecting from foo")
+        );
+        assert_eq!(testcases[1].system_out, None);
+    }
+
+    #[test]
+    fn crashed_shard_fails_its_tests_only() {
+        let tests = make_available_tests();
+        let runnable: Vec<_> = tests.iter().filter(|x| !shard::is_disabled(x)).collect();
+        let shard = Shard { index: 1, total: 2 };
+        let message = describe_crash(shard, None);
+        assert_eq!(message, "Test runner crashed in shard 1 of 2");
+
+        let mut report = make_crash_report(&shard.tests(&runnable), &message);
+        assert_eq!(report.failures, Some(2));
+        assert_eq!(report.testsuites.len(), 2);
+
+        shard::sort_like(&mut report, &tests);
+        assert_eq!(report.testsuites[0].name, "Foo");
+        assert_eq!(report.testsuites[0].testcases[0].name, "Foo3");
+        assert_eq!(report.testsuites[1].testcases[0].name, "Bar2");
+    }
+}
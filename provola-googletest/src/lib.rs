@@ -1,13 +1,17 @@
-use provola_core::test_runners::{Only, TestRunnerOpt};
-use provola_core::{AvailableTests, CoreReport, Error, Executable};
-use std::fs::File;
-use std::io::BufReader;
-use std::time::Duration;
-use subprocess::PopenConfig;
-use subprocess::Redirection;
-use subprocess::{ExitStatus, Popen};
-
+use provola_core::report::RepeatSummary;
+use provola_core::test::xunit::FullyQualifiedTestCase;
+use provola_core::test_runners::{run_repeatedly, Pattern, Repeat, Repetitions, TestRunnerOpt};
+use provola_core::{
+    run_process, run_process_streaming, AvailableTests, CoreReport, Error, Executable, ProcessOpt,
+    TempReport, TestEvent, TestResult,
+};
+use shard::Shard;
+use std::path::Path;
+
+mod console;
+mod crash;
 mod report;
+mod shard;
 
 fn add_list_argv(mut argv: Vec<String>) -> Vec<String> {
     argv.push("--gtest_list_tests".to_string());
@@ -15,52 +19,39 @@ fn add_list_argv(mut argv: Vec<String>) -> Vec<String> {
     argv
 }
 
-fn add_run_argv(mut argv: Vec<String>, report_path: &str) -> Vec<String> {
-    argv.push(format!("--gtest_output=json:{}", report_path));
+fn add_run_argv(mut argv: Vec<String>, report_path: &Path) -> Vec<String> {
+    argv.push(format!("--gtest_output=json:{}", report_path.display()));
     argv.push("--gtest_color=no".to_string());
     argv
 }
 
-fn run_exec_with_argv(argv: Vec<String>) -> Result<String, Error> {
-    log::debug!("{:?}", argv);
-
-    let mut p = Popen::create(
-        &argv,
-        PopenConfig {
-            stdin: Redirection::None,
-            stdout: Redirection::Pipe,
-            stderr: Redirection::Pipe,
-            ..Default::default()
-        },
-    )?;
-
-    let (out, _err) = p.communicate(None)?;
-
-    // TODO Timeout from configuration
-    let timeout = Duration::from_secs(3600);
+/// Flags to repeat tests `count` times and to shuffle them, if requested
+fn make_repeat_argv(opt: &TestRunnerOpt, count: usize) -> Vec<String> {
+    let mut argv = Vec::new();
 
-    if let Some(exit_status) = p.wait_timeout(timeout)? {
-        if let ExitStatus::Exited(code) = exit_status {
-            log::debug!("Exit status: {}", code);
-        } else {
-            return Err(Error::GenericError("Invalid exit status".to_string()));
-        }
+    if count > 1 {
+        argv.push(format!("--gtest_repeat={}", count));
+    }
 
-        log::trace!("done");
-    } else {
-        log::warn!("Terminate subprocess");
-        p.terminate()?;
+    // GoogleTest changes the seed at every iteration
+    if let Some(seed) = opt.repeat.as_ref().and_then(|x| x.seed) {
+        argv.push("--gtest_shuffle".to_string());
+        argv.push(format!("--gtest_random_seed={}", seed));
     }
 
-    Ok(out.unwrap_or_default())
+    argv
 }
 
 fn extract_test_suite_name(s: &str) -> String {
     s.chars().take_while(|&x| x != '.').collect()
 }
 
+/// Parameterized and typed tests are followed by a comment with their
+/// parameter, like `  Foo/0  # GetParam() = 1`, which is not in their name
 fn extract_test_case_name(s: &str) -> String {
-    s.chars().skip(2).collect()
+    let s = s.get(2..).unwrap_or_default();
+    let s = s.split('#').next().unwrap_or_default();
+    s.trim_end().to_string()
 }
 
 fn parse_available_tests(s: &str) -> Result<AvailableTests, Error> {
@@ -91,82 +82,388 @@ fn parse_available_tests(s: &str) -> Result<AvailableTests, Error> {
     Ok(tests)
 }
 
-fn generate_available_tests(executable: &Executable) -> Result<AvailableTests, Error> {
+fn generate_available_tests(
+    executable: &Executable,
+    process: &ProcessOpt,
+) -> Result<AvailableTests, Error> {
     let argv = add_list_argv(executable.into());
-    let out = run_exec_with_argv(argv)?;
+    let out = run_process(&argv, process)?.out;
     parse_available_tests(&out)
 }
 
-fn generate_report(executable: &Executable, test_filter: &TestFilter) -> Result<CoreReport, Error> {
-    let report_path = "googletest_report.json";
-    let executable = executable.into();
-
-    let mut argv = add_run_argv(executable, report_path);
+/// Run a shard, which executes `tests`
+fn generate_report(
+    executable: &Executable,
+    process: &ProcessOpt,
+    test_filter: &TestFilter,
+    flags: &[String],
+    shard: Shard,
+    tests: &[&FullyQualifiedTestCase],
+    events: &mut dyn FnMut(TestEvent),
+) -> Result<CoreReport, Error> {
+    let report_file = TempReport::new("googletest", "json");
+    let mut argv = add_run_argv(executable.into(), &report_file);
 
     if let Some(test_filter_s) = &test_filter.0 {
         argv.push(format!("--gtest_filter={}", test_filter_s));
     }
 
-    run_exec_with_argv(argv)?;
+    argv.extend_from_slice(flags);
 
-    let file = File::open(report_path).map_err(|e| {
-        let msg = format!("Cannot open {}: {}", report_path, e);
-        Error::GenericError(msg)
+    let mut parser = console::ConsoleParser::default();
+    let mut results = crash::ConsoleResults::default();
+    let shard_process = ProcessOpt {
+        env: [process.env.clone(), shard.env()].concat(),
+        ..process.clone()
+    };
+    let exit_status = run_process_streaming(&argv, &shard_process, &mut |line| {
+        let event = parser.parse_line(line);
+        results.add(&event);
+        events(event);
     })?;
 
-    let reader = BufReader::new(file);
-    let gtest_rep: report::UnitTest = serde_json::from_reader(reader).unwrap();
-    let core_rep = CoreReport::from(gtest_rep);
-    Ok(core_rep)
+    let report = match report_file.read_to_string() {
+        Ok(s) => serde_json::from_str::<report::UnitTest>(&s).map_err(|e| {
+            log::warn!("Invalid report {}: {}", report_file.display(), e);
+        }),
+        Err(Error::TestRunnerCrashed(_)) => Err(()),
+        Err(e) => return Err(e),
+    };
+
+    if let Ok(report) = report {
+        let mut report = CoreReport::from(report);
+        results.add_output(&mut report);
+        return Ok(report);
+    }
+
+    let message = crash::describe_crash(shard, exit_status);
+    let crashed = parser.running();
+
+    if let Some(fqtc) = crashed {
+        log::warn!("{} while running {}", message, fqtc);
+    }
+
+    // Without any progress, running the same tests again would crash again
+    let is_progress = crashed.is_some() || !results.is_empty();
+
+    let remaining = results.remaining(tests, crashed);
+
+    let mut report = results.into_report(crashed, &message, events);
+
+    if remaining.is_empty() {
+        return Ok(report);
+    }
+
+    if is_progress {
+        log::info!(
+            "Running {} tests not run because of the crash",
+            remaining.len()
+        );
+        let shard = Shard { index: 0, total: 1 };
+
+        for (test_filter, batch) in make_filter_batches(&remaining, MAX_FILTER_LEN) {
+            let others = generate_report(
+                executable,
+                process,
+                &test_filter,
+                flags,
+                shard,
+                &batch,
+                events,
+            )?;
+            report.merge(others);
+        }
+    } else {
+        let others = crash::make_crash_report(&remaining, &message);
+        TestEvent::from_report(&others).for_each(&mut *events);
+        report.merge(others);
+    }
+
+    Ok(report)
+}
+
+/// Run tests with many processes, each one with its own shard. Events of
+/// all the shards are emitted by the calling thread.
+fn generate_sharded_report(
+    executable: &Executable,
+    process: &ProcessOpt,
+    test_filter: &TestFilter,
+    flags: &[String],
+    runnable: &[&FullyQualifiedTestCase],
+    max_shards: usize,
+    events: &mut dyn FnMut(TestEvent),
+) -> Result<CoreReport, Error> {
+    let (events_s, events_r) = std::sync::mpsc::channel();
+
+    let results: Vec<_> = std::thread::scope(|s| {
+        let handles: Vec<_> = Shard::split(max_shards, runnable.len())
+            .map(|shard| {
+                let events_s = events_s.clone();
+                s.spawn(move || {
+                    let mut send = |x| events_s.send(x).unwrap_or_default();
+                    let tests = shard.tests(runnable);
+                    generate_report(
+                        executable,
+                        process,
+                        test_filter,
+                        flags,
+                        shard,
+                        &tests,
+                        &mut send,
+                    )
+                })
+            })
+            .collect();
+
+        // Events are received until every shard is finished
+        drop(events_s);
+        events_r.into_iter().for_each(&mut *events);
+
+        handles
+            .into_iter()
+            .map(|handle| {
+                handle
+                    .join()
+                    .unwrap_or_else(|_| Err(Error::GenericError("Shard panicked".to_string())))
+            })
+            .collect()
+    });
+
+    let mut merged = CoreReport::default();
+    let mut time = None;
+
+    for result in results {
+        let report = result?;
+
+        // Shards run concurrently
+        time = time.max(report.time);
+        merged.merge(report);
+    }
+
+    merged.time = time;
+    Ok(merged)
 }
 
 pub struct TestRunner {
     executable: Executable,
+    process: ProcessOpt,
     available_tests: AvailableTests,
+    shards: usize,
 }
 
 #[derive(Default)]
 struct TestFilter(Option<String>);
 
+/// Longest filter listing the tests to run again after a crash. A single
+/// argument cannot be longer than 128 KiB on Linux (`MAX_ARG_STRLEN`).
+const MAX_FILTER_LEN: usize = 64 * 1024;
+
+/// Split tests in batches, each one with a filter not longer than `max_len`
+/// (unless a single name is longer)
+fn make_filter_batches<'a>(
+    tests: &[&'a FullyQualifiedTestCase],
+    max_len: usize,
+) -> Vec<(TestFilter, Vec<&'a FullyQualifiedTestCase>)> {
+    let mut batches: Vec<(String, Vec<_>)> = vec![];
+
+    for &test in tests {
+        let name = test.to_string();
+
+        match batches.last_mut() {
+            Some((filter, batch)) if filter.len() + 1 + name.len() <= max_len => {
+                filter.push(':');
+                filter.push_str(&name);
+                batch.push(test);
+            }
+            _ => batches.push((name, vec![test])),
+        }
+    }
+
+    batches
+        .into_iter()
+        .map(|(filter, batch)| (TestFilter(Some(filter)), batch))
+        .collect()
+}
+
+/// GoogleTest patterns equivalent to a pattern. A name without a dot may
+/// be just the name of a test case, as in other test runners.
+fn make_gtest_patterns(pattern: &Pattern, tests: &AvailableTests) -> Vec<String> {
+    match pattern {
+        // An id may also be the name of some test case
+        Pattern::Index(_) | Pattern::Id(_) => tests
+            .enumerate()
+            .filter(|(index, x)| {
+                pattern.is_match(*index, &x.test_suite.0, &x.test_case.0, Some(x.id))
+            })
+            .map(|(_, x)| x.to_string())
+            .collect(),
+        Pattern::Suite(test_suite) => vec![format!("{}.*", test_suite)],
+        Pattern::Name(name) if name.contains('.') => vec![name.clone()],
+        Pattern::Name(name) => vec![name.clone(), format!("*.{}", name)],
+        Pattern::Tag(tag) => {
+            log::warn!("GoogleTest does not support tags, ignoring [{}]", tag);
+            vec![]
+        }
+    }
+}
+
 fn make_test_filter(opt: &TestRunnerOpt, tests: &AvailableTests) -> Result<TestFilter, Error> {
-    let fqtn = match opt.only {
-        Only::SingleByIndex(index) => tests.get(index),
-        Only::SingleByFqtc(fqtc) => tests.get_by_id(fqtc),
-        Only::All => None,
+    let selection = match opt.only.selection() {
+        Some(selection) => selection,
+        None => return Ok(TestFilter(None)),
+    };
+
+    let patterns = |x: &[Pattern]| -> Vec<String> {
+        x.iter()
+            .flat_map(|x| make_gtest_patterns(x, tests))
+            .collect()
     };
 
-    let s = fqtn.map(|x| format!("{}.{}", x.test_suite.0, x.test_case.0));
+    let positive = patterns(&selection.include);
+    let negative = patterns(&selection.exclude);
 
-    Ok(TestFilter(s))
+    let mut s = match (selection.include.is_empty(), positive.is_empty()) {
+        (true, _) => "*".to_string(),
+        (false, true) => return Err(Error::NothingToDo),
+        (false, false) => positive.join(":"),
+    };
+
+    if !negative.is_empty() {
+        s.push('-');
+        s.push_str(&negative.join(":"));
+    }
+
+    Ok(TestFilter(Some(s)))
+}
+
+/// Tests which are executed by GoogleTest, so they are split among shards
+fn find_runnable<'a>(
+    opt: &TestRunnerOpt,
+    tests: &'a AvailableTests,
+) -> Vec<&'a FullyQualifiedTestCase> {
+    let selected = match opt.only.selection() {
+        Some(selection) => selection.filter(tests),
+        None => tests.iter().collect(),
+    };
+
+    selected
+        .into_iter()
+        .filter(|x| !shard::is_disabled(x))
+        .collect()
 }
 
 impl TestRunner {
-    fn generate_report(&self, opt: &TestRunnerOpt) -> Result<CoreReport, Error> {
-        let test_filter = make_test_filter(opt, &self.available_tests);
-        generate_report(&self.executable, &test_filter?)
+    /// Run tests with at most `shards` processes in parallel, or as many as
+    /// the available CPUs if `None`. Fails if tests cannot be listed, e.g.
+    /// when the executable is missing or crashes.
+    pub fn new(executable: Executable, shards: Option<usize>) -> Result<Self, Error> {
+        Self::with_process(executable, shards, ProcessOpt::default())
     }
-}
 
-impl From<Executable> for TestRunner {
-    fn from(executable: Executable) -> Self {
-        // TODO Fix unwrap
-        let available_tests = generate_available_tests(&executable).unwrap();
-        TestRunner {
+    /// Like `new`, running the executable in the working directory and
+    /// with the environment of `process`
+    pub fn with_process(
+        executable: Executable,
+        shards: Option<usize>,
+        process: ProcessOpt,
+    ) -> Result<Self, Error> {
+        let shards =
+            shards.unwrap_or_else(|| std::thread::available_parallelism().map_or(1, |x| x.get()));
+
+        let available_tests = generate_available_tests(&executable, &process)?;
+        Ok(TestRunner {
             executable,
+            process,
             available_tests,
+            shards,
+        })
+    }
+
+    /// Run the selected tests `count` times. The report is the one of the
+    /// last iteration.
+    fn generate_report(
+        &self,
+        opt: &TestRunnerOpt,
+        count: usize,
+        events: &mut dyn FnMut(TestEvent),
+    ) -> Result<CoreReport, Error> {
+        let test_filter = make_test_filter(opt, &self.available_tests)?;
+        let flags = make_repeat_argv(opt, count);
+        let runnable = find_runnable(opt, &self.available_tests);
+
+        events(TestEvent::RunStarted(Some(runnable.len() * count)));
+
+        let report = generate_sharded_report(
+            &self.executable,
+            &self.process,
+            &test_filter,
+            &flags,
+            &runnable,
+            self.shards,
+            events,
+        );
+
+        events(TestEvent::RunFinished);
+
+        let mut report = report?;
+        shard::sort_like(&mut report, &self.available_tests);
+
+        if let Some(cwd) = &self.process.cwd {
+            report.resolve_locations(cwd);
         }
+
+        Ok(report)
     }
 }
 
 impl provola_core::test_runners::TestRunner for TestRunner {
     fn run(&self, opt: &TestRunnerOpt) -> Result<provola_core::TestResult, provola_core::Error> {
-        let report = self.generate_report(opt)?;
+        self.run_with_events(opt, &mut |_| {})
+    }
+
+    fn run_with_events(
+        &self,
+        opt: &TestRunnerOpt,
+        events: &mut dyn FnMut(TestEvent),
+    ) -> Result<provola_core::TestResult, provola_core::Error> {
+        let report = self.generate_report(opt, 1, events)?;
         let result = report.into();
         Ok(result)
     }
 
+    fn run_repeated(
+        &self,
+        opt: &TestRunnerOpt,
+        repeat: &Repeat,
+        events: &mut dyn FnMut(TestEvent),
+    ) -> Result<TestResult, Error> {
+        // GoogleTest can repeat tests a fixed number of times only
+        let count = match repeat {
+            Repeat {
+                count: Some(count),
+                until_failure: false,
+                duration: None,
+                ..
+            } => *count,
+            _ => return run_repeatedly(self, opt, repeat, events),
+        };
+
+        let mut repetitions = Repetitions::default();
+        let report = self.generate_report(opt, count, &mut |event| {
+            repetitions.add_event(&event);
+            events(event);
+        })?;
+
+        let summary = RepeatSummary {
+            runs: count,
+            seed: repeat.seed,
+        };
+
+        Ok(repetitions.finish(report, summary).into())
+    }
+
     fn list(&self, _opt: &TestRunnerOpt) -> Result<AvailableTests, Error> {
-        generate_available_tests(&self.executable)
+        generate_available_tests(&self.executable, &self.process)
     }
 }
 
@@ -175,6 +472,18 @@ mod tests {
     use std::path::PathBuf;
 
     use super::*;
+    use provola_core::test_runners::{Only, Selection};
+
+    /// Tests of the crate, with a disabled one
+    pub(crate) fn make_available_tests() -> AvailableTests {
+        let mut tests = AvailableTests::default();
+        tests.push("Foo", "Foo1");
+        tests.push("Foo", "DISABLED_Foo2");
+        tests.push("Foo", "Foo3");
+        tests.push("Bar", "Bar1");
+        tests.push("Bar", "Bar2");
+        tests
+    }
 
     fn make_exec() -> Executable {
         let path = PathBuf::from("./examples/data/build/example");
@@ -187,7 +496,17 @@ mod tests {
     fn run_valid_executable() {
         let exec = make_exec();
         let test_filter = TestFilter::default();
-        assert!(generate_report(&exec, &test_filter).is_ok());
+        let shard = Shard { index: 0, total: 1 };
+        assert!(generate_report(
+            &exec,
+            &ProcessOpt::default(),
+            &test_filter,
+            &[],
+            shard,
+            &[],
+            &mut |_| {}
+        )
+        .is_ok());
     }
 
     // Ignored because example must be built first
@@ -195,7 +514,7 @@ mod tests {
     #[test]
     fn from_valid_executable() {
         let exec = make_exec();
-        let tr = TestRunner::from(exec);
+        let tr = TestRunner::new(exec, None).unwrap();
         let tr: Box<dyn provola_core::test_runners::TestRunner> = Box::new(tr);
         let tr_opt = TestRunnerOpt::default();
         assert!(tr.run(&tr_opt).is_ok());
@@ -206,7 +525,7 @@ mod tests {
     #[test]
     fn generate_available_tests_from_valid_executable() {
         let exec = make_exec();
-        let list = generate_available_tests(&exec).unwrap();
+        let list = generate_available_tests(&exec, &ProcessOpt::default()).unwrap();
         assert_eq!(list.len(), 4);
     }
 
@@ -222,4 +541,77 @@ Bar.
         let list = parse_available_tests(s).unwrap();
         insta::assert_debug_snapshot!(&list);
     }
+
+    #[test]
+    fn split_long_filters_in_batches() {
+        let tests: Vec<_> = ["Foo.Foo1", "Foo.Foo2", "Bar.Bar1"]
+            .iter()
+            .map(|x| {
+                let (test_suite, test_case) = x.split_once('.').unwrap();
+                FullyQualifiedTestCase::from_test_suite_test_case(test_suite, test_case)
+            })
+            .collect();
+        let tests: Vec<_> = tests.iter().collect();
+
+        let batches = make_filter_batches(&tests, 17);
+        let filters: Vec<_> = batches.iter().map(|(x, _)| x.0.clone().unwrap()).collect();
+        assert_eq!(filters, vec!["Foo.Foo1:Foo.Foo2", "Bar.Bar1"]);
+        assert_eq!(batches[0].1.len(), 2);
+
+        let batches = make_filter_batches(&tests, MAX_FILTER_LEN);
+        assert_eq!(batches.len(), 1);
+    }
+
+    #[test]
+    fn filter_from_selection() {
+        let mut tests = AvailableTests::default();
+        tests.push("Foo", "Foo1");
+        tests.push("Bar", "Bar1");
+        let selection = Selection {
+            include: vec![
+                Pattern::Index(1),
+                Pattern::Suite("Foo".to_string()),
+                Pattern::Name("Baz*".to_string()),
+            ],
+            exclude: vec![Pattern::Name("*.Slow*".to_string())],
+        };
+        let opt = TestRunnerOpt {
+            only: Only::Selection(selection),
+            ..Default::default()
+        };
+        let filter = make_test_filter(&opt, &tests).unwrap();
+        assert_eq!(filter.0.unwrap(), "Bar.Bar1:Foo.*:Baz*:*.Baz*-*.Slow*");
+    }
+
+    #[test]
+    fn repeat_and_shuffle_natively() {
+        let opt = TestRunnerOpt {
+            repeat: Some(Repeat {
+                count: Some(3),
+                seed: Some(42),
+                ..Default::default()
+            }),
+            ..Default::default()
+        };
+        assert_eq!(
+            make_repeat_argv(&opt, 3),
+            vec![
+                "--gtest_repeat=3",
+                "--gtest_shuffle",
+                "--gtest_random_seed=42"
+            ]
+        );
+        assert!(make_repeat_argv(&TestRunnerOpt::default(), 1).is_empty());
+    }
+
+    #[test]
+    fn filter_excluding_only() {
+        let tests = AvailableTests::default();
+        let opt = TestRunnerOpt {
+            only: Only::Selection(Selection::parse(["-Foo.*"]).unwrap()),
+            ..Default::default()
+        };
+        let filter = make_test_filter(&opt, &tests).unwrap();
+        assert_eq!(filter.0.unwrap(), "*-Foo.*");
+    }
 }
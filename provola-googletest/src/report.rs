@@ -1,4 +1,4 @@
-use provola_core::report::CoreStatus;
+use provola_core::report::{CoreStatus, SourceLocation};
 use provola_core::test::xunit::FullyQualifiedTestCase;
 use provola_core::test::xunit::FullyQualifiedTestCaseId;
 use provola_core::CoreFailure;
@@ -73,6 +73,9 @@ pub(crate) struct TestInfo {
     #[serde(default, skip_serializing_if = "Vec::is_empty")]
     failures: Vec<Failure>,
     result: String,
+    /// Where the test is defined, written by GoogleTest 1.11 or later
+    file: Option<String>,
+    line: Option<usize>,
     timestamp: Timestamp,
     #[serde(skip)]
     fqtc: Option<FullyQualifiedTestCaseId>,
@@ -89,6 +92,8 @@ impl From<UnitTest> for CoreReport {
             time: parse_duration(&x.time),
             timestamp: Some(x.timestamp),
             testsuites: x.testsuites.into_iter().map(|x| x.into()).collect(),
+            repeat: None,
+            coverage: None,
         }
     }
 }
@@ -140,6 +145,7 @@ impl From<TestInfo> for CoreTestCase {
             time: parse_duration(&x.time),
             failures: x.failures.into_iter().map(|x| x.into()).collect(),
             fqtc: x.fqtc,
+            location: x.file.map(|file| SourceLocation { file, line: x.line }),
             ..Default::default()
         }
     }
@@ -147,9 +153,20 @@ impl From<TestInfo> for CoreTestCase {
 
 impl From<Failure> for CoreFailure {
     fn from(x: Failure) -> Self {
-        CoreFailure {
-            message: x.failure,
-            ttype: x.ttype,
+        // The first line is the location of the failed assertion
+        let (first, rest) = x.failure.split_once('\n').unwrap_or((&x.failure, ""));
+
+        match SourceLocation::parse(first) {
+            Some(location) => CoreFailure {
+                message: rest.to_string(),
+                ttype: x.ttype,
+                location: Some(location),
+            },
+            None => CoreFailure {
+                message: x.failure,
+                ttype: x.ttype,
+                location: None,
+            },
         }
     }
 }
@@ -0,0 +1,104 @@
+use provola_core::test::xunit::{AvailableTests, FullyQualifiedTestCase};
+use provola_core::{CoreReport, CoreTestCase};
+use std::collections::HashMap;
+
+/// A process running a subset of the tests, as in the GoogleTest sharding
+/// protocol: the n-th runnable test is executed by the shard with index
+/// `n % total`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) struct Shard {
+    pub index: usize,
+    pub total: usize,
+}
+
+impl Shard {
+    /// Shards needed to run `runnable` tests with at most `max` processes
+    pub fn split(max: usize, runnable: usize) -> impl Iterator<Item = Shard> {
+        let total = max.min(runnable).max(1);
+        (0..total).map(move |index| Shard { index, total })
+    }
+
+    /// Environment variables selecting this shard. None is needed when
+    /// there is just one shard.
+    pub fn env(&self) -> Vec<(String, String)> {
+        if self.total == 1 {
+            return vec![];
+        }
+
+        vec![
+            ("GTEST_TOTAL_SHARDS".to_string(), self.total.to_string()),
+            ("GTEST_SHARD_INDEX".to_string(), self.index.to_string()),
+        ]
+    }
+
+    /// Tests executed by this shard, given all the runnable ones
+    pub fn tests<'a>(
+        &self,
+        runnable: &[&'a FullyQualifiedTestCase],
+    ) -> Vec<&'a FullyQualifiedTestCase> {
+        runnable
+            .iter()
+            .skip(self.index)
+            .step_by(self.total)
+            .copied()
+            .collect()
+    }
+}
+
+/// Disabled tests are not run, unless explicitly requested, and are not
+/// counted by the sharding protocol
+pub(crate) fn is_disabled(fqtc: &FullyQualifiedTestCase) -> bool {
+    fqtc.test_suite.0.starts_with("DISABLED_") || fqtc.test_case.0.starts_with("DISABLED_")
+}
+
+/// Sort test suites and test cases of a report merged from many shards as
+/// the available tests
+pub(crate) fn sort_like(report: &mut CoreReport, tests: &AvailableTests) {
+    let positions: HashMap<_, _> = tests.enumerate().map(|(i, x)| (x.id, i)).collect();
+    let position = |x: &CoreTestCase| {
+        x.fqtc
+            .and_then(|id| positions.get(&id).copied())
+            .unwrap_or(usize::MAX)
+    };
+
+    for test_suite in report.testsuites.iter_mut() {
+        test_suite.testcases.sort_by_key(position);
+    }
+
+    report
+        .testsuites
+        .sort_by_key(|x| x.testcases.first().map(position).unwrap_or(usize::MAX));
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tests::make_available_tests;
+
+    fn names(tests: Vec<&FullyQualifiedTestCase>) -> Vec<String> {
+        tests.iter().map(|x| x.to_string()).collect()
+    }
+
+    #[test]
+    fn split_runnable_tests() {
+        let tests = make_available_tests();
+        let runnable: Vec<_> = tests.iter().filter(|x| !is_disabled(x)).collect();
+        let shards: Vec<_> = Shard::split(3, runnable.len()).collect();
+        assert_eq!(shards.len(), 3);
+        assert_eq!(
+            names(shards[0].tests(&runnable)),
+            vec!["Foo.Foo1", "Bar.Bar2"]
+        );
+        assert_eq!(names(shards[1].tests(&runnable)), vec!["Foo.Foo3"]);
+        assert_eq!(names(shards[2].tests(&runnable)), vec!["Bar.Bar1"]);
+        assert_eq!(shards[2].env()[1].1, "2");
+    }
+
+    #[test]
+    fn do_not_split_few_tests() {
+        let shards: Vec<_> = Shard::split(8, 1).collect();
+        assert_eq!(shards, vec![Shard { index: 0, total: 1 }]);
+        assert!(shards[0].env().is_empty());
+        assert_eq!(Shard::split(8, 0).count(), 1);
+    }
+}
@@ -0,0 +1,17 @@
+package foo
+
+import "testing"
+
+func TestFoo1(t *testing.T) {
+	t.Errorf("expected %d, got %d", 1, 2)
+}
+
+func TestFoo2(t *testing.T) {
+}
+
+func TestBar(t *testing.T) {
+	t.Run("Bar1", func(t *testing.T) {})
+	t.Run("Bar2", func(t *testing.T) {
+		t.Skip("not ready")
+	})
+}
@@ -0,0 +1,3 @@
+module example.com/provola
+
+go 1.17
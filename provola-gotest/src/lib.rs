@@ -0,0 +1,233 @@
+use provola_core::test::xunit::FullyQualifiedTestCase;
+use provola_core::test_runners::{Selection, TestRunnerOpt};
+use provola_core::{run_process, AvailableTests, CoreReport, Error, Executable, ProcessOpt};
+use std::path::{Path, PathBuf};
+
+mod report;
+
+/// Escape characters with special meaning in a go regular expression
+fn escape_regex(s: &str) -> String {
+    let mut escaped = String::with_capacity(s.len());
+    for c in s.chars() {
+        if "\\.+*?()|[]{}^$".contains(c) {
+            escaped.push('\\');
+        }
+        escaped.push(c);
+    }
+    escaped
+}
+
+/// `-run` argument to select some tests. Subtests are separated by slash,
+/// and each part is matched separately by go, so more tests than needed may
+/// be selected.
+fn make_run_filter(test_cases: &[&str]) -> String {
+    let mut levels: Vec<Vec<String>> = Vec::new();
+
+    for test_case in test_cases {
+        for (level, part) in test_case.split('/').enumerate() {
+            if levels.len() <= level {
+                levels.push(Vec::new());
+            }
+
+            let part = escape_regex(part);
+
+            if !levels[level].contains(&part) {
+                levels[level].push(part);
+            }
+        }
+    }
+
+    levels
+        .into_iter()
+        .map(|x| match x.as_slice() {
+            [part] => format!("^{}$", part),
+            _ => format!("^({})$", x.join("|")),
+        })
+        .collect::<Vec<_>>()
+        .join("/")
+}
+
+/// Selection of the test cases in the report. A package which cannot be
+/// built has no test cases, just the build failure, which must be kept.
+fn make_selection(selected: &[FullyQualifiedTestCase]) -> Selection {
+    let build_failures = selected
+        .iter()
+        .map(|x| report::build_failure_id(&x.test_suite.0));
+    Selection::from_ids(selected.iter().map(|x| x.id).chain(build_failures))
+}
+
+pub struct TestRunner {
+    /// Package patterns, e.g. `./...`
+    patterns: Vec<String>,
+    /// When a directory is given, go is executed inside it
+    cwd: Option<PathBuf>,
+    /// Command running test binaries, passed to `-exec`
+    wrapper: Vec<String>,
+}
+
+impl TestRunner {
+    fn go_test_argv(&self) -> Vec<String> {
+        let mut argv = vec!["go".to_string(), "test".to_string(), "-json".to_string()];
+
+        if !self.wrapper.is_empty() {
+            argv.push("-exec".to_string());
+            argv.push(self.wrapper.join(" "));
+        }
+
+        argv
+    }
+
+    fn generate_available_tests(&self) -> Result<AvailableTests, Error> {
+        let mut argv = self.go_test_argv();
+        argv.push("-list".to_string());
+        argv.push(".".to_string());
+        argv.extend(self.patterns.iter().cloned());
+        let out = run_process(
+            &argv,
+            &ProcessOpt {
+                cwd: self.cwd.clone(),
+                ..Default::default()
+            },
+        )?
+        .out;
+        let events = report::parse_events(&out);
+        Ok(report::make_available_tests(events))
+    }
+
+    fn generate_report(&self, opt: &TestRunnerOpt) -> Result<CoreReport, Error> {
+        let mut argv = self.go_test_argv();
+
+        let selected = opt.only.find_selected(|| self.generate_available_tests())?;
+
+        if let Some(selected) = &selected {
+            let test_cases: Vec<&str> = selected.iter().map(|x| x.test_case.0.as_str()).collect();
+            argv.push("-run".to_string());
+            argv.push(make_run_filter(&test_cases));
+
+            // Test suite is the package
+            for fqtc in selected {
+                if !argv.contains(&fqtc.test_suite.0) {
+                    argv.push(fqtc.test_suite.0.clone());
+                }
+            }
+        } else {
+            argv.extend(self.patterns.iter().cloned());
+        }
+
+        let out = run_process(
+            &argv,
+            &ProcessOpt {
+                cwd: self.cwd.clone(),
+                ..Default::default()
+            },
+        )?
+        .out;
+        let events = report::parse_events(&out);
+        let mut report = report::make_core_report(events);
+
+        if let Some(cwd) = &self.cwd {
+            report.resolve_locations(cwd);
+        }
+
+        if let Some(selected) = &selected {
+            report.retain_selection(&make_selection(selected));
+        }
+
+        Ok(report)
+    }
+}
+
+impl From<Executable> for TestRunner {
+    fn from(executable: Executable) -> Self {
+        let args = Vec::from(executable.unwrapped());
+        let wrapper = executable.wrapper().to_vec();
+
+        match args.as_slice() {
+            [dir] if Path::new(dir).is_dir() => TestRunner {
+                patterns: vec!["./...".to_string()],
+                cwd: Some(PathBuf::from(dir)),
+                wrapper,
+            },
+            _ => TestRunner {
+                patterns: args,
+                cwd: None,
+                wrapper,
+            },
+        }
+    }
+}
+
+impl provola_core::test_runners::TestRunner for TestRunner {
+    fn run(&self, opt: &TestRunnerOpt) -> Result<provola_core::TestResult, provola_core::Error> {
+        let report = self.generate_report(opt)?;
+        let result = report.into();
+        Ok(result)
+    }
+
+    fn list(&self, _opt: &TestRunnerOpt) -> Result<AvailableTests, Error> {
+        self.generate_available_tests()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn run_filter_for_test() {
+        assert_eq!(make_run_filter(&["TestFoo"]), "^TestFoo$");
+    }
+
+    #[test]
+    fn run_filter_for_subtest() {
+        assert_eq!(
+            make_run_filter(&["TestBar/Bar_1.5"]),
+            "^TestBar$/^Bar_1\\.5$"
+        );
+    }
+
+    #[test]
+    fn run_filter_for_many_tests() {
+        let filter = make_run_filter(&["TestFoo", "TestBar/Bar_1", "TestBar/Bar_2"]);
+        assert_eq!(filter, "^(TestFoo|TestBar)$/^(Bar_1|Bar_2)$");
+    }
+
+    #[test]
+    fn select_test_of_package_not_built() {
+        let s = std::fs::read_to_string("examples/data/test_report.json").unwrap();
+        let mut report = report::make_core_report(report::parse_events(&s));
+        let selected = vec![FullyQualifiedTestCase::from_test_suite_test_case(
+            "example.com/provola/broken",
+            "TestBroken",
+        )];
+        report.retain_selection(&make_selection(&selected));
+        assert_eq!(report.testsuites.len(), 1);
+        assert_eq!(report.testsuites[0].testcases[0].name, report::BUILD_FAILED);
+        assert_eq!(report.errors, Some(1));
+        let result = provola_core::TestResult::from(report);
+        assert!(matches!(result, provola_core::TestResult::Fail(_)));
+    }
+
+    #[test]
+    fn wrapper_runs_test_binaries() {
+        let exec = Executable::from(PathBuf::from("examples/data"));
+        let tr = TestRunner::from(exec.wrap(vec!["taskset".to_string(), "-c".to_string()]));
+        assert_eq!(tr.patterns, vec!["./..."]);
+        assert_eq!(tr.cwd, Some(PathBuf::from("examples/data")));
+        assert_eq!(
+            tr.go_test_argv(),
+            vec!["go", "test", "-json", "-exec", "taskset -c"]
+        );
+    }
+
+    // Ignored because go must be installed
+    #[ignore]
+    #[test]
+    fn run_example_tests() {
+        let exec = Executable::from(PathBuf::from("examples/data"));
+        let tr = TestRunner::from(exec);
+        let tr: Box<dyn provola_core::test_runners::TestRunner> = Box::new(tr);
+        let tr_opt = TestRunnerOpt::default();
+        assert!(tr.run(&tr_opt).is_ok());
+    }
+}
@@ -0,0 +1,331 @@
+use provola_core::report::{captured_output, CoreStatus, Timestamp};
+use provola_core::test::xunit::{FullyQualifiedTestCase, FullyQualifiedTestCaseId};
+use provola_core::{AvailableTests, CoreFailure, CoreReport, CoreTestCase, CoreTestSuite};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+/// Name of the test case used to report a package which cannot be built
+pub(crate) const BUILD_FAILED: &str = "[build failed]";
+
+/// A single line of `go test -json` output
+/// See `go doc test2json`
+#[derive(Serialize, Deserialize, Debug, Default)]
+#[serde(rename_all = "PascalCase")]
+pub(crate) struct TestEvent {
+    pub time: Option<Timestamp>,
+    pub action: String,
+    pub package: Option<String>,
+    /// Only available for build events (`build-output` and `build-fail`),
+    /// e.g. `example.com/foo [example.com/foo.test]`
+    pub import_path: Option<String>,
+    pub test: Option<String>,
+    /// Seconds
+    pub elapsed: Option<f64>,
+    pub output: Option<String>,
+}
+
+impl TestEvent {
+    /// Build events have an import path instead of a package. Package is
+    /// the first part of it.
+    fn package(&self) -> Option<&str> {
+        self.package.as_deref().or_else(|| {
+            self.import_path
+                .as_deref()
+                .and_then(|x| x.split_whitespace().next())
+        })
+    }
+}
+
+/// Parse `go test -json` output. Lines which are not valid json (go may
+/// print something when it cannot build a package) are ignored.
+pub(crate) fn parse_events(s: &str) -> Vec<TestEvent> {
+    s.lines()
+        .filter_map(|line| serde_json::from_str(line).ok())
+        .collect()
+}
+
+fn parse_elapsed(x: Option<f64>) -> Option<std::time::Duration> {
+    x.map(std::time::Duration::from_secs_f64)
+}
+
+/// Output lines which are only noise in a failure message
+fn is_status_line(line: &str) -> bool {
+    let line = line.trim_start();
+    line.starts_with("=== RUN")
+        || line.starts_with("=== PAUSE")
+        || line.starts_with("=== CONT")
+        || line.starts_with("--- FAIL")
+        || line.starts_with("--- PASS")
+        || line.starts_with("--- SKIP")
+}
+
+#[derive(Default)]
+struct Test {
+    status: CoreStatus,
+    elapsed: Option<std::time::Duration>,
+    output: Vec<String>,
+}
+
+#[derive(Default)]
+struct Package {
+    tests: Vec<(String, Test)>,
+    failed: bool,
+    build_failed: bool,
+    output: Vec<String>,
+    elapsed: Option<std::time::Duration>,
+    timestamp: Option<Timestamp>,
+}
+
+impl Package {
+    fn test(&mut self, name: &str) -> &mut Test {
+        let index = match self.tests.iter().position(|x| x.0 == name) {
+            Some(index) => index,
+            None => {
+                self.tests.push((name.to_string(), Test::default()));
+                self.tests.len() - 1
+            }
+        };
+
+        &mut self.tests[index].1
+    }
+
+    fn has_failed_tests(&self) -> bool {
+        self.tests
+            .iter()
+            .any(|x| matches!(x.1.status, CoreStatus::Fail))
+    }
+}
+
+fn into_core_test_case(package: &str, name: String, test: Test) -> CoreTestCase {
+    let fqtc = FullyQualifiedTestCase::from_test_suite_test_case(package, &name);
+
+    let output = test
+        .output
+        .iter()
+        .filter(|x| !is_status_line(x))
+        .map(|x| x.trim())
+        .collect::<Vec<_>>()
+        .join("\n");
+
+    // Output of a failed test is its failure message, there is no need to
+    // repeat it
+    let (failures, system_out) = if matches!(test.status, CoreStatus::Fail) {
+        let failure = CoreFailure {
+            ttype: String::default(),
+            message: output,
+            location: None,
+        };
+
+        (vec![failure], None)
+    } else {
+        (Vec::default(), captured_output(Some(output)))
+    };
+
+    CoreTestCase {
+        fqtc: Some(fqtc.id),
+        classname: Some(package.to_string()),
+        name,
+        status: test.status,
+        time: test.elapsed,
+        failures,
+        system_out,
+        system_err: None,
+        repeat: None,
+        location: None,
+    }
+}
+
+fn into_core_test_suite(name: String, package: Package) -> CoreTestSuite {
+    // A package which fails without any failed test has not been able to
+    // run at all (e.g. build failure, panic in TestMain). This is reported
+    // as an error of the whole test suite, with a test case (like
+    // go-junit-report does) containing the output.
+    let error = package.build_failed || (package.failed && !package.has_failed_tests());
+
+    let mut testcases: Vec<CoreTestCase> = package
+        .tests
+        .into_iter()
+        .map(|(test_name, test)| into_core_test_case(&name, test_name, test))
+        .collect();
+
+    if error {
+        testcases.push(CoreTestCase {
+            fqtc: Some(build_failure_id(&name)),
+            classname: Some(name.clone()),
+            name: BUILD_FAILED.to_string(),
+            status: CoreStatus::Fail,
+            failures: vec![CoreFailure {
+                ttype: "error".to_string(),
+                message: package.output.concat().trim().to_string(),
+                location: None,
+            }],
+            ..Default::default()
+        });
+    }
+
+    let failures = testcases
+        .iter()
+        .filter(|x| matches!(x.status, CoreStatus::Fail))
+        .count();
+
+    let skipped = testcases
+        .iter()
+        .filter(|x| matches!(x.status, CoreStatus::Skipped))
+        .count();
+
+    CoreTestSuite {
+        name,
+        tests: testcases.len(),
+        errors: Some(error as usize),
+        failures: Some(failures),
+        skipped: Some(skipped),
+        time: package.elapsed,
+        timestamp: package.timestamp,
+        testcases,
+        ..Default::default()
+    }
+}
+
+/// Id of the test case reporting that a package cannot be built
+pub(crate) fn build_failure_id(package: &str) -> FullyQualifiedTestCaseId {
+    FullyQualifiedTestCase::from_test_suite_test_case(package, BUILD_FAILED).id
+}
+
+pub(crate) fn make_core_report(events: Vec<TestEvent>) -> CoreReport {
+    let mut packages: BTreeMap<String, Package> = BTreeMap::new();
+
+    for event in events {
+        let name = match event.package() {
+            Some(x) => x.to_string(),
+            None => continue,
+        };
+
+        let package = packages.entry(name).or_default();
+
+        match (event.test.as_deref(), event.action.as_str()) {
+            (_, "build-output") => {
+                package.output.extend(event.output);
+            }
+            (_, "build-fail") => {
+                package.build_failed = true;
+            }
+            (None, "start") => {
+                package.timestamp = event.time;
+            }
+            (None, "output") => {
+                package.output.extend(event.output);
+            }
+            (None, "fail") => {
+                package.failed = true;
+                package.elapsed = parse_elapsed(event.elapsed);
+            }
+            (None, "pass" | "skip") => {
+                package.elapsed = parse_elapsed(event.elapsed);
+            }
+            (Some(test), "output") => {
+                package.test(test).output.extend(event.output);
+            }
+            (Some(test), "run") => {
+                package.test(test).status = CoreStatus::Unknown;
+            }
+            (Some(test), action @ ("pass" | "fail" | "skip")) => {
+                let test = package.test(test);
+                test.elapsed = parse_elapsed(event.elapsed);
+                test.status = match action {
+                    "pass" => CoreStatus::Pass,
+                    "fail" => CoreStatus::Fail,
+                    _ => CoreStatus::Skipped,
+                };
+            }
+            _ => {}
+        }
+    }
+
+    let testsuites: Vec<CoreTestSuite> = packages
+        .into_iter()
+        .map(|(name, package)| into_core_test_suite(name, package))
+        .collect();
+
+    let errors = testsuites.iter().filter_map(|x| x.errors).sum();
+    let failures = testsuites.iter().filter_map(|x| x.failures).sum();
+    let tests = testsuites.iter().map(|x| x.tests).sum();
+
+    CoreReport {
+        name: Some("go test".to_string()),
+        errors: Some(errors),
+        failures: Some(failures),
+        tests: Some(tests),
+        testsuites,
+        ..Default::default()
+    }
+}
+
+/// Parse `go test -list . -json` output. Test names are printed as output
+/// of the package, followed by a summary line (e.g. `ok  example.com/foo`).
+pub(crate) fn make_available_tests(events: Vec<TestEvent>) -> AvailableTests {
+    let mut tests = AvailableTests::default();
+
+    for event in events {
+        if let (Some(package), Some(output)) = (event.package(), &event.output) {
+            let name = output.trim();
+
+            let is_test_name = !name.is_empty()
+                && !name.contains(char::is_whitespace)
+                && (name.starts_with("Test")
+                    || name.starts_with("Example")
+                    || name.starts_with("Fuzz"));
+
+            if is_test_name {
+                tests.push(package, name);
+            }
+        }
+    }
+
+    tests
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn read_example_file(path: &str) -> Vec<TestEvent> {
+        let s = std::fs::read_to_string(path).unwrap();
+        parse_events(&s)
+    }
+
+    #[test]
+    fn parse_json_events() {
+        let events = read_example_file("examples/data/test_report.json");
+        insta::assert_debug_snapshot!(&events);
+    }
+
+    #[test]
+    fn convert_to_core_report() {
+        let events = read_example_file("examples/data/test_report.json");
+        let report = make_core_report(events);
+        insta::assert_debug_snapshot!(&report);
+    }
+
+    #[test]
+    fn build_failure_is_a_test_suite_error() {
+        let events = read_example_file("examples/data/test_report.json");
+        let report = make_core_report(events);
+        let broken = report
+            .testsuites
+            .iter()
+            .find(|x| x.name == "example.com/provola/broken")
+            .unwrap();
+        assert_eq!(broken.errors, Some(1));
+        assert_eq!(broken.testcases[0].name, BUILD_FAILED);
+        assert!(broken.testcases[0].failures[0]
+            .message
+            .contains("syntax error"));
+    }
+
+    #[test]
+    fn parse_go_test_list_output() {
+        let events = read_example_file("examples/data/test_list.json");
+        let list = make_available_tests(events);
+        insta::assert_debug_snapshot!(&list);
+    }
+}
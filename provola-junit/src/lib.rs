@@ -0,0 +1,192 @@
+use provola_core::test::xunit::FullyQualifiedTestCase;
+use provola_core::test_runners::{Selection, TestRunnerOpt};
+use provola_core::{run_process, AvailableTests, CoreReport, Error, Executable, ProcessOpt};
+use std::path::PathBuf;
+use std::time::SystemTime;
+
+pub mod report;
+
+pub use report::parse_report;
+
+/// Arguments to be appended to the command to run a single test. Template
+/// is split on whitespace, then placeholders `{suite}`, `{case}` and
+/// `{classname}` are replaced in each argument. There isn't a classname in
+/// the list of available tests, so `{classname}` is the same as `{suite}`.
+fn make_filter_args(template: &str, fqtc: &FullyQualifiedTestCase) -> Vec<String> {
+    let suite = &fqtc.test_suite.0;
+    let case = &fqtc.test_case.0;
+
+    template
+        .split_whitespace()
+        .map(|x| {
+            x.replace("{suite}", suite)
+                .replace("{classname}", suite)
+                .replace("{case}", case)
+        })
+        .collect()
+}
+
+/// Report files matching a path or glob pattern
+pub fn find_report_files(pattern: &str) -> Result<Vec<PathBuf>, Error> {
+    let paths = glob::glob(pattern).map_err(|e| {
+        let msg = format!("Invalid report path {}: {}", pattern, e);
+        Error::GenericError(msg)
+    })?;
+
+    Ok(paths
+        .filter_map(Result::ok)
+        .filter(|x| x.is_file())
+        .collect())
+}
+
+/// Files modified after `time`, the others are left from previous runs
+fn written_since(paths: Vec<PathBuf>, time: SystemTime) -> Vec<PathBuf> {
+    paths
+        .into_iter()
+        .filter(|x| {
+            let modified = x.metadata().and_then(|x| x.modified());
+            modified.is_ok_and(|x| x >= time)
+        })
+        .collect()
+}
+
+/// Parse and merge report files, test suites with the same name are merged
+pub fn read_report_files(paths: &[PathBuf]) -> Result<CoreReport, Error> {
+    let mut report: Option<CoreReport> = None;
+
+    for path in paths {
+        log::debug!("Reading {}", path.display());
+        let s = std::fs::read_to_string(path)?;
+        let other = parse_report(&s)?;
+
+        match &mut report {
+            Some(report) => report.merge(other),
+            None => report = Some(other),
+        }
+    }
+
+    report.ok_or(Error::ReportUnavailable)
+}
+
+/// Run an arbitrary command which writes one or more JUnit XML reports
+pub struct TestRunner {
+    command: Vec<String>,
+    /// Path (or glob pattern) of the report files generated by the command
+    report: String,
+    /// Template of the arguments needed to run a single test
+    filter: Option<String>,
+}
+
+impl TestRunner {
+    pub fn new(executable: Executable, report: String, filter: Option<String>) -> Self {
+        TestRunner {
+            command: Vec::from(&executable),
+            report,
+            filter,
+        }
+    }
+
+    /// Available tests are read from the reports of a previous run. If
+    /// there isn't any, the command is executed to generate them.
+    fn generate_available_tests(&self) -> Result<AvailableTests, Error> {
+        let mut paths = find_report_files(&self.report)?;
+
+        if paths.is_empty() {
+            run_process(&self.command, &ProcessOpt::default())?;
+            paths = find_report_files(&self.report)?;
+        }
+
+        let report = read_report_files(&paths)?;
+        Ok(AvailableTests::from(&report))
+    }
+
+    fn generate_report(&self, opt: &TestRunnerOpt) -> Result<CoreReport, Error> {
+        let mut argv = self.command.clone();
+
+        // Selected tests must be resolved before deleting old reports
+        let selected = opt.only.find_selected(|| self.generate_available_tests())?;
+
+        // The filter template can select just one test, the others are
+        // filtered after running all of them
+        let post_filter = match (&selected, &self.filter) {
+            (Some(selected), Some(filter)) if selected.len() == 1 => {
+                argv.extend(make_filter_args(filter, &selected[0]));
+                None
+            }
+            (Some(selected), _) => Some(Selection::from_ids(selected.iter().map(|x| x.id))),
+            (None, _) => None,
+        };
+
+        // Old reports must not be mistaken for the result of this run, but
+        // they are not removed: the pattern may match unrelated files too
+        let start = SystemTime::now();
+
+        run_process(&argv, &ProcessOpt::default())?;
+
+        let paths = find_report_files(&self.report)?;
+        let paths = written_since(paths, start);
+        let mut report = read_report_files(&paths)?;
+
+        if let Some(selection) = &post_filter {
+            report.retain_selection(selection);
+        }
+
+        Ok(report)
+    }
+}
+
+impl provola_core::test_runners::TestRunner for TestRunner {
+    fn run(&self, opt: &TestRunnerOpt) -> Result<provola_core::TestResult, provola_core::Error> {
+        let report = self.generate_report(opt)?;
+        let result = report.into();
+        Ok(result)
+    }
+
+    fn list(&self, _opt: &TestRunnerOpt) -> Result<AvailableTests, Error> {
+        self.generate_available_tests()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn filter_args_from_template() {
+        let fqtc = FullyQualifiedTestCase::from_test_suite_test_case("Foo", "Foo1");
+        let args = make_filter_args("--filter {suite}::{case}", &fqtc);
+        assert_eq!(args, vec!["--filter", "Foo::Foo1"]);
+    }
+
+    #[test]
+    fn filter_args_with_classname() {
+        let fqtc = FullyQualifiedTestCase::from_test_suite_test_case("Foo", "Foo1");
+        let args = make_filter_args("-Dtest={classname}#{case}", &fqtc);
+        assert_eq!(args, vec!["-Dtest=Foo#Foo1"]);
+    }
+
+    #[test]
+    fn read_and_merge_report_files() {
+        let paths = find_report_files("examples/data/*.xml").unwrap();
+        assert_eq!(paths.len(), 2);
+        let report = read_report_files(&paths).unwrap();
+        assert_eq!(report.testsuites.len(), 3);
+        assert_eq!(report.tests, Some(6));
+        assert_eq!(report.failures, Some(2));
+    }
+
+    #[test]
+    fn ignore_reports_of_previous_runs() {
+        let paths = find_report_files("examples/data/*.xml").unwrap();
+        assert_eq!(written_since(paths.clone(), SystemTime::UNIX_EPOCH), paths);
+        assert!(written_since(paths, SystemTime::now()).is_empty());
+    }
+
+    #[test]
+    fn list_tests_from_existing_reports() {
+        let exec = Executable::Multiple(vec!["false".to_string()]);
+        let tr = TestRunner::new(exec, "examples/data/test_report.xml".to_string(), None);
+        let list = tr.generate_available_tests().unwrap();
+        assert_eq!(list.len(), 4);
+    }
+}
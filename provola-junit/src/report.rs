@@ -0,0 +1,313 @@
+//! Parser for JUnit XML reports, as generated by many test frameworks (Jest,
+//! PHPUnit, Maven Surefire, Boost.Test, ctest...).
+
+use provola_core::report::{captured_output, CoreStatus, SourceLocation, Timestamp};
+use provola_core::test::xunit::FullyQualifiedTestCase;
+use provola_core::{CoreFailure, CoreReport, CoreTestCase, CoreTestSuite, Error};
+use serde::{Deserialize, Serialize};
+
+type Duration = String;
+
+pub type Name = String;
+pub type ClassName = String;
+pub type FailureType = String;
+pub type Message = String;
+
+/// Time is in seconds, with decimals
+fn parse_duration(s: &str) -> Option<std::time::Duration> {
+    s.parse::<f64>()
+        .ok()
+        .filter(|x| x.is_finite() && *x >= 0.0)
+        .map(std::time::Duration::from_secs_f64)
+}
+
+/// Timestamp may have a timezone or not, in this case we assume UTC
+fn parse_timestamp(s: &str) -> Option<Timestamp> {
+    if let Ok(x) = chrono::DateTime::parse_from_rfc3339(s) {
+        return Some(x.with_timezone(&chrono::Utc));
+    }
+
+    let naive = chrono::NaiveDateTime::parse_from_str(s, "%Y-%m-%dT%H:%M:%S%.f").ok()?;
+    Some(Timestamp::from_utc(naive, chrono::Utc))
+}
+
+/// Root element is `<testsuites>`
+#[derive(Serialize, Deserialize, Debug, Default)]
+pub struct Report {
+    pub name: Option<Name>,
+    #[serde(rename = "testsuite", default)]
+    pub testsuites: Vec<TestSuite>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Default)]
+pub struct TestSuite {
+    #[serde(default)]
+    pub name: Name,
+    pub errors: Option<usize>,
+    pub failures: Option<usize>,
+    pub disabled: Option<usize>,
+    pub skipped: Option<usize>,
+    pub tests: Option<usize>,
+    pub time: Option<Duration>,
+    pub timestamp: Option<String>,
+    pub hostname: Option<String>,
+    pub id: Option<String>,
+    pub package: Option<String>,
+    #[serde(rename = "testcase", default)]
+    pub testcases: Vec<TestCase>,
+    #[serde(rename = "system-out")]
+    pub system_out: Option<String>,
+    #[serde(rename = "system-err")]
+    pub system_err: Option<String>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Default)]
+pub struct TestCase {
+    #[serde(default)]
+    pub name: Name,
+    pub classname: Option<ClassName>,
+    pub time: Option<Duration>,
+    pub status: Option<String>,
+    #[serde(rename = "failure", default)]
+    pub failures: Vec<Failure>,
+    #[serde(rename = "error", default)]
+    pub errors: Vec<Failure>,
+    pub skipped: Option<Failure>,
+    /// Where the test is defined, written by some frameworks, like pytest
+    pub file: Option<String>,
+    pub line: Option<String>,
+    #[serde(rename = "system-out")]
+    pub system_out: Option<String>,
+    #[serde(rename = "system-err")]
+    pub system_err: Option<String>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Default)]
+pub struct Failure {
+    #[serde(rename = "type", default)]
+    pub ttype: FailureType,
+    #[serde(default)]
+    pub message: Message,
+    #[serde(rename = "$value", default)]
+    pub text: String,
+}
+
+fn count_status(testcases: &[CoreTestCase], status: CoreStatus) -> usize {
+    testcases
+        .iter()
+        .filter(|x| std::mem::discriminant(&x.status) == std::mem::discriminant(&status))
+        .count()
+}
+
+impl From<Report> for CoreReport {
+    fn from(x: Report) -> Self {
+        let testsuites: Vec<CoreTestSuite> = x.testsuites.into_iter().map(|x| x.into()).collect();
+        let errors = testsuites.iter().filter_map(|x| x.errors).sum();
+        let failures = testsuites.iter().filter_map(|x| x.failures).sum();
+        let tests = testsuites.iter().map(|x| x.tests).sum();
+        let time = testsuites
+            .iter()
+            .filter_map(|x| x.time)
+            .reduce(|x, y| x + y);
+        let timestamp = testsuites.iter().filter_map(|x| x.timestamp).min();
+
+        CoreReport {
+            name: x.name,
+            errors: Some(errors),
+            failures: Some(failures),
+            tests: Some(tests),
+            time,
+            timestamp,
+            testsuites,
+            ..Default::default()
+        }
+    }
+}
+
+impl From<TestSuite> for CoreTestSuite {
+    fn from(x: TestSuite) -> Self {
+        let suite_name = x.name;
+
+        // Test cases with an error are counted as failures, only errors
+        // outside of test cases are left in the count of errors
+        let errored = x.testcases.iter().filter(|x| !x.errors.is_empty()).count();
+        let errors = x.errors.map(|x| x.saturating_sub(errored));
+
+        let testcases: Vec<CoreTestCase> = x
+            .testcases
+            .into_iter()
+            .map(|test_case| into_core_test_case(&suite_name, test_case))
+            .collect();
+
+        // Failures are counted from test cases, because some generators do
+        // not count errors as failures
+        let failures = count_status(&testcases, CoreStatus::Fail);
+        let skipped = count_status(&testcases, CoreStatus::Skipped);
+
+        CoreTestSuite {
+            tests: testcases.len(),
+            errors,
+            failures: Some(failures),
+            disabled: x.disabled,
+            skipped: Some(skipped),
+            hostname: x.hostname,
+            id: x.id,
+            package: x.package,
+            time: x.time.as_deref().and_then(parse_duration),
+            timestamp: x.timestamp.as_deref().and_then(parse_timestamp),
+            name: suite_name,
+            testcases,
+            system_out: captured_output(x.system_out),
+            system_err: captured_output(x.system_err),
+        }
+    }
+}
+
+fn into_core_test_case(test_suite: &str, x: TestCase) -> CoreTestCase {
+    let fqtc = FullyQualifiedTestCase::from_test_suite_test_case(test_suite, &x.name);
+
+    let status = if !x.failures.is_empty() || !x.errors.is_empty() {
+        CoreStatus::Fail
+    } else if x.skipped.is_some() || x.status.as_deref() == Some("notrun") {
+        CoreStatus::Skipped
+    } else {
+        CoreStatus::Pass
+    };
+
+    let errors = x.errors.into_iter().map(|mut x| {
+        if x.ttype.is_empty() {
+            x.ttype = "error".to_string();
+        }
+        x
+    });
+
+    CoreTestCase {
+        fqtc: Some(fqtc.id),
+        classname: x.classname,
+        name: x.name,
+        status,
+        time: x.time.as_deref().and_then(parse_duration),
+        failures: x
+            .failures
+            .into_iter()
+            .chain(errors)
+            .map(|x| x.into())
+            .collect(),
+        system_out: captured_output(x.system_out),
+        system_err: captured_output(x.system_err),
+        repeat: None,
+        location: x.file.map(|file| SourceLocation {
+            file,
+            line: x.line.and_then(|x| x.parse().ok()),
+        }),
+    }
+}
+
+impl From<Failure> for CoreFailure {
+    fn from(x: Failure) -> Self {
+        let text = x.text.trim();
+
+        // Text usually contains the message, with more details
+        let message = if text.is_empty() {
+            x.message
+        } else {
+            text.to_string()
+        };
+
+        CoreFailure {
+            ttype: x.ttype,
+            message,
+            location: None,
+        }
+    }
+}
+
+/// Name of the root element, skipping xml declaration and comments
+fn root_element_name(s: &str) -> Option<&str> {
+    let mut s = s;
+
+    loop {
+        let start = s.find('<')?;
+        s = &s[start + 1..];
+
+        if s.starts_with('?') || s.starts_with('!') {
+            continue;
+        }
+
+        let end = s.find(|c: char| c.is_whitespace() || c == '>' || c == '/')?;
+        return Some(&s[..end]);
+    }
+}
+
+fn parse_error(e: serde_xml_rs::Error) -> Error {
+    Error::ReportParseError(Box::new(e))
+}
+
+/// Parse a JUnit XML report. Root element can be `<testsuites>` or a single
+/// `<testsuite>`.
+pub fn parse_report(s: &str) -> Result<CoreReport, Error> {
+    match root_element_name(s) {
+        Some("testsuites") => {
+            let report: Report = serde_xml_rs::from_str(s).map_err(parse_error)?;
+            Ok(report.into())
+        }
+        Some("testsuite") => {
+            let test_suite: TestSuite = serde_xml_rs::from_str(s).map_err(parse_error)?;
+            let report = Report {
+                name: None,
+                testsuites: vec![test_suite],
+            };
+            Ok(report.into())
+        }
+        _ => Err(Error::ReportUnavailable),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn read_example_file(path: &str) -> String {
+        std::fs::read_to_string(path).unwrap()
+    }
+
+    #[test]
+    fn parse_xml_report() {
+        let s = read_example_file("examples/data/test_report.xml");
+        let report: Report = serde_xml_rs::from_str(&s).unwrap();
+        insta::assert_debug_snapshot!(&report);
+    }
+
+    #[test]
+    fn convert_to_core_report() {
+        let s = read_example_file("examples/data/test_report.xml");
+        let report = parse_report(&s).unwrap();
+        insta::assert_debug_snapshot!(&report);
+    }
+
+    #[test]
+    fn convert_single_test_suite_to_core_report() {
+        let s = read_example_file("examples/data/test_report_single_suite.xml");
+        let report = parse_report(&s).unwrap();
+        insta::assert_debug_snapshot!(&report);
+    }
+
+    #[test]
+    fn root_element_name_skips_declaration_and_comments() {
+        let s = "<?xml version=\"1.0\"?>\n<!-- comment -->\n<testsuite name=\"x\"/>";
+        assert_eq!(root_element_name(s), Some("testsuite"));
+    }
+
+    #[test]
+    fn count_errors_once() {
+        let s = read_example_file("examples/data/test_report_single_suite.xml");
+        let report = parse_report(&s).unwrap();
+        assert_eq!(report.failures, Some(1));
+        assert_eq!(report.errors, Some(0));
+    }
+
+    #[test]
+    fn parse_invalid_report() {
+        assert!(parse_report("not xml").is_err());
+    }
+}
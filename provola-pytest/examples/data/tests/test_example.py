@@ -0,0 +1,18 @@
+import pytest
+
+
+def test_foo():
+    print("foo")
+    assert False
+
+
+class TestBar:
+    def test_bar1(self):
+        assert True
+
+    def test_bar2(self):
+        assert True
+
+    @pytest.mark.skip(reason="not ready")
+    def test_bar3(self):
+        assert True
@@ -0,0 +1,13 @@
+import unittest
+
+
+class TestBaz(unittest.TestCase):
+    def test_baz1(self):
+        self.assertEqual(1, 2)
+
+    def test_baz2(self):
+        self.assertTrue(True)
+
+
+if __name__ == "__main__":
+    unittest.main()
@@ -0,0 +1,358 @@
+use provola_core::test::xunit::FullyQualifiedTestCaseId;
+use provola_core::test_runners::{Pattern, Selection, TestRunnerOpt};
+use provola_core::{
+    run_process, AvailableTests, CoreReport, Error, Executable, ProcessOpt, TempReport,
+};
+use std::path::{Path, PathBuf};
+use subprocess::{ExitStatus, Popen, PopenConfig, Redirection};
+
+mod report;
+mod unittest;
+
+const PYTHON: &str = "python3";
+
+fn python_argv(module: &str) -> Vec<String> {
+    vec![PYTHON.to_string(), "-m".to_string(), module.to_string()]
+}
+
+/// pytest is checked through the wrapper, because it may run python in a
+/// different environment
+fn is_pytest_available(wrapper: &[String]) -> bool {
+    let mut argv = [wrapper.to_vec(), python_argv("pytest")].concat();
+    argv.push("--version".to_string());
+
+    let status = Popen::create(
+        &argv,
+        PopenConfig {
+            stdout: Redirection::Pipe,
+            stderr: Redirection::Merge,
+            ..Default::default()
+        },
+    )
+    .and_then(|mut p| {
+        p.communicate(None)?;
+        p.wait()
+    });
+
+    matches!(status, Ok(ExitStatus::Exited(0)))
+}
+
+/// Convert a pytest node id (`tests/test_example.py::TestBar::test_bar1`) in
+/// test suite and test case. Test suite is the dotted module path followed
+/// by the class (`tests.test_example.TestBar`), which is what pytest uses as
+/// classname in junit reports.
+fn split_node_id(node_id: &str) -> Option<(String, String)> {
+    let mut parts = node_id.split("::");
+    let module = parts.next()?;
+    let module = module.strip_suffix(".py").unwrap_or(module);
+    let module = module.replace('/', ".");
+    let mut parts: Vec<&str> = parts.collect();
+    let test_case = parts.pop()?.to_string();
+    let test_suite = std::iter::once(module.as_str())
+        .chain(parts)
+        .collect::<Vec<_>>()
+        .join(".");
+    Some((test_suite, test_case))
+}
+
+/// Tests collected by pytest, with the node id needed to run each of them
+#[derive(Default)]
+struct Collected {
+    tests: AvailableTests,
+    node_ids: Vec<(FullyQualifiedTestCaseId, String)>,
+}
+
+impl Collected {
+    fn node_id(&self, id: FullyQualifiedTestCaseId) -> Option<&str> {
+        self.node_ids
+            .iter()
+            .find(|x| x.0 == id)
+            .map(|x| x.1.as_str())
+    }
+}
+
+fn parse_collected_tests(s: &str) -> Collected {
+    let mut collected = Collected::default();
+
+    for node_id in s.lines().filter(|x| x.contains("::")) {
+        if let Some((test_suite, test_case)) = split_node_id(node_id) {
+            collected.tests.push(test_suite, test_case);
+            let fqtc = collected.tests.iter().last().unwrap();
+            collected.node_ids.push((fqtc.id, node_id.to_string()));
+        }
+    }
+
+    collected
+}
+
+/// pytest `-m` expression equivalent to a selection made only of tags,
+/// which are pytest markers
+fn make_marker_expression(selection: &Selection) -> Option<String> {
+    let marker = |x: &Pattern| match x {
+        Pattern::Tag(tag) => Some(tag.clone()),
+        _ => None,
+    };
+
+    let include: Vec<String> = selection
+        .include
+        .iter()
+        .map(marker)
+        .collect::<Option<_>>()?;
+    let exclude: Vec<String> = selection
+        .exclude
+        .iter()
+        .map(marker)
+        .collect::<Option<_>>()?;
+
+    let mut terms = Vec::new();
+
+    if !include.is_empty() {
+        terms.push(format!("({})", include.join(" or ")));
+    }
+
+    terms.extend(exclude.iter().map(|x| format!("not {}", x)));
+
+    if terms.is_empty() {
+        None
+    } else {
+        Some(terms.join(" and "))
+    }
+}
+
+enum Backend {
+    Pytest,
+    Unittest,
+}
+
+pub struct TestRunner {
+    targets: Vec<String>,
+    /// Command running python, if any
+    wrapper: Vec<String>,
+    backend: Backend,
+}
+
+impl TestRunner {
+    /// Run a python module, through the wrapper
+    fn python_argv(&self, module: &str) -> Vec<String> {
+        [self.wrapper.clone(), python_argv(module)].concat()
+    }
+
+    fn pytest_collect(&self) -> Result<Collected, Error> {
+        let mut argv = self.python_argv("pytest");
+        argv.push("--collect-only".to_string());
+        argv.push("-q".to_string());
+        argv.extend(self.targets.iter().cloned());
+        let out = run_process(&argv, &ProcessOpt::default())?;
+        Ok(parse_collected_tests(&out.out))
+    }
+
+    fn pytest_run(&self, opt: &TestRunnerOpt) -> Result<CoreReport, Error> {
+        let report_file = TempReport::new("pytest", "xml");
+
+        let mut argv = self.python_argv("pytest");
+        argv.push(format!("--junitxml={}", report_file.display()));
+        // Captured output (and logging) of each test goes in the report
+        argv.push("-o".to_string());
+        argv.push("junit_logging=all".to_string());
+
+        let selection = opt.only.selection();
+
+        if let Some(expression) = selection.as_ref().and_then(make_marker_expression) {
+            argv.push("-m".to_string());
+            argv.push(expression);
+            argv.extend(self.targets.iter().cloned());
+        } else if let Some(selection) = selection {
+            let collected = self.pytest_collect()?;
+            let selected = selection.filter(&collected.tests);
+
+            if selected.is_empty() {
+                return Err(Error::NothingToDo);
+            }
+
+            for fqtc in selected {
+                argv.extend(collected.node_id(fqtc.id).map(String::from));
+            }
+        } else {
+            argv.extend(self.targets.iter().cloned());
+        }
+
+        run_process(&argv, &ProcessOpt::default())?;
+
+        let s = report_file.read_to_string()?;
+        report::parse_report(&s)
+    }
+
+    /// unittest discovers tests in a directory, matching file names with a
+    /// pattern. When target is a file, we use its directory and its name.
+    fn unittest_start_dir_and_pattern(&self) -> (PathBuf, Option<String>) {
+        let target = PathBuf::from(self.targets.first().map(String::as_str).unwrap_or("."));
+
+        if target.is_file() {
+            let dir = target.parent().map(Path::to_path_buf).unwrap_or_default();
+            let pattern = target.file_name().map(|x| x.to_string_lossy().to_string());
+            (dir, pattern)
+        } else {
+            (target, None)
+        }
+    }
+
+    fn unittest_discover(&self) -> Result<AvailableTests, Error> {
+        let (start_dir, pattern) = self.unittest_start_dir_and_pattern();
+        let mut argv = self.wrapper.clone();
+        argv.push(PYTHON.to_string());
+        argv.push("-c".to_string());
+        argv.push(unittest::DISCOVER_SCRIPT.to_string());
+        argv.push(start_dir.to_string_lossy().to_string());
+        argv.extend(pattern);
+        let out = run_process(&argv, &ProcessOpt::default())?;
+        Ok(unittest::parse_available_tests(&out.out))
+    }
+
+    fn unittest_run(&self, opt: &TestRunnerOpt) -> Result<CoreReport, Error> {
+        let (start_dir, pattern) = self.unittest_start_dir_and_pattern();
+
+        let mut argv = self.python_argv("unittest");
+        argv.push("-v".to_string());
+
+        // Test ids are relative to the start directory
+        let selected = opt.only.find_selected(|| self.unittest_discover())?;
+
+        if let Some(selected) = selected {
+            for fqtc in selected {
+                argv.push(format!("{}.{}", fqtc.test_suite.0, fqtc.test_case.0));
+            }
+        } else {
+            argv.push("discover".to_string());
+            argv.push("-s".to_string());
+            argv.push(".".to_string());
+            if let Some(pattern) = pattern {
+                argv.push("-p".to_string());
+                argv.push(pattern);
+            }
+        }
+
+        let cwd = if start_dir.as_os_str().is_empty() {
+            None
+        } else {
+            Some(start_dir.clone())
+        };
+
+        // unittest writes results to stderr
+        let out = run_process(
+            &argv,
+            &ProcessOpt {
+                cwd: cwd.clone(),
+                ..Default::default()
+            },
+        )?;
+        let mut report = unittest::parse_report(&out.err);
+
+        if let Some(cwd) = &cwd {
+            report.resolve_locations(cwd);
+        }
+
+        Ok(report)
+    }
+}
+
+impl From<Executable> for TestRunner {
+    fn from(executable: Executable) -> Self {
+        let targets = Vec::from(executable.unwrapped());
+        let wrapper = executable.wrapper().to_vec();
+
+        let backend = if is_pytest_available(&wrapper) {
+            Backend::Pytest
+        } else {
+            log::warn!("pytest not available, fallback to unittest");
+            Backend::Unittest
+        };
+
+        TestRunner {
+            targets,
+            wrapper,
+            backend,
+        }
+    }
+}
+
+impl provola_core::test_runners::TestRunner for TestRunner {
+    fn run(&self, opt: &TestRunnerOpt) -> Result<provola_core::TestResult, provola_core::Error> {
+        let report = match self.backend {
+            Backend::Pytest => self.pytest_run(opt)?,
+            Backend::Unittest => self.unittest_run(opt)?,
+        };
+        let result = report.into();
+        Ok(result)
+    }
+
+    fn list(&self, _opt: &TestRunnerOpt) -> Result<AvailableTests, Error> {
+        match self.backend {
+            Backend::Pytest => Ok(self.pytest_collect()?.tests),
+            Backend::Unittest => self.unittest_discover(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn wrapper_runs_python() {
+        let exec = Executable::from(PathBuf::from("tests/"));
+        let tr = TestRunner::from(exec.wrap(vec!["taskset".to_string(), "-c".to_string()]));
+        assert_eq!(tr.targets, vec!["tests/"]);
+        assert_eq!(
+            tr.python_argv("pytest"),
+            vec!["taskset", "-c", PYTHON, "-m", "pytest"]
+        );
+    }
+
+    #[test]
+    fn check_pytest_through_wrapper() {
+        assert!(!is_pytest_available(&["false".to_string()]));
+    }
+
+    #[test]
+    fn marker_expression_from_tags() {
+        let selection = Selection::parse(["[slow]", "[db]", "-[flaky]"]).unwrap();
+        let expression = make_marker_expression(&selection).unwrap();
+        assert_eq!(expression, "(slow or db) and not flaky");
+        let selection = Selection::parse(["[slow]", "test_foo"]).unwrap();
+        assert_eq!(make_marker_expression(&selection), None);
+    }
+
+    #[test]
+    fn split_node_id_of_function() {
+        let (test_suite, test_case) = split_node_id("tests/test_example.py::test_foo").unwrap();
+        assert_eq!(test_suite, "tests.test_example");
+        assert_eq!(test_case, "test_foo");
+    }
+
+    #[test]
+    fn split_node_id_of_method() {
+        let (test_suite, test_case) =
+            split_node_id("tests/test_example.py::TestBar::test_bar1").unwrap();
+        assert_eq!(test_suite, "tests.test_example.TestBar");
+        assert_eq!(test_case, "test_bar1");
+    }
+
+    #[test]
+    fn parse_pytest_collect_only_output() {
+        let s = std::fs::read_to_string("examples/data/collect_only.txt").unwrap();
+        let collected = parse_collected_tests(&s);
+        insta::assert_debug_snapshot!(&collected.tests);
+        assert_eq!(collected.node_ids.len(), 6);
+    }
+
+    // Ignored because pytest must be installed
+    #[ignore]
+    #[test]
+    fn run_example_tests() {
+        let exec = Executable::from(PathBuf::from("examples/data/tests"));
+        let tr = TestRunner::from(exec);
+        let tr: Box<dyn provola_core::test_runners::TestRunner> = Box::new(tr);
+        let tr_opt = TestRunnerOpt::default();
+        assert!(tr.run(&tr_opt).is_ok());
+    }
+}
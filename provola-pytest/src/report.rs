@@ -0,0 +1,102 @@
+use provola_core::report::{captured_output, CoreStatus};
+use provola_core::test::xunit::FullyQualifiedTestCase;
+use provola_core::{CoreReport, CoreTestCase, CoreTestSuite, Error};
+use std::collections::BTreeMap;
+
+fn count_status(testcases: &[CoreTestCase], status: CoreStatus) -> usize {
+    testcases
+        .iter()
+        .filter(|x| std::mem::discriminant(&x.status) == std::mem::discriminant(&status))
+        .count()
+}
+
+/// pytest puts every test case in a single test suite (named "pytest"),
+/// using classname to tell which module/class they belong to. We use
+/// module/class as test suite, so we can have the same structure we get
+/// when listing available tests.
+fn group_by_classname(testsuites: Vec<CoreTestSuite>) -> Vec<CoreTestSuite> {
+    let mut suites: BTreeMap<String, CoreTestSuite> = BTreeMap::new();
+
+    for testsuite in testsuites {
+        for mut testcase in testsuite.testcases {
+            let classname = testcase
+                .classname
+                .clone()
+                .unwrap_or_else(|| testsuite.name.clone());
+
+            let fqtc =
+                FullyQualifiedTestCase::from_test_suite_test_case(&classname, &testcase.name);
+            testcase.fqtc = Some(fqtc.id);
+            testcase.system_out = captured_output(testcase.system_out.map(strip_banners));
+            testcase.system_err = captured_output(testcase.system_err.map(strip_banners));
+
+            let suite = suites
+                .entry(classname.clone())
+                .or_insert_with(|| CoreTestSuite {
+                    name: classname,
+                    hostname: testsuite.hostname.clone(),
+                    ..Default::default()
+                });
+
+            suite.tests += 1;
+            suite.testcases.push(testcase);
+        }
+    }
+
+    suites
+        .into_values()
+        .map(|mut suite| {
+            suite.failures = Some(count_status(&suite.testcases, CoreStatus::Fail));
+            suite.skipped = Some(count_status(&suite.testcases, CoreStatus::Skipped));
+            suite
+        })
+        .collect()
+}
+
+/// Remove lines like `----- Captured Out -----`, written by pytest before
+/// each section of the captured output
+fn strip_banners(s: String) -> String {
+    let is_banner = |x: &str| {
+        x.starts_with('-')
+            && x.trim_matches('-')
+                .strip_prefix(" Captured ")
+                .is_some_and(|x| x.ends_with(' '))
+    };
+
+    s.lines()
+        .filter(|x| !is_banner(x))
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+/// Parse the JUnit XML report written by pytest
+pub fn parse_report(s: &str) -> Result<CoreReport, Error> {
+    let mut report = provola_junit::parse_report(s)?;
+    report.name = Some("pytest".to_string());
+    report.testsuites = group_by_classname(std::mem::take(&mut report.testsuites));
+    Ok(report)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn read_example_file() -> String {
+        std::fs::read_to_string("examples/data/test_report.xml").unwrap()
+    }
+
+    #[test]
+    fn strip_captured_output_banners() {
+        let s = "----- Captured Log -----\nINFO root:test.py:3 bar\n----- Captured Out -----\nfoo\n--- x";
+        assert_eq!(
+            strip_banners(s.to_string()),
+            "INFO root:test.py:3 bar\nfoo\n--- x"
+        );
+    }
+
+    #[test]
+    fn convert_to_core_report() {
+        let report = parse_report(&read_example_file()).unwrap();
+        insta::assert_debug_snapshot!(&report);
+    }
+}
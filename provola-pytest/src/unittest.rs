@@ -0,0 +1,198 @@
+//! Fallback for environments where pytest is not installed: tests are
+//! discovered and run with the `unittest` module of the standard library.
+
+use provola_core::report::CoreStatus;
+use provola_core::test::xunit::FullyQualifiedTestCase;
+use provola_core::{AvailableTests, CoreFailure, CoreReport, CoreTestCase, CoreTestSuite};
+use std::collections::BTreeMap;
+
+/// Print the id of every test discovered in the directory given as the first
+/// argument, one per line (e.g. `test_example.TestBar.test_bar1`)
+pub(crate) const DISCOVER_SCRIPT: &str = r#"
+import sys, unittest
+
+def walk(suite):
+    for test in suite:
+        if isinstance(test, unittest.TestSuite):
+            yield from walk(test)
+        else:
+            yield test.id()
+
+loader = unittest.defaultTestLoader
+pattern = sys.argv[2] if len(sys.argv) > 2 else "test*.py"
+for test_id in walk(loader.discover(sys.argv[1], pattern=pattern)):
+    print(test_id)
+"#;
+
+/// Split a unittest id (`module.Class.method`) in test suite (`module.Class`)
+/// and test case (`method`)
+fn split_test_id(id: &str) -> Option<(&str, &str)> {
+    id.rsplit_once('.')
+}
+
+pub(crate) fn parse_available_tests(s: &str) -> AvailableTests {
+    let mut tests = AvailableTests::default();
+
+    for (test_suite, test_case) in s.lines().filter_map(split_test_id) {
+        tests.push(test_suite, test_case);
+    }
+
+    tests
+}
+
+/// Parse a line like `test_baz1 (test_unittest.TestBaz.test_baz1) ... FAIL`.
+/// Before python 3.11 the method name is not repeated inside parenthesis.
+fn parse_result_line(line: &str) -> Option<(String, String, &str)> {
+    let (test, outcome) = line.split_once(" ... ")?;
+    let (name, class) = test.split_once(" (")?;
+    let class = class.strip_suffix(')')?;
+    let class = class
+        .strip_suffix(name)
+        .and_then(|x| x.strip_suffix('.'))
+        .unwrap_or(class);
+    Some((class.to_string(), name.to_string(), outcome))
+}
+
+/// Parse a line like `FAIL: test_baz1 (test_unittest.TestBaz.test_baz1)`,
+/// returning failure type, test suite and test case
+fn parse_failure_header(line: &str) -> Option<(String, String, String)> {
+    let (ttype, test) = line.split_once(": ")?;
+
+    if ttype != "FAIL" && ttype != "ERROR" {
+        return None;
+    }
+
+    let (class, name, _) = parse_result_line(&format!("{} ... ", test))?;
+    Some((ttype.to_string(), class, name))
+}
+
+fn status_from_outcome(outcome: &str) -> CoreStatus {
+    match outcome {
+        "ok" | "expected failure" => CoreStatus::Pass,
+        "FAIL" | "ERROR" | "unexpected success" => CoreStatus::Fail,
+        x if x.starts_with("skipped") => CoreStatus::Skipped,
+        _ => CoreStatus::Unknown,
+    }
+}
+
+/// Parse the output of `python -m unittest -v`, which is written to stderr
+pub(crate) fn parse_report(s: &str) -> CoreReport {
+    let mut suites: BTreeMap<String, CoreTestSuite> = BTreeMap::new();
+
+    for (class, name, outcome) in s.lines().filter_map(parse_result_line) {
+        let fqtc = FullyQualifiedTestCase::from_test_suite_test_case(&class, &name);
+        let suite = suites
+            .entry(class.clone())
+            .or_insert_with(|| CoreTestSuite {
+                name: class.clone(),
+                ..Default::default()
+            });
+
+        suite.tests += 1;
+        suite.testcases.push(CoreTestCase {
+            fqtc: Some(fqtc.id),
+            classname: Some(class),
+            name,
+            status: status_from_outcome(outcome),
+            ..Default::default()
+        });
+    }
+
+    // Tracebacks are printed after all results, each one in a section
+    // delimited by lines of `=` and `-`
+    let mut lines = s.lines().peekable();
+
+    while let Some(line) = lines.next() {
+        let (ttype, class, name) = match parse_failure_header(line) {
+            Some(x) => x,
+            None => continue,
+        };
+
+        // Skip the `-----` separator
+        lines.next();
+
+        let mut message = Vec::new();
+        while let Some(line) = lines.peek() {
+            if line.starts_with("=====") || line.starts_with("-----") {
+                break;
+            }
+            message.push(*line);
+            lines.next();
+        }
+
+        let message = message.join("\n").trim().to_string();
+
+        let testcase = suites
+            .get_mut(&class)
+            .and_then(|suite| suite.testcases.iter_mut().find(|x| x.name == name));
+
+        if let Some(testcase) = testcase {
+            testcase.failures.push(CoreFailure {
+                ttype,
+                message,
+                location: None,
+            });
+        }
+    }
+
+    let testsuites: Vec<CoreTestSuite> = suites
+        .into_values()
+        .map(|mut suite| {
+            let failures = suite
+                .testcases
+                .iter()
+                .filter(|x| matches!(x.status, CoreStatus::Fail))
+                .count();
+            suite.failures = Some(failures);
+            suite
+        })
+        .collect();
+
+    let failures = testsuites.iter().filter_map(|x| x.failures).sum();
+    let tests = testsuites.iter().map(|x| x.tests).sum();
+
+    CoreReport {
+        name: Some("unittest".to_string()),
+        failures: Some(failures),
+        tests: Some(tests),
+        testsuites,
+        ..Default::default()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_result_line_with_method_name() {
+        let (class, name, outcome) =
+            parse_result_line("test_baz1 (test_unittest.TestBaz.test_baz1) ... FAIL").unwrap();
+        assert_eq!(class, "test_unittest.TestBaz");
+        assert_eq!(name, "test_baz1");
+        assert_eq!(outcome, "FAIL");
+    }
+
+    #[test]
+    fn parse_result_line_without_method_name() {
+        let (class, name, outcome) =
+            parse_result_line("test_baz2 (test_unittest.TestBaz) ... ok").unwrap();
+        assert_eq!(class, "test_unittest.TestBaz");
+        assert_eq!(name, "test_baz2");
+        assert_eq!(outcome, "ok");
+    }
+
+    #[test]
+    fn parse_unittest_output() {
+        let s = std::fs::read_to_string("examples/data/unittest_output.txt").unwrap();
+        let report = parse_report(&s);
+        insta::assert_debug_snapshot!(&report);
+    }
+
+    #[test]
+    fn parse_discovered_tests() {
+        let s = "test_unittest.TestBaz.test_baz1\ntest_unittest.TestBaz.test_baz2\n";
+        let list = parse_available_tests(s);
+        assert_eq!(list.len(), 2);
+    }
+}
@@ -0,0 +1,399 @@
+//! Machine-readable reporters, for editor plugins and scripts.
+//!
+//! `Json` writes a single document when tests are finished, while
+//! `JsonLines` writes a document for each event, one per line, followed by
+//! the same document written by `Json`. Each document has the version of
+//! the schema, which changes when documents cannot be read as before.
+
+use provola_core::{Reason, Reporter, ReporterError, TestEvent, TestResult};
+use serde::{Deserialize, Serialize};
+use std::io::Write;
+use std::path::PathBuf;
+
+pub const SCHEMA_VERSION: u32 = 1;
+
+/// A line of the difference between the expected output and the actual one
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub enum DiffLine {
+    Same(String),
+    /// Expected, but missing from the actual output
+    Expected(String),
+    /// In the actual output, but not expected
+    Actual(String),
+}
+
+/// The result of a run
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct JsonResult {
+    pub schema_version: u32,
+    pub result: TestResult,
+    /// When the output is not the expected one (e.g. in data tests)
+    pub diff: Option<Vec<DiffLine>>,
+}
+
+/// Something happening while tests are running
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct JsonEvent {
+    pub schema_version: u32,
+    pub event: TestEvent,
+}
+
+/// A line written by `JsonLines`
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(untagged)]
+pub enum JsonLine {
+    Event(JsonEvent),
+    Result(JsonResult),
+}
+
+impl From<TestResult> for JsonResult {
+    fn from(result: TestResult) -> Self {
+        let diff = match &result {
+            TestResult::Pass(Reason::NotExpected { actual, expected })
+            | TestResult::Fail(Reason::NotExpected { actual, expected }) => {
+                diff_lines(expected, actual)
+            }
+            _ => None,
+        };
+
+        JsonResult {
+            schema_version: SCHEMA_VERSION,
+            result,
+            diff,
+        }
+    }
+}
+
+impl From<TestEvent> for JsonEvent {
+    fn from(event: TestEvent) -> Self {
+        JsonEvent {
+            schema_version: SCHEMA_VERSION,
+            event,
+        }
+    }
+}
+
+/// Largest table of `diff_lines`, about 32 MiB. Longer outputs have no diff.
+const MAX_DIFF_CELLS: usize = 4 * 1024 * 1024;
+
+/// Line by line difference, from their longest common subsequence. Lines
+/// which are the same at the start and at the end are not compared, while
+/// the remaining ones must fit in `MAX_DIFF_CELLS`, or `None` is returned.
+pub fn diff_lines(expected: &str, actual: &str) -> Option<Vec<DiffLine>> {
+    let a: Vec<&str> = expected.lines().collect();
+    let b: Vec<&str> = actual.lines().collect();
+
+    let prefix = a.iter().zip(&b).take_while(|(x, y)| x == y).count();
+    let suffix = a[prefix..]
+        .iter()
+        .rev()
+        .zip(b[prefix..].iter().rev())
+        .take_while(|(x, y)| x == y)
+        .count();
+    let same = |x: &[&str]| {
+        x.iter()
+            .map(|x| DiffLine::Same(x.to_string()))
+            .collect::<Vec<_>>()
+    };
+
+    let mut diff = same(&a[..prefix]);
+    let (a_end, b_end) = (a.len() - suffix, b.len() - suffix);
+    let (a_mid, b_mid) = (&a[prefix..a_end], &b[prefix..b_end]);
+
+    if (a_mid.len() + 1).saturating_mul(b_mid.len() + 1) > MAX_DIFF_CELLS {
+        return None;
+    }
+
+    diff.extend(diff_lcs(a_mid, b_mid));
+    diff.extend(same(&a[a_end..]));
+    Some(diff)
+}
+
+fn diff_lcs(a: &[&str], b: &[&str]) -> Vec<DiffLine> {
+    // lcs[i][j] is the length of the longest common subsequence of a[i..]
+    // and b[j..]
+    let mut lcs = vec![vec![0usize; b.len() + 1]; a.len() + 1];
+
+    for i in (0..a.len()).rev() {
+        for j in (0..b.len()).rev() {
+            lcs[i][j] = if a[i] == b[j] {
+                lcs[i + 1][j + 1] + 1
+            } else {
+                lcs[i + 1][j].max(lcs[i][j + 1])
+            };
+        }
+    }
+
+    let mut diff = Vec::new();
+    let (mut i, mut j) = (0, 0);
+
+    while i < a.len() || j < b.len() {
+        if i < a.len() && j < b.len() && a[i] == b[j] {
+            diff.push(DiffLine::Same(a[i].to_string()));
+            i += 1;
+            j += 1;
+        } else if j == b.len() || (i < a.len() && lcs[i + 1][j] >= lcs[i][j + 1]) {
+            diff.push(DiffLine::Expected(a[i].to_string()));
+            i += 1;
+        } else {
+            diff.push(DiffLine::Actual(b[j].to_string()));
+            j += 1;
+        }
+    }
+
+    diff
+}
+
+/// Where documents are written: a file, overwritten by `Json` at every run
+/// and appended to by `JsonLines`, or stdout
+#[derive(Default)]
+pub struct Output {
+    path: Option<PathBuf>,
+}
+
+impl Output {
+    pub fn new(path: Option<PathBuf>) -> Self {
+        Self { path }
+    }
+
+    fn write(&self, s: &str, append: bool) -> Result<(), ReporterError> {
+        let path = match &self.path {
+            Some(path) => path,
+            None => {
+                println!("{}", s);
+                return Ok(());
+            }
+        };
+
+        let mut file = std::fs::OpenOptions::new()
+            .create(true)
+            .write(true)
+            .append(append)
+            .truncate(!append)
+            .open(path)?;
+        writeln!(file, "{}", s)?;
+        Ok(())
+    }
+}
+
+fn to_json(value: &impl Serialize, pretty: bool) -> Result<String, ReporterError> {
+    let s = if pretty {
+        serde_json::to_string_pretty(value)
+    } else {
+        serde_json::to_string(value)
+    };
+
+    s.map_err(|e| ReporterError::IoError(e.into()))
+}
+
+#[derive(Default)]
+pub struct JsonReporter {
+    output: Output,
+}
+
+impl JsonReporter {
+    pub fn new(output: Output) -> Self {
+        Self { output }
+    }
+}
+
+impl Reporter for JsonReporter {
+    fn report(&self, result: TestResult) -> Result<(), ReporterError> {
+        let s = to_json(&JsonResult::from(result), true)?;
+        self.output.write(&s, false)
+    }
+}
+
+#[derive(Default)]
+pub struct JsonLinesReporter {
+    output: Output,
+}
+
+impl JsonLinesReporter {
+    pub fn new(output: Output) -> Self {
+        Self { output }
+    }
+}
+
+impl Reporter for JsonLinesReporter {
+    fn event(&self, event: &TestEvent) -> Result<(), ReporterError> {
+        let s = to_json(&JsonEvent::from(event.clone()), false)?;
+        self.output.write(&s, true)
+    }
+
+    fn report(&self, result: TestResult) -> Result<(), ReporterError> {
+        let s = to_json(&JsonResult::from(result), false)?;
+        self.output.write(&s, true)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use provola_core::report::{CoreStatus, Duration, SourceLocation};
+    use provola_core::test::xunit::FullyQualifiedTestCase;
+    use provola_core::{CoreFailure, CoreReport, CoreTestCase};
+
+    fn make_report() -> CoreReport {
+        let mut report = CoreReport {
+            name: Some("AllTests".to_string()),
+            ..Default::default()
+        };
+
+        let failed = CoreTestCase {
+            name: "Foo1".to_string(),
+            status: CoreStatus::Fail,
+            time: Some(Duration::from_millis(12)),
+            failures: vec![CoreFailure {
+                ttype: "assertion".to_string(),
+                message: "Expected: 1 == 2".to_string(),
+                location: Some(SourceLocation {
+                    file: "tests.cpp".to_string(),
+                    line: Some(12),
+                }),
+            }],
+            system_out: Some("Connecting to foo".to_string()),
+            ..Default::default()
+        };
+        let passed = CoreTestCase {
+            name: "Bar1".to_string(),
+            status: CoreStatus::Pass,
+            ..Default::default()
+        };
+
+        report.push_test_case("Foo", failed);
+        report.push_test_case("Bar", passed);
+        report.timestamp = "2022-01-02T03:04:05Z".parse().ok();
+        report
+    }
+
+    /// Documents are equal when they are written the same way
+    fn round_trip<T: Serialize + for<'a> Deserialize<'a>>(value: &T) -> T {
+        let s = serde_json::to_string(value).unwrap();
+        let read: T = serde_json::from_str(&s).unwrap();
+        assert_eq!(serde_json::to_string(&read).unwrap(), s);
+        read
+    }
+
+    #[test]
+    fn report_round_trip() {
+        let result = JsonResult::from(TestResult::from(make_report()));
+        let read = round_trip(&result);
+
+        assert_eq!(read.schema_version, SCHEMA_VERSION);
+        assert!(read.diff.is_none());
+
+        let report = read.result.report().unwrap();
+        assert!(matches!(read.result, TestResult::Fail(_)));
+        assert_eq!(report.failures, Some(1));
+        assert_eq!(
+            report.testsuites[0].testcases[0].failures[0].message,
+            "Expected: 1 == 2"
+        );
+        assert_eq!(report.timestamp, "2022-01-02T03:04:05Z".parse().ok());
+    }
+
+    #[test]
+    fn no_diff_of_long_outputs() {
+        let expected = (0..4096).map(|x| format!("{}\n", x)).collect::<String>();
+        let actual = expected.replace('1', "2");
+        assert_eq!(diff_lines(&expected, &actual), None);
+
+        // Lines which are the same at the start and at the end are not compared
+        let actual = format!("{}x\n{}", expected, expected);
+        let diff = diff_lines(&expected, &actual).unwrap();
+        assert_eq!(diff.len(), 4096 * 2 + 1);
+    }
+
+    #[test]
+    fn data_test_round_trip_with_diff() {
+        let result = TestResult::Fail(Reason::not_expected("a\nc\nd", "a\nb\nc"));
+        let read = round_trip(&JsonResult::from(result));
+
+        assert!(matches!(
+            &read.result,
+            TestResult::Fail(Reason::NotExpected { actual, .. }) if actual == "a\nc\nd"
+        ));
+        assert_eq!(
+            read.diff.unwrap(),
+            vec![
+                DiffLine::Same("a".to_string()),
+                DiffLine::Expected("b".to_string()),
+                DiffLine::Same("c".to_string()),
+                DiffLine::Actual("d".to_string()),
+            ]
+        );
+
+        let read = round_trip(&JsonResult::from(TestResult::Pass(Reason::Unknown)));
+        assert!(matches!(read.result, TestResult::Pass(Reason::Unknown)));
+    }
+
+    #[test]
+    fn schema_is_stable() {
+        let result = TestResult::Fail(Reason::Generic("cannot build".to_string()));
+        let value = serde_json::to_value(JsonResult::from(result)).unwrap();
+        let expected = serde_json::json!({
+            "schema_version": 1,
+            "result": {
+                "outcome": "Fail",
+                "reason": { "kind": "Generic", "content": "cannot build" }
+            },
+            "diff": null
+        });
+        assert_eq!(value, expected);
+
+        let fqtc = FullyQualifiedTestCase::from_test_suite_test_case("Foo", "Foo1");
+        let id = fqtc.id.to_string();
+        let value = serde_json::to_value(JsonEvent::from(TestEvent::TestStarted(fqtc))).unwrap();
+        let expected = serde_json::json!({
+            "schema_version": 1,
+            "event": {
+                "type": "TestStarted",
+                "content": { "test_suite": "Foo", "test_case": "Foo1", "id": id }
+            }
+        });
+        assert_eq!(value, expected);
+    }
+
+    #[test]
+    fn json_lines_round_trip() {
+        let fqtc = FullyQualifiedTestCase::from_test_suite_test_case("Foo", "Foo1");
+        let events = vec![
+            TestEvent::RunStarted(Some(2)),
+            TestEvent::TestStarted(fqtc.clone()),
+            TestEvent::TestFinished {
+                fqtc,
+                status: CoreStatus::Fail,
+                time: Some(Duration::from_millis(12)),
+            },
+            TestEvent::Output("Connecting to foo".to_string()),
+            TestEvent::RunFinished,
+        ];
+
+        let mut lines: Vec<String> = events
+            .into_iter()
+            .map(|x| serde_json::to_string(&JsonEvent::from(x)).unwrap())
+            .collect();
+        let result = JsonResult::from(TestResult::from(make_report()));
+        lines.push(serde_json::to_string(&result).unwrap());
+
+        let read: Vec<JsonLine> = lines
+            .iter()
+            .map(|x| serde_json::from_str(x).unwrap())
+            .collect();
+
+        assert_eq!(read.len(), 6);
+        assert!(matches!(
+            &read[2],
+            JsonLine::Event(JsonEvent {
+                event: TestEvent::TestFinished { fqtc, .. },
+                ..
+            }) if fqtc.to_string() == "Foo.Foo1"
+        ));
+        assert!(matches!(&read[5], JsonLine::Result(x) if x.result.report().is_some()));
+
+        for (line, read) in lines.iter().zip(&read) {
+            assert_eq!(&serde_json::to_string(read).unwrap(), line);
+        }
+    }
+}
@@ -1,36 +1,70 @@
 use lazy_static::lazy_static;
 use provola_core::{Error, Reporter, TestResult};
+use std::path::Path;
 use strum_macros::{Display, EnumString, IntoStaticStr};
 
+mod json;
+
+pub use json::{
+    diff_lines, DiffLine, JsonEvent, JsonLine, JsonLinesReporter, JsonReporter, JsonResult, Output,
+    SCHEMA_VERSION,
+};
+
 #[cfg(feature = "terminalreporter")]
 pub use provola_terminalreporter::TerminalReporter;
 
 #[cfg(feature = "terminalreporter")]
 pub use provola_terminalreporter::ColorfulTerminalReporter;
 
+#[cfg(feature = "filereporter")]
+pub use provola_filereporter::JUnitReporter;
+
 fn make<T: Reporter + Default + 'static>() -> Result<Box<dyn Reporter>, Error> {
     Ok(Box::new(T::default()))
 }
 
-pub fn make_reporter(rt: ReporterType) -> Result<Box<dyn Reporter>, Error> {
+/// Reporters writing to a file need its `path`, the others ignore it
+pub fn make_reporter(rt: ReporterType, path: Option<&Path>) -> Result<Box<dyn Reporter>, Error> {
     match rt {
         ReporterType::Basic => make::<BasicReporter>(),
 
+        ReporterType::Json => {
+            let output = Output::new(path.map(Path::to_path_buf));
+            Ok(Box::new(JsonReporter::new(output)))
+        }
+
+        ReporterType::JsonLines => {
+            let output = Output::new(path.map(Path::to_path_buf));
+            Ok(Box::new(JsonLinesReporter::new(output)))
+        }
+
         #[cfg(feature = "terminalreporter")]
         ReporterType::Terminal => make::<TerminalReporter>(),
 
         #[cfg(feature = "terminalreporter")]
         ReporterType::ColorfulTerminal => make::<ColorfulTerminalReporter>(),
+
+        #[cfg(feature = "filereporter")]
+        ReporterType::JUnit => {
+            let path = path.ok_or_else(|| {
+                Error::GenericError("JUnit reporter needs a report file".to_string())
+            })?;
+            Ok(Box::new(JUnitReporter::new(path.to_path_buf())))
+        }
     }
 }
 
 #[derive(Debug, EnumString, IntoStaticStr, Clone, Copy, Display)]
 pub enum ReporterType {
     Basic,
+    Json,
+    JsonLines,
     #[cfg(feature = "terminalreporter")]
     Terminal,
     #[cfg(feature = "terminalreporter")]
     ColorfulTerminal,
+    #[cfg(feature = "filereporter")]
+    JUnit,
 }
 
 #[derive(Default)]
@@ -0,0 +1,21 @@
+#!/bin/sh
+# A test runner which prints TAP to stdout
+
+echo "1..3"
+
+if [ "$(expr 1 + 1)" = "2" ]; then
+  echo "ok 1 - addition"
+else
+  echo "not ok 1 - addition"
+fi
+
+if [ "$(expr 2 \* 3)" = "5" ]; then
+  echo "ok 2 - multiplication"
+else
+  echo "not ok 2 - multiplication"
+  echo "  ---"
+  echo "  message: 2 * 3 is not 5"
+  echo "  ..."
+fi
+
+echo "ok 3 - division # SKIP not implemented"
@@ -0,0 +1,130 @@
+use provola_core::test_runners::{Selection, TestRunnerOpt};
+use provola_core::{run_process, AvailableTests, CoreReport, Error, Executable, ProcessOpt};
+use std::path::Path;
+
+mod report;
+
+fn is_real_test_case(name: &str) -> bool {
+    name != report::PLAN_MISMATCH && name != report::BAIL_OUT
+}
+
+fn make_available_tests(report: &CoreReport) -> AvailableTests {
+    let mut tests = AvailableTests::default();
+
+    for test_suite in report.testsuites.iter() {
+        for test_case in test_suite.testcases.iter() {
+            if is_real_test_case(&test_case.name) {
+                tests.push(&test_suite.name, &test_case.name);
+            }
+        }
+    }
+
+    tests
+}
+
+/// Name of the top level test suite, from the last argument (e.g. the test
+/// script or the directory containing the tests)
+fn suite_name(argv: &[String]) -> String {
+    argv.last()
+        .map(Path::new)
+        .and_then(|x| x.file_name())
+        .map(|x| x.to_string_lossy().to_string())
+        .unwrap_or_default()
+}
+
+/// Run an executable which writes TAP to stdout
+pub struct TestRunner {
+    argv: Vec<String>,
+}
+
+impl TestRunner {
+    fn generate_report(&self) -> Result<CoreReport, Error> {
+        let out = run_process(&self.argv, &ProcessOpt::default())?.out;
+        Ok(report::parse_report(&out, &suite_name(&self.argv)))
+    }
+
+    fn generate_selected_report(&self, opt: &TestRunnerOpt) -> Result<CoreReport, Error> {
+        let mut report = self.generate_report()?;
+
+        let selection = match opt.only.selection() {
+            Some(selection) => selection,
+            None => return Ok(report),
+        };
+
+        let tests = make_available_tests(&report);
+        let ids: Vec<_> = selection.filter(&tests).iter().map(|x| x.id).collect();
+
+        if ids.is_empty() {
+            return Err(Error::NothingToDo);
+        }
+
+        // TAP has no standard way to run a single test, so all tests are
+        // executed and only the selected ones are kept in the report.
+        report.retain_selection(&Selection::from_ids(ids));
+
+        if report.testsuites.is_empty() {
+            return Err(Error::NothingToDo);
+        }
+
+        Ok(report)
+    }
+}
+
+impl From<Executable> for TestRunner {
+    fn from(executable: Executable) -> Self {
+        let argv = Vec::from(&executable);
+        TestRunner { argv }
+    }
+}
+
+impl provola_core::test_runners::TestRunner for TestRunner {
+    fn run(&self, opt: &TestRunnerOpt) -> Result<provola_core::TestResult, provola_core::Error> {
+        let report = self.generate_selected_report(opt)?;
+        let result = report.into();
+        Ok(result)
+    }
+
+    fn list(&self, _opt: &TestRunnerOpt) -> Result<AvailableTests, Error> {
+        // Tests are known only after running them
+        let report = self.generate_report()?;
+        Ok(make_available_tests(&report))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use provola_core::test_runners::Only;
+    use provola_core::TestResult;
+
+    fn example_test_runner() -> TestRunner {
+        let argv = vec!["sh".to_string(), "examples/data/example.sh".to_string()];
+        TestRunner::from(Executable::Multiple(argv))
+    }
+
+    #[test]
+    fn run_example_tests() {
+        let tr: Box<dyn provola_core::test_runners::TestRunner> = Box::new(example_test_runner());
+        let tr_opt = TestRunnerOpt::default();
+        assert!(matches!(tr.run(&tr_opt), Ok(TestResult::Fail(_))));
+    }
+
+    #[test]
+    fn list_example_tests() {
+        let tr: Box<dyn provola_core::test_runners::TestRunner> = Box::new(example_test_runner());
+        let list = tr.list(&TestRunnerOpt::default()).unwrap();
+        assert_eq!(list.len(), 3);
+        assert_eq!(list.get(0).unwrap().test_suite.0, "example.sh");
+        assert_eq!(list.get(0).unwrap().test_case.0, "addition");
+    }
+
+    #[test]
+    fn run_single_example_test() {
+        let tr: Box<dyn provola_core::test_runners::TestRunner> = Box::new(example_test_runner());
+        let tr_opt = TestRunnerOpt {
+            only: Only::SingleByIndex(0),
+            ..Default::default()
+        };
+        assert!(matches!(tr.run(&tr_opt), Ok(TestResult::Pass(_))));
+    }
+}
@@ -0,0 +1,418 @@
+use provola_core::report::CoreStatus;
+use provola_core::test::xunit::FullyQualifiedTestCase;
+use provola_core::{CoreFailure, CoreReport, CoreTestCase, CoreTestSuite};
+
+/// Name of the test case used to report a plan which does not match the
+/// number of tests
+pub(crate) const PLAN_MISMATCH: &str = "[plan]";
+/// Name of the test case used to report a `Bail out!`
+pub(crate) const BAIL_OUT: &str = "[bail out]";
+
+/// Subtests are indented by 4 spaces
+const SUBTEST_INDENT: usize = 4;
+/// YAML diagnostic blocks are indented by 2 spaces
+const YAML_INDENT: usize = 2;
+
+#[derive(Debug, PartialEq, Eq)]
+enum Directive {
+    Skip,
+    Todo,
+}
+
+/// A line like `not ok 2 - Foo2 # TODO not implemented yet`
+#[derive(Debug, PartialEq, Eq)]
+struct TestPoint {
+    ok: bool,
+    number: Option<usize>,
+    description: String,
+    directive: Option<Directive>,
+}
+
+fn indentation(line: &str) -> usize {
+    line.len() - line.trim_start_matches(' ').len()
+}
+
+/// Find the `#` which starts a directive, ignoring escaped `\#`
+fn find_directive(s: &str) -> Option<usize> {
+    let bytes = s.as_bytes();
+    (0..bytes.len()).find(|&i| bytes[i] == b'#' && (i == 0 || bytes[i - 1] != b'\\'))
+}
+
+fn parse_directive(s: &str) -> Option<Directive> {
+    let s = s.trim_start().to_ascii_uppercase();
+
+    if s.starts_with("SKIP") {
+        Some(Directive::Skip)
+    } else if s.starts_with("TODO") {
+        Some(Directive::Todo)
+    } else {
+        None
+    }
+}
+
+fn parse_test_point(line: &str) -> Option<TestPoint> {
+    let (ok, rest) = if let Some(rest) = line.strip_prefix("not ok") {
+        (false, rest)
+    } else if let Some(rest) = line.strip_prefix("ok") {
+        (true, rest)
+    } else {
+        return None;
+    };
+
+    // `okay` is not a test point
+    if !rest.is_empty() && !rest.starts_with(' ') {
+        return None;
+    }
+
+    let rest = rest.trim_start();
+    let number_len = rest
+        .find(|c: char| !c.is_ascii_digit())
+        .unwrap_or(rest.len());
+    let number = rest[..number_len].parse().ok();
+    let rest = rest[number_len..].trim_start();
+    let rest = rest.strip_prefix('-').unwrap_or(rest);
+
+    let (description, directive) = match find_directive(rest) {
+        Some(index) => (&rest[..index], parse_directive(&rest[index + 1..])),
+        None => (rest, None),
+    };
+
+    Some(TestPoint {
+        ok,
+        number,
+        description: description.trim().replace("\\#", "#"),
+        directive,
+    })
+}
+
+/// Parse a plan like `1..6` or `1..0 # SKIP no tests`
+fn parse_plan(line: &str) -> Option<usize> {
+    let rest = line.strip_prefix("1..")?;
+    let len = rest
+        .find(|c: char| !c.is_ascii_digit())
+        .unwrap_or(rest.len());
+    rest[..len].parse().ok()
+}
+
+fn make_error_test_case(suite: &str, name: &str, message: String) -> CoreTestCase {
+    let fqtc = FullyQualifiedTestCase::from_test_suite_test_case(suite, name);
+
+    CoreTestCase {
+        fqtc: Some(fqtc.id),
+        classname: Some(suite.to_string()),
+        name: name.to_string(),
+        status: CoreStatus::Fail,
+        failures: vec![CoreFailure {
+            ttype: "error".to_string(),
+            message,
+            location: None,
+        }],
+        ..Default::default()
+    }
+}
+
+struct Parser<'a> {
+    lines: Vec<&'a str>,
+    pos: usize,
+    /// After `Bail out!` nothing else is parsed
+    bailed_out: bool,
+}
+
+impl<'a> Parser<'a> {
+    fn new(s: &'a str) -> Self {
+        Parser {
+            lines: s.lines().collect(),
+            pos: 0,
+            bailed_out: false,
+        }
+    }
+
+    /// Lines of a YAML diagnostic block following a test point, or the
+    /// comments following it (e.g. bats prints failure details as comments)
+    fn parse_diagnostic(&mut self, indent: usize) -> String {
+        let yaml_indent = indent + YAML_INDENT;
+        let is_yaml_start = |line: &str| indentation(line) == yaml_indent && line.trim() == "---";
+
+        let mut diagnostic = Vec::new();
+
+        if self.lines.get(self.pos).is_some_and(|x| is_yaml_start(x)) {
+            self.pos += 1;
+
+            while let Some(line) = self.lines.get(self.pos) {
+                self.pos += 1;
+
+                if indentation(line) == yaml_indent && line.trim() == "..." {
+                    break;
+                }
+
+                diagnostic.push(line.get(yaml_indent..).unwrap_or_else(|| line.trim()));
+            }
+        } else {
+            while let Some(line) = self.lines.get(self.pos) {
+                let comment = line.get(indent..).and_then(|x| x.strip_prefix('#'));
+
+                // A comment introducing a subtest is not a diagnostic
+                match comment {
+                    Some(x) if indentation(line) == indent && !is_subtest(x) => {
+                        diagnostic.push(x.strip_prefix(' ').unwrap_or(x));
+                        self.pos += 1;
+                    }
+                    _ => break,
+                }
+            }
+        }
+
+        diagnostic.join("\n")
+    }
+
+    /// Parse test points at the given indentation. Each subtest is added as
+    /// a new test suite, after the test suite containing it.
+    fn parse_suite(&mut self, indent: usize, name: &str, suites: &mut Vec<CoreTestSuite>) {
+        let index = suites.len();
+        suites.push(CoreTestSuite {
+            name: name.to_string(),
+            ..Default::default()
+        });
+
+        let mut plan: Option<usize> = None;
+        let mut test_points = 0;
+        // Name and index of the last subtest, waiting for its test point
+        let mut subtest: Option<(String, usize)> = None;
+
+        while let Some(line) = self.lines.get(self.pos) {
+            if line.trim().is_empty() {
+                self.pos += 1;
+                continue;
+            }
+
+            if indentation(line) < indent || self.bailed_out {
+                break;
+            }
+
+            let content = &line[indent..];
+
+            if let Some(subtest_name) = content.strip_prefix('#').and_then(is_subtest_header) {
+                self.pos += 1;
+                let full_name = format!("{}/{}", name, subtest_name);
+                let subtest_index = suites.len();
+                self.parse_suite(indent + SUBTEST_INDENT, &full_name, suites);
+                subtest = Some((subtest_name.to_string(), subtest_index));
+            } else if indentation(content) == SUBTEST_INDENT {
+                // TAP 14 allows subtests without the `# Subtest` comment,
+                // their name is the description of the following test point
+                let subtest_index = suites.len();
+                self.parse_suite(indent + SUBTEST_INDENT, name, suites);
+                subtest = Some((String::default(), subtest_index));
+            } else if let Some(reason) = content.strip_prefix("Bail out!") {
+                self.pos += 1;
+                let reason = reason.trim().to_string();
+                let test_case = make_error_test_case(name, BAIL_OUT, reason);
+                suites[index].testcases.push(test_case);
+                self.bailed_out = true;
+            } else if let Some(x) = parse_plan(content) {
+                self.pos += 1;
+                plan = Some(x);
+            } else if let Some(test_point) = parse_test_point(content) {
+                self.pos += 1;
+                test_points += 1;
+                let diagnostic = self.parse_diagnostic(indent);
+
+                // Test point of a subtest is a summary of the subtest suite
+                if let Some((subtest_name, subtest_index)) = subtest.take() {
+                    if subtest_name.is_empty() {
+                        let full_name = format!("{}/{}", name, test_point.description);
+                        suites[subtest_index].name = full_name;
+                    }
+
+                    if !suites[subtest_index].testcases.is_empty() {
+                        continue;
+                    }
+                }
+
+                let test_case = into_core_test_case(name, test_point, diagnostic);
+                suites[index].testcases.push(test_case);
+            } else {
+                // Anything else (version, comments, unknown lines) is ignored
+                self.pos += 1;
+            }
+        }
+
+        let suite = &mut suites[index];
+
+        // After a bail out, the plan is not expected to be satisfied
+        if !self.bailed_out && plan != Some(test_points) {
+            let message = match plan {
+                Some(plan) => format!("Planned {} tests, but {} ran", plan, test_points),
+                None => format!("Missing plan, {} tests ran", test_points),
+            };
+            let test_case = make_error_test_case(name, PLAN_MISMATCH, message);
+            suite.testcases.push(test_case);
+        }
+
+        let errors = suite
+            .testcases
+            .iter()
+            .filter(|x| x.name == BAIL_OUT || x.name == PLAN_MISMATCH)
+            .count();
+
+        let failures = suite
+            .testcases
+            .iter()
+            .filter(|x| matches!(x.status, CoreStatus::Fail))
+            .count();
+
+        let skipped = suite
+            .testcases
+            .iter()
+            .filter(|x| matches!(x.status, CoreStatus::Skipped))
+            .count();
+
+        suite.tests = suite.testcases.len();
+        suite.errors = Some(errors);
+        suite.failures = Some(failures);
+        suite.skipped = Some(skipped);
+    }
+}
+
+fn is_subtest(comment: &str) -> bool {
+    is_subtest_header(comment).is_some()
+}
+
+/// `# Subtest: name`, the `#` already stripped
+fn is_subtest_header(comment: &str) -> Option<&str> {
+    let name = comment.trim_start().strip_prefix("Subtest")?;
+    let name = name.strip_prefix(':').unwrap_or(name);
+    Some(name.trim())
+}
+
+fn into_core_test_case(suite: &str, x: TestPoint, diagnostic: String) -> CoreTestCase {
+    let name = if x.description.is_empty() {
+        format!("test {}", x.number.unwrap_or_default())
+    } else {
+        x.description
+    };
+
+    let fqtc = FullyQualifiedTestCase::from_test_suite_test_case(suite, &name);
+
+    // A failing TODO test is not a failure, it is just expected to fail
+    let status = match (x.directive, x.ok) {
+        (Some(Directive::Skip), _) => CoreStatus::Skipped,
+        (Some(Directive::Todo), _) => CoreStatus::Ignored,
+        (None, true) => CoreStatus::Pass,
+        (None, false) => CoreStatus::Fail,
+    };
+
+    let failures = if matches!(status, CoreStatus::Fail) {
+        vec![CoreFailure {
+            ttype: String::default(),
+            message: diagnostic,
+            location: None,
+        }]
+    } else {
+        Vec::default()
+    };
+
+    CoreTestCase {
+        fqtc: Some(fqtc.id),
+        classname: Some(suite.to_string()),
+        name,
+        status,
+        failures,
+        ..Default::default()
+    }
+}
+
+/// Parse TAP (version 13 or 14) output. Tests at top level are in a test
+/// suite with the given name, while each subtest is a test suite.
+pub(crate) fn parse_report(s: &str, name: &str) -> CoreReport {
+    let mut parser = Parser::new(s);
+    let mut testsuites = Vec::new();
+    parser.parse_suite(0, name, &mut testsuites);
+
+    // Top level suite is empty when there are only subtests
+    testsuites.retain(|x| x.tests > 0);
+
+    let errors = testsuites.iter().filter_map(|x| x.errors).sum();
+    let failures = testsuites.iter().filter_map(|x| x.failures).sum();
+    let tests = testsuites.iter().map(|x| x.tests).sum();
+
+    CoreReport {
+        name: Some(name.to_string()),
+        errors: Some(errors),
+        failures: Some(failures),
+        tests: Some(tests),
+        testsuites,
+        ..Default::default()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn read_example_file(path: &str) -> CoreReport {
+        let s = std::fs::read_to_string(path).unwrap();
+        parse_report(&s, "example")
+    }
+
+    #[test]
+    fn parse_test_point_with_directive() {
+        let x = parse_test_point("ok 3 - Foo3 \\# not a directive # SKIP reason").unwrap();
+        assert_eq!(
+            x,
+            TestPoint {
+                ok: true,
+                number: Some(3),
+                description: "Foo3 # not a directive".to_string(),
+                directive: Some(Directive::Skip),
+            }
+        );
+    }
+
+    #[test]
+    fn parse_test_point_without_number() {
+        let x = parse_test_point("not ok").unwrap();
+        assert!(!x.ok);
+        assert_eq!(x.number, None);
+        assert!(parse_test_point("okay").is_none());
+    }
+
+    #[test]
+    fn convert_to_core_report() {
+        let report = read_example_file("examples/data/test_report.tap");
+        insta::assert_debug_snapshot!(&report);
+    }
+
+    #[test]
+    fn convert_bats_output_to_core_report() {
+        let report = read_example_file("examples/data/bats_output.tap");
+        insta::assert_debug_snapshot!(&report);
+    }
+
+    #[test]
+    fn bail_out_is_a_test_suite_error() {
+        let report = read_example_file("examples/data/bail_out.tap");
+        let suite = &report.testsuites[0];
+        assert_eq!(suite.errors, Some(1));
+        assert_eq!(suite.testcases[1].name, BAIL_OUT);
+        assert_eq!(
+            suite.testcases[1].failures[0].message,
+            "Cannot connect to database"
+        );
+    }
+
+    #[test]
+    fn plan_mismatch_is_a_test_suite_error() {
+        let report = parse_report("1..3\nok 1\nok 2\n", "example");
+        assert_eq!(report.errors, Some(1));
+        assert_eq!(report.testsuites[0].testcases[2].name, PLAN_MISMATCH);
+    }
+
+    #[test]
+    fn subtest_without_comment_is_named_after_its_test_point() {
+        let s = "1..1\n    1..1\n    ok 1 - Bar1\nok 1 - Bar\n";
+        let report = parse_report(s, "example");
+        assert_eq!(report.testsuites.len(), 1);
+        assert_eq!(report.testsuites[0].name, "example/Bar");
+    }
+}
@@ -1,17 +1,24 @@
+use crate::progress::Progress;
 use colored::*;
+use provola_core::coverage::Coverage;
+use provola_core::CoreFailure;
 use provola_core::Reason;
 use provola_core::Reporter;
 use provola_core::ReporterError;
+use provola_core::TestEvent;
 use provola_core::TestResult;
+use std::cell::RefCell;
 use std::io::Write;
 
 #[derive(Default)]
-pub struct ThisReporter;
+pub struct ThisReporter {
+    progress: RefCell<Progress>,
+}
 
 trait ThisDisplay: Sized {
     fn tr_fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result;
 
-    fn to_tr_wrapper(&self) -> ThisWrapper<Self> {
+    fn to_tr_wrapper(&self) -> ThisWrapper<'_, Self> {
         ThisWrapper(self)
     }
 }
@@ -28,6 +35,14 @@ where
 }
 
 impl Reporter for ThisReporter {
+    fn event(&self, event: &TestEvent) -> Result<(), ReporterError> {
+        let mut writer = std::io::stderr();
+        self.progress
+            .borrow_mut()
+            .update(event, &mut writer, |x| format!("{} {}", "✖".red(), x))
+            .map_err(ReporterError::IoError)
+    }
+
     fn report(&self, result: TestResult) -> Result<(), ReporterError> {
         let mut writer = std::io::stdout();
         write!(writer, "{}", result.to_tr_wrapper()).map_err(ReporterError::IoError)
@@ -50,6 +65,56 @@ impl ThisDisplay for TestResult {
     }
 }
 
+/// Text linking to a url, as an OSC 8 escape sequence, which many terminals
+/// can open with a click
+fn hyperlink(text: &str, url: &str) -> String {
+    if colored::control::SHOULD_COLORIZE.should_colorize() {
+        format!("\x1b]8;;{}\x1b\\{}\x1b]8;;\x1b\\", url, text)
+    } else {
+        text.to_string()
+    }
+}
+
+impl ThisDisplay for CoreFailure {
+    fn tr_fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        if let Some(location) = &self.location {
+            let link = hyperlink(&location.to_string(), &location.url());
+            writeln!(f, "      {}", link.underline())?;
+        }
+
+        for line in self.message.lines() {
+            writeln!(f, "      {}", line)?;
+        }
+
+        Ok(())
+    }
+}
+
+impl ThisDisplay for Coverage {
+    fn tr_fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        writeln!(f, "{}", "Coverage".bold())?;
+
+        for file in &self.files {
+            let summary = file.summary();
+            write!(f, "  {} lines {}", file.file, summary.lines)?;
+            writeln!(f, " branches {}", summary.branches)?;
+        }
+
+        let summary = self.summary();
+        write!(f, "  {} lines {}", "Total".bold(), summary.lines)?;
+        write!(f, " branches {}", summary.branches)?;
+
+        match self.lines_delta() {
+            Some(delta) if delta > 0.0 => write!(f, " {}", format!("+{:.1}%", delta).green())?,
+            Some(delta) if delta < 0.0 => write!(f, " {}", format!("{:.1}%", delta).red())?,
+            Some(_) => write!(f, " {}", "unchanged".dimmed())?,
+            None => {}
+        }
+
+        writeln!(f)
+    }
+}
+
 impl ThisDisplay for Reason {
     fn tr_fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match &self {
@@ -63,16 +128,70 @@ impl ThisDisplay for Reason {
                     writeln!(f, "{}", name.bold())?;
                 }
 
+                if let Some(repeat) = &report.repeat {
+                    write!(f, "Repeated {} times", repeat.runs)?;
+
+                    if let Some(seed) = repeat.seed {
+                        write!(f, " with seed {}", seed)?;
+                    }
+
+                    writeln!(f)?;
+                }
+
+                let mut package = None;
+
                 for testsuite in &report.testsuites {
+                    // Test suites of each action of a composite action
+                    if testsuite.package.is_some() && testsuite.package != package {
+                        package = testsuite.package.clone();
+                        writeln!(
+                            f,
+                            "{}",
+                            testsuite
+                                .package
+                                .as_deref()
+                                .unwrap_or_default()
+                                .bold()
+                                .underline()
+                        )?;
+                    }
+
                     writeln!(f, "  {}", testsuite.name.bold())?;
 
                     for testcase in &testsuite.testcases {
                         let ok = testcase.failures.is_empty();
                         let symbol = if ok { "✔".green() } else { "✖".red() };
-                        writeln!(f, "    {} {}", symbol, testcase.name)?;
+                        write!(f, "    {} {}", symbol, testcase.name)?;
+
+                        if let Some(stats) = &testcase.repeat {
+                            if stats.is_flaky() {
+                                write!(f, " {}", "FLAKY".yellow().bold())?;
+                            }
+
+                            write!(f, " {}", stats.to_string().dimmed())?;
+                        }
+
+                        writeln!(f)?;
+
+                        for failure in &testcase.failures {
+                            write!(f, "{}", failure.to_tr_wrapper())?;
+                        }
+
+                        // Output of passed tests is just noise
+                        if let Some(output) = testcase.output().filter(|_| !ok) {
+                            writeln!(f, "      {}", "Output".dimmed())?;
+
+                            for line in output.lines() {
+                                writeln!(f, "      {}", line)?;
+                            }
+                        }
                     }
                 }
 
+                if let Some(coverage) = &report.coverage {
+                    write!(f, "{}", coverage.to_tr_wrapper())?;
+                }
+
                 Ok(())
             }
         }
@@ -82,6 +201,9 @@ impl ThisDisplay for Reason {
 #[cfg(test)]
 mod test {
     use super::*;
+    use provola_core::report::SourceLocation;
+    use provola_core::report::{CoreStatus, Duration, RepeatStats, RepeatSummary};
+    use provola_core::{CoreReport, CoreTestCase};
     use std::fmt::Write;
 
     #[test]
@@ -103,4 +225,143 @@ mod test {
         write!(s, "{}", res).unwrap();
         insta::assert_debug_snapshot!(s);
     }
+
+    #[test]
+    fn report_flaky() {
+        let mut report = CoreReport {
+            repeat: Some(RepeatSummary {
+                runs: 4,
+                seed: Some(42),
+            }),
+            ..Default::default()
+        };
+
+        let ms = |x| Some(Duration::from_millis(x));
+        let runs = [
+            (CoreStatus::Pass, ms(10)),
+            (CoreStatus::Fail, ms(30)),
+            (CoreStatus::Pass, ms(20)),
+            (CoreStatus::Pass, ms(40)),
+        ];
+        let test_case = CoreTestCase {
+            name: "Foo1".to_string(),
+            status: CoreStatus::Fail,
+            failures: vec![CoreFailure::default()],
+            repeat: Some(RepeatStats::new(&runs)),
+            ..Default::default()
+        };
+        report.push_test_case("Foo", test_case);
+
+        let mut s = String::new();
+        let res = TestResult::Fail(Reason::Report(report));
+        let res = res.to_tr_wrapper();
+        write!(s, "{}", res).unwrap();
+        insta::assert_debug_snapshot!(s);
+    }
+
+    #[test]
+    fn report_failure_location() {
+        let mut report = CoreReport::default();
+        let test_case = CoreTestCase {
+            name: "Foo1".to_string(),
+            status: CoreStatus::Fail,
+            failures: vec![CoreFailure {
+                ttype: "".to_string(),
+                message: "Value of: true\nExpected: false".to_string(),
+                location: Some(SourceLocation {
+                    file: "/src/tests.cpp".to_string(),
+                    line: Some(4),
+                }),
+            }],
+            ..Default::default()
+        };
+        report.push_test_case("Foo", test_case);
+
+        let mut s = String::new();
+        let res = TestResult::Fail(Reason::Report(report));
+        let res = res.to_tr_wrapper();
+        write!(s, "{}", res).unwrap();
+        insta::assert_debug_snapshot!(s);
+    }
+
+    #[test]
+    fn report_failure_output() {
+        let mut report = CoreReport::default();
+        let failed = CoreTestCase {
+            name: "Foo1".to_string(),
+            status: CoreStatus::Fail,
+            failures: vec![CoreFailure {
+                message: "Expected: false".to_string(),
+                ..Default::default()
+            }],
+            system_out: Some("Connecting to foo".to_string()),
+            system_err: Some("Connection refused".to_string()),
+            ..Default::default()
+        };
+        let passed = CoreTestCase {
+            name: "Foo2".to_string(),
+            status: CoreStatus::Pass,
+            system_out: Some("Connecting to foo".to_string()),
+            ..Default::default()
+        };
+        report.push_test_case("Foo", failed);
+        report.push_test_case("Foo", passed);
+
+        let mut s = String::new();
+        let res = TestResult::Fail(Reason::Report(report));
+        let res = res.to_tr_wrapper();
+        write!(s, "{}", res).unwrap();
+        insta::assert_debug_snapshot!(s);
+    }
+
+    #[test]
+    fn report_composite() {
+        let mut report = CoreReport::default();
+
+        for (package, suite) in [("unit", "Foo"), ("unit", "Bar"), ("integration", "Foo")] {
+            let test_case = CoreTestCase {
+                name: "Test1".to_string(),
+                status: CoreStatus::Pass,
+                ..Default::default()
+            };
+            let mut other = CoreReport::default();
+            other.push_test_case(suite, test_case);
+            other.testsuites[0].package = Some(package.to_string());
+            report.merge(other);
+        }
+
+        let mut s = String::new();
+        let res = TestResult::Pass(Reason::Report(report));
+        let res = res.to_tr_wrapper();
+        write!(s, "{}", res).unwrap();
+        insta::assert_debug_snapshot!(s);
+    }
+
+    #[test]
+    fn report_coverage() {
+        let lcov = "SF:/src/foo.c\nBRDA:4,0,0,1\nBRDA:4,0,1,0\nDA:3,1\nDA:4,1\nDA:5,0\nend_of_record\nSF:/src/bar.c\nDA:1,4\nend_of_record\n";
+        let mut coverage = Coverage::parse_lcov(lcov).unwrap();
+        coverage.previous = Some(provola_core::coverage::CoverageSummary {
+            lines: provola_core::coverage::Counts {
+                covered: 1,
+                total: 2,
+            },
+            ..Default::default()
+        });
+
+        let mut report = CoreReport::default();
+        let test_case = CoreTestCase {
+            name: "Foo1".to_string(),
+            status: CoreStatus::Pass,
+            ..Default::default()
+        };
+        report.push_test_case("Foo", test_case);
+        report.coverage = Some(coverage);
+
+        let mut s = String::new();
+        let res = TestResult::Pass(Reason::Report(report));
+        let res = res.to_tr_wrapper();
+        write!(s, "{}", res).unwrap();
+        insta::assert_debug_snapshot!(s);
+    }
 }
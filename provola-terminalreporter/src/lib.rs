@@ -1,4 +1,5 @@
 mod colorful;
+mod progress;
 mod simple;
 
 pub use colorful::ThisReporter as ColorfulTerminalReporter;
@@ -0,0 +1,121 @@
+use provola_core::report::CoreStatus;
+use provola_core::test::xunit::FullyQualifiedTestCase;
+use provola_core::TestEvent;
+use std::io::{IsTerminal, Write};
+
+/// Erase the current line of the terminal
+const CLEAR_LINE: &str = "\r\x1b[K";
+
+/// Progress of tests while they are running. The running test is shown on a
+/// line which is overwritten, only if it is a terminal, while failed tests
+/// are written as soon as they are known.
+pub(crate) struct Progress {
+    total: Option<usize>,
+    finished: usize,
+    is_terminal: bool,
+}
+
+impl Default for Progress {
+    fn default() -> Self {
+        Self::new(std::io::stderr().is_terminal())
+    }
+}
+
+impl Progress {
+    pub fn new(is_terminal: bool) -> Self {
+        Self {
+            total: None,
+            finished: 0,
+            is_terminal,
+        }
+    }
+
+    pub fn update(
+        &mut self,
+        event: &TestEvent,
+        writer: &mut impl Write,
+        failed: impl Fn(&FullyQualifiedTestCase) -> String,
+    ) -> std::io::Result<()> {
+        match event {
+            TestEvent::RunStarted(total) => {
+                self.total = *total;
+                self.finished = 0;
+            }
+            TestEvent::TestStarted(fqtc) if self.is_terminal => {
+                write!(writer, "{}", CLEAR_LINE)?;
+
+                match self.total {
+                    Some(total) => write!(writer, "[{}/{}] {}", self.finished, total, fqtc)?,
+                    None => write!(writer, "[{}] {}", self.finished, fqtc)?,
+                }
+            }
+            TestEvent::TestFinished { fqtc, status, .. } => {
+                self.finished += 1;
+
+                if let CoreStatus::Fail = status {
+                    if self.is_terminal {
+                        write!(writer, "{}", CLEAR_LINE)?;
+                    }
+
+                    writeln!(writer, "{}", failed(fqtc))?;
+                }
+            }
+            TestEvent::RunFinished if self.is_terminal => {
+                write!(writer, "{}", CLEAR_LINE)?;
+            }
+            _ => {}
+        }
+
+        writer.flush()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn make_events() -> Vec<TestEvent> {
+        let foo1 = FullyQualifiedTestCase::from_test_suite_test_case("Foo", "Foo1");
+        let foo2 = FullyQualifiedTestCase::from_test_suite_test_case("Foo", "Foo2");
+        let finished = |fqtc: &FullyQualifiedTestCase, status| TestEvent::TestFinished {
+            fqtc: fqtc.clone(),
+            status,
+            time: None,
+        };
+
+        vec![
+            TestEvent::RunStarted(Some(2)),
+            TestEvent::TestStarted(foo1.clone()),
+            finished(&foo1, CoreStatus::Pass),
+            TestEvent::TestStarted(foo2.clone()),
+            TestEvent::Output("tests.cpp:12: Failure".to_string()),
+            finished(&foo2, CoreStatus::Fail),
+            TestEvent::RunFinished,
+        ]
+    }
+
+    fn show(is_terminal: bool) -> String {
+        let mut progress = Progress::new(is_terminal);
+        let mut writer = Vec::new();
+
+        for event in make_events() {
+            progress
+                .update(&event, &mut writer, |x| format!("FAIL {}", x))
+                .unwrap();
+        }
+
+        String::from_utf8(writer).unwrap()
+    }
+
+    #[test]
+    fn show_failed_tests_only() {
+        assert_eq!(show(false), "FAIL Foo.Foo2\n");
+    }
+
+    #[test]
+    fn show_running_test_on_terminal() {
+        let expected =
+            "\r\x1b[K[0/2] Foo.Foo1\r\x1b[K[1/2] Foo.Foo2\r\x1b[KFAIL Foo.Foo2\n\r\x1b[K";
+        assert_eq!(show(true), expected);
+    }
+}
@@ -1,16 +1,21 @@
+use crate::progress::Progress;
 use provola_core::Reason;
 use provola_core::Reporter;
 use provola_core::ReporterError;
+use provola_core::TestEvent;
 use provola_core::TestResult;
+use std::cell::RefCell;
 use std::io::Write;
 
 #[derive(Default)]
-pub struct ThisReporter;
+pub struct ThisReporter {
+    progress: RefCell<Progress>,
+}
 
 trait ThisDisplay: Sized {
     fn tr_fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result;
 
-    fn to_tr_wrapper(&self) -> ThisWrapper<Self> {
+    fn to_tr_wrapper(&self) -> ThisWrapper<'_, Self> {
         ThisWrapper(self)
     }
 }
@@ -27,6 +32,14 @@ where
 }
 
 impl Reporter for ThisReporter {
+    fn event(&self, event: &TestEvent) -> Result<(), ReporterError> {
+        let mut writer = std::io::stderr();
+        self.progress
+            .borrow_mut()
+            .update(event, &mut writer, |x| format!("FAIL {}", x))
+            .map_err(ReporterError::IoError)
+    }
+
     fn report(&self, result: TestResult) -> Result<(), ReporterError> {
         let mut writer = std::io::stdout();
         write!(writer, "{}", result.to_tr_wrapper()).map_err(ReporterError::IoError)
@@ -76,7 +89,25 @@ impl ThisDisplay for Reason {
 
                 writeln!(f)?;
 
+                if let Some(repeat) = &report.repeat {
+                    write!(f, "repeated: {}", repeat.runs)?;
+
+                    if let Some(seed) = repeat.seed {
+                        write!(f, " | seed: {}", seed)?;
+                    }
+
+                    writeln!(f)?;
+                }
+
+                let mut package = None;
+
                 for testsuite in &report.testsuites {
+                    // Test suites of each action of a composite action
+                    if testsuite.package.is_some() && testsuite.package != package {
+                        package = testsuite.package.clone();
+                        writeln!(f, "  {}", testsuite.package.as_deref().unwrap_or_default())?;
+                    }
+
                     write!(f, "    {} | ", testsuite.name)?;
 
                     write!(f, "tests: {} | ", testsuite.tests)?;
@@ -98,8 +129,36 @@ impl ThisDisplay for Reason {
                     for testcase in &testsuite.testcases {
                         let ok = testcase.failures.is_empty();
                         let result = if ok { "PASS" } else { "FAIL" };
-                        writeln!(f, "        {} {}", testcase.name, result)?;
+                        write!(f, "        {} {}", testcase.name, result)?;
+
+                        if let Some(stats) = &testcase.repeat {
+                            if stats.is_flaky() {
+                                write!(f, " FLAKY")?;
+                            }
+
+                            write!(f, " | {}", stats)?;
+                        }
+
+                        writeln!(f)?;
+
+                        if let Some(output) = testcase.output().filter(|_| !ok) {
+                            for line in output.lines() {
+                                writeln!(f, "            {}", line)?;
+                            }
+                        }
+                    }
+                }
+
+                if let Some(coverage) = &report.coverage {
+                    let summary = coverage.summary();
+                    write!(f, "coverage | lines: {} | ", summary.lines)?;
+                    write!(f, "branches: {} | ", summary.branches)?;
+
+                    if let Some(delta) = coverage.lines_delta() {
+                        write!(f, "delta: {:+.1}% | ", delta)?;
                     }
+
+                    writeln!(f)?;
                 }
 
                 Ok(())
@@ -111,6 +170,8 @@ impl ThisDisplay for Reason {
 #[cfg(test)]
 mod test {
     use super::*;
+    use provola_core::report::{CoreStatus, Duration, RepeatStats, RepeatSummary};
+    use provola_core::{CoreFailure, CoreReport, CoreTestCase};
     use std::fmt::Write;
 
     #[test]
@@ -132,4 +193,37 @@ mod test {
         write!(s, "{}", res).unwrap();
         insta::assert_debug_snapshot!(s);
     }
+
+    #[test]
+    fn report_flaky() {
+        let mut report = CoreReport {
+            repeat: Some(RepeatSummary {
+                runs: 4,
+                seed: Some(42),
+            }),
+            ..Default::default()
+        };
+
+        let ms = |x| Some(Duration::from_millis(x));
+        let runs = [
+            (CoreStatus::Pass, ms(10)),
+            (CoreStatus::Fail, ms(30)),
+            (CoreStatus::Pass, ms(20)),
+            (CoreStatus::Pass, ms(40)),
+        ];
+        let test_case = CoreTestCase {
+            name: "Foo1".to_string(),
+            status: CoreStatus::Fail,
+            failures: vec![CoreFailure::default()],
+            repeat: Some(RepeatStats::new(&runs)),
+            ..Default::default()
+        };
+        report.push_test_case("Foo", test_case);
+
+        let mut s = String::new();
+        let res = TestResult::Fail(Reason::Report(report));
+        let res = res.to_tr_wrapper();
+        write!(s, "{}", res).unwrap();
+        insta::assert_debug_snapshot!(s);
+    }
 }
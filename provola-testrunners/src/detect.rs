@@ -0,0 +1,178 @@
+use crate::TestRunnerType;
+use provola_core::test::framework::{detect_framework, Framework};
+use std::path::Path;
+
+/// Files which identify the root of a project, or a build directory
+const PROJECT_FILES: &[(TestRunnerType, &str)] = &[
+    #[cfg(feature = "ctest")]
+    (TestRunnerType::CTest, "CTestTestfile.cmake"),
+    #[cfg(feature = "gotest")]
+    (TestRunnerType::GoTest, "go.mod"),
+    #[cfg(feature = "pytest")]
+    (TestRunnerType::Pytest, "pytest.ini"),
+    #[cfg(feature = "pytest")]
+    (TestRunnerType::Pytest, "conftest.py"),
+    #[cfg(feature = "pytest")]
+    (TestRunnerType::Pytest, "tox.ini"),
+];
+
+/// Extensions of files which can be passed directly to a test runner
+const EXTENSIONS: &[(TestRunnerType, &str)] = &[
+    #[cfg(feature = "pytest")]
+    (TestRunnerType::Pytest, "py"),
+    #[cfg(feature = "gotest")]
+    (TestRunnerType::GoTest, "go"),
+    #[cfg(feature = "dotnet")]
+    (TestRunnerType::Dotnet, "sln"),
+    #[cfg(feature = "dotnet")]
+    (TestRunnerType::Dotnet, "csproj"),
+    #[cfg(feature = "dotnet")]
+    (TestRunnerType::Dotnet, "fsproj"),
+    #[cfg(feature = "dotnet")]
+    (TestRunnerType::Dotnet, "vbproj"),
+    #[cfg(feature = "dotnet")]
+    (TestRunnerType::Dotnet, "dll"),
+];
+
+fn detect_from_extension(path: &Path) -> Option<TestRunnerType> {
+    let extension = path.extension()?.to_str()?;
+
+    EXTENSIONS
+        .iter()
+        .find(|(_, x)| *x == extension)
+        .map(|(trt, _)| *trt)
+}
+
+fn detect_from_markers(path: &Path) -> Option<TestRunnerType> {
+    match detect_framework(path)? {
+        #[cfg(feature = "googletest")]
+        Framework::GoogleTest => Some(TestRunnerType::GoogleTest),
+        #[cfg(feature = "doctest")]
+        Framework::Doctest => Some(TestRunnerType::Doctest),
+        #[cfg(feature = "catch2")]
+        Framework::Catch2 => Some(TestRunnerType::Catch2),
+        #[cfg(feature = "boosttest")]
+        Framework::BoostTest => Some(TestRunnerType::BoostTest),
+        #[cfg(feature = "cpputest")]
+        Framework::CppUTest => Some(TestRunnerType::CppUTest),
+        #[cfg(feature = "cmocka")]
+        Framework::CMocka => Some(TestRunnerType::CMocka),
+        #[cfg(feature = "unity")]
+        Framework::Unity => Some(TestRunnerType::Unity),
+        #[allow(unreachable_patterns)]
+        _ => None,
+    }
+}
+
+fn detect_from_directory(path: &Path) -> Option<TestRunnerType> {
+    let found = PROJECT_FILES
+        .iter()
+        .find(|(_, file_name)| path.join(file_name).is_file())
+        .map(|(trt, _)| *trt);
+
+    if found.is_some() {
+        return found;
+    }
+
+    // A directory with a project or a solution, or with python test files
+    std::fs::read_dir(path)
+        .ok()?
+        .filter_map(|x| x.ok())
+        .map(|x| x.path())
+        .filter(|x| x.is_file())
+        .find_map(|x| {
+            let trt = detect_from_extension(&x)?;
+            let is_test_file = |x: &Path| {
+                let stem = x.file_stem().and_then(|x| x.to_str()).unwrap_or_default();
+                stem.starts_with("test_") || stem.ends_with("_test")
+            };
+
+            match trt {
+                #[cfg(feature = "dotnet")]
+                TestRunnerType::Dotnet if x.extension().is_some_and(|x| x != "dll") => Some(trt),
+                #[cfg(feature = "pytest")]
+                TestRunnerType::Pytest if is_test_file(&x) => Some(trt),
+                _ => None,
+            }
+        })
+}
+
+/// Detect the test runner type from the path given as test runner, which
+/// may be an executable, a test file or a directory. Executables are not
+/// run: they are scanned for strings which only a test framework embeds.
+/// Returns `None` if the test runner type cannot be detected, e.g. for
+/// commands in `PATH`.
+pub fn detect_test_runner_type(path: &Path) -> Option<TestRunnerType> {
+    // Go packages pattern, like `./...`
+    #[cfg(feature = "gotest")]
+    if path.ends_with("...") {
+        return Some(TestRunnerType::GoTest);
+    }
+
+    if path.is_dir() {
+        detect_from_directory(path)
+    } else if path.is_file() {
+        detect_from_extension(path).or_else(|| detect_from_markers(path))
+    } else {
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::path::PathBuf;
+
+    fn write_temp_file(name: &str, data: &[u8]) -> PathBuf {
+        let path = std::env::temp_dir().join(name);
+        std::fs::write(&path, data).unwrap();
+        path
+    }
+
+    #[test]
+    fn detect_from_executable_strings() {
+        let path = write_temp_file("provola_detect_gtest", b"\x7fELF\0--gtest_list_tests\0");
+        let trt = detect_test_runner_type(&path);
+        std::fs::remove_file(&path).unwrap();
+        assert_eq!(trt, Some(TestRunnerType::GoogleTest));
+
+        let path = write_temp_file("provola_detect_catch2", b"\x7fELF\0--list-reporters\0");
+        let trt = detect_test_runner_type(&path);
+        std::fs::remove_file(&path).unwrap();
+        assert_eq!(trt, Some(TestRunnerType::Catch2));
+    }
+
+    #[test]
+    fn detect_unknown_executable() {
+        let path = write_temp_file("provola_detect_unknown", b"\x7fELF\0--help\0");
+        let trt = detect_test_runner_type(&path);
+        std::fs::remove_file(&path).unwrap();
+        assert_eq!(trt, None);
+    }
+
+    #[test]
+    fn detect_from_project_directory() {
+        let trt = detect_test_runner_type(Path::new("../provola-gotest/examples/data"));
+        assert_eq!(trt, Some(TestRunnerType::GoTest));
+        let trt = detect_test_runner_type(Path::new("../provola-dotnet/examples/data"));
+        assert_eq!(trt, Some(TestRunnerType::Dotnet));
+    }
+
+    #[test]
+    fn detect_from_file_extension() {
+        let trt =
+            detect_test_runner_type(Path::new("../provola-dotnet/examples/data/Example.csproj"));
+        assert_eq!(trt, Some(TestRunnerType::Dotnet));
+    }
+
+    #[test]
+    fn detect_go_packages_pattern() {
+        let trt = detect_test_runner_type(Path::new("./..."));
+        assert_eq!(trt, Some(TestRunnerType::GoTest));
+    }
+
+    #[test]
+    fn detect_command_in_path() {
+        assert_eq!(detect_test_runner_type(Path::new("npx")), None);
+    }
+}
@@ -1,7 +1,14 @@
-use provola_core::test_runners::TestRunner;
+use provola_core::test_runners::{
+    BuildCommand, BuildTestRunner, CoverageOpt, CoverageTestRunner, TestRunner,
+};
 use provola_core::{Error, Executable};
+use std::path::PathBuf;
 use strum_macros::{Display, EnumString};
 
+mod detect;
+
+pub use detect::detect_test_runner_type;
+
 fn from_exec<T>(info: TestRunnerInfo) -> Result<Box<dyn TestRunner>, Error>
 where
     T: From<Executable> + provola_core::test_runners::TestRunner + 'static,
@@ -9,16 +16,90 @@ where
     Ok(Box::new(T::from(info.exec)))
 }
 
-pub fn make_test_runner(info: TestRunnerInfo) -> Result<Box<dyn TestRunner>, Error> {
+pub fn make_test_runner(mut info: TestRunnerInfo) -> Result<Box<dyn TestRunner>, Error> {
+    if info.coverage.is_some() && !info.trt.runs_native_executable() {
+        return Err(Error::GenericError(format!(
+            "Code coverage is not available with {}",
+            info.trt
+        )));
+    }
+
+    // The test runner is made again after each build
+    if let Some(build) = info.build.take() {
+        if let Some(coverage) = &info.coverage {
+            coverage.tool.check_build(&build);
+        }
+
+        let env = info.coverage.as_ref().map(|x| x.tool.build_env());
+        let make = move || make_test_runner(info.clone());
+        let tr = BuildTestRunner::new(build, make).with_env(env.unwrap_or_default());
+        return Ok(Box::new(tr));
+    }
+
+    // Errors found by valgrind are read from the XML it writes
+    #[cfg(feature = "valgrind")]
+    if let Some((exec, xml_dir)) = provola_valgrind::prepare(&info.exec)? {
+        info.exec = exec;
+        let inner = make_test_runner(info)?;
+        return Ok(Box::new(provola_valgrind::TestRunner::new(inner, xml_dir)));
+    }
+
+    // After valgrind, which must be the first command of the wrapper
+    if let Some(coverage) = info.coverage.take() {
+        let exec = info.exec.clone();
+        let make = |exec| {
+            info.exec = exec;
+            make_test_runner(info)
+        };
+        return Ok(Box::new(CoverageTestRunner::new(coverage, exec, make)?));
+    }
+
     let test_runner_type = info.trt;
     match test_runner_type {
         #[cfg(feature = "googletest")]
-        TestRunnerType::GoogleTest => from_exec::<provola_googletest::TestRunner>(info),
+        TestRunnerType::GoogleTest => make_googletest_test_runner(info),
         #[cfg(feature = "catch2")]
         TestRunnerType::Catch2 => from_exec::<provola_catch2::TestRunner>(info),
+        #[cfg(feature = "pytest")]
+        TestRunnerType::Pytest => from_exec::<provola_pytest::TestRunner>(info),
+        #[cfg(feature = "gotest")]
+        TestRunnerType::GoTest => from_exec::<provola_gotest::TestRunner>(info),
+        #[cfg(feature = "junit")]
+        TestRunnerType::JUnit => make_junit_test_runner(info),
+        #[cfg(feature = "tap")]
+        TestRunnerType::Tap => from_exec::<provola_tap::TestRunner>(info),
+        #[cfg(feature = "boosttest")]
+        TestRunnerType::BoostTest => from_exec::<provola_boosttest::TestRunner>(info),
+        #[cfg(feature = "doctest")]
+        TestRunnerType::Doctest => from_exec::<provola_doctest::TestRunner>(info),
+        #[cfg(feature = "unity")]
+        TestRunnerType::Unity => from_exec::<provola_unity::TestRunner>(info),
+        #[cfg(feature = "cmocka")]
+        TestRunnerType::CMocka => from_exec::<provola_cmocka::TestRunner>(info),
+        #[cfg(feature = "cpputest")]
+        TestRunnerType::CppUTest => from_exec::<provola_cpputest::TestRunner>(info),
+        #[cfg(feature = "dotnet")]
+        TestRunnerType::Dotnet => from_exec::<provola_dotnet::TestRunner>(info),
+        #[cfg(feature = "ctest")]
+        TestRunnerType::CTest => from_exec::<provola_ctest::TestRunner>(info),
     }
 }
 
+#[cfg(feature = "googletest")]
+fn make_googletest_test_runner(info: TestRunnerInfo) -> Result<Box<dyn TestRunner>, Error> {
+    let tr = provola_googletest::TestRunner::new(info.exec, info.shards)?;
+    Ok(Box::new(tr))
+}
+
+#[cfg(feature = "junit")]
+fn make_junit_test_runner(info: TestRunnerInfo) -> Result<Box<dyn TestRunner>, Error> {
+    let report = info
+        .report
+        .ok_or_else(|| Error::GenericError("JUnit report path is missing".to_string()))?;
+    let tr = provola_junit::TestRunner::new(info.exec, report, info.filter);
+    Ok(Box::new(tr))
+}
+
 #[derive(
     Debug, EnumString, Clone, Copy, Display, serde::Deserialize, serde::Serialize, PartialEq, Eq,
 )]
@@ -27,16 +108,120 @@ pub enum TestRunnerType {
     GoogleTest,
     #[cfg(feature = "catch2")]
     Catch2,
+    #[cfg(feature = "pytest")]
+    Pytest,
+    #[cfg(feature = "gotest")]
+    GoTest,
+    #[cfg(feature = "junit")]
+    JUnit,
+    #[cfg(feature = "tap")]
+    Tap,
+    #[cfg(feature = "boosttest")]
+    BoostTest,
+    #[cfg(feature = "doctest")]
+    Doctest,
+    #[cfg(feature = "unity")]
+    Unity,
+    #[cfg(feature = "cmocka")]
+    CMocka,
+    #[cfg(feature = "cpputest")]
+    CppUTest,
+    #[cfg(feature = "dotnet")]
+    Dotnet,
+    #[cfg(feature = "ctest")]
+    CTest,
+}
+
+impl TestRunnerType {
+    /// Check if the test runner is a native executable, which can be
+    /// instrumented to collect code coverage
+    pub fn runs_native_executable(&self) -> bool {
+        match self {
+            #[cfg(feature = "googletest")]
+            TestRunnerType::GoogleTest => true,
+            #[cfg(feature = "catch2")]
+            TestRunnerType::Catch2 => true,
+            #[cfg(feature = "boosttest")]
+            TestRunnerType::BoostTest => true,
+            #[cfg(feature = "doctest")]
+            TestRunnerType::Doctest => true,
+            #[cfg(feature = "unity")]
+            TestRunnerType::Unity => true,
+            #[cfg(feature = "cmocka")]
+            TestRunnerType::CMocka => true,
+            #[cfg(feature = "cpputest")]
+            TestRunnerType::CppUTest => true,
+            #[allow(unreachable_patterns)]
+            _ => false,
+        }
+    }
 }
 
 #[derive(serde::Deserialize, serde::Serialize, Clone, Debug, PartialEq, Eq)]
 pub struct TestRunnerInfo {
     pub exec: Executable,
     pub trt: TestRunnerType,
+    /// Path (or glob pattern) of the report generated by the test runner,
+    /// for test runners which cannot choose where to write it
+    #[serde(default)]
+    pub report: Option<String>,
+    /// Template of the arguments needed to run a single test
+    #[serde(default)]
+    pub filter: Option<String>,
+    /// Maximum number of processes running tests in parallel, for test
+    /// runners supporting it. If `None`, as many as the available CPUs.
+    #[serde(default)]
+    pub shards: Option<usize>,
+    /// How the test executable is built, before running tests
+    #[serde(default)]
+    pub build: Option<BuildCommand>,
+    /// How code coverage is collected, if it is
+    #[serde(default)]
+    pub coverage: Option<CoverageOpt>,
 }
 
 impl TestRunnerInfo {
     pub fn new(exec: Executable, trt: TestRunnerType) -> Self {
-        Self { exec, trt }
+        Self {
+            exec,
+            trt,
+            report: None,
+            filter: None,
+            shards: None,
+            build: None,
+            coverage: None,
+        }
+    }
+
+    /// Files written by the test runner, outside of temporary directories.
+    /// They can be glob patterns.
+    pub fn written_paths(&self) -> Vec<PathBuf> {
+        match self.trt {
+            #[cfg(feature = "cpputest")]
+            TestRunnerType::CppUTest => vec![PathBuf::from(provola_cpputest::REPORT_FILES)],
+            #[cfg(feature = "junit")]
+            TestRunnerType::JUnit => self.report.iter().map(PathBuf::from).collect(),
+            #[allow(unreachable_patterns)]
+            _ => vec![],
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use provola_core::test_runners::CoverageTool;
+    use std::path::PathBuf;
+
+    #[cfg(feature = "pytest")]
+    #[test]
+    fn coverage_of_native_executables_only() {
+        let exec = Executable::from(PathBuf::from("tests/"));
+        let mut info = TestRunnerInfo::new(exec, TestRunnerType::Pytest);
+        info.coverage = Some(CoverageOpt {
+            tool: CoverageTool::Llvm,
+            lcov: None,
+        });
+        assert!(make_test_runner(info).is_err());
     }
 }
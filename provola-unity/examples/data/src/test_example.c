@@ -0,0 +1,29 @@
+#include "unity.h"
+
+static int add(int a, int b) {
+  return a + b;
+}
+
+void setUp(void) {}
+
+void tearDown(void) {}
+
+void test_add(void) {
+  TEST_ASSERT_EQUAL_INT(3, add(1, 2));
+}
+
+void test_add_negative(void) {
+  TEST_ASSERT_EQUAL_INT(-1, add(-1, -1));
+}
+
+void test_not_implemented(void) {
+  TEST_IGNORE_MESSAGE("Not implemented");
+}
+
+int main(void) {
+  UNITY_BEGIN();
+  RUN_TEST(test_add);
+  RUN_TEST(test_add_negative);
+  RUN_TEST(test_not_implemented);
+  return UNITY_END();
+}
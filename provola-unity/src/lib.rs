@@ -0,0 +1,105 @@
+use provola_core::test_runners::{Selection, TestRunnerOpt};
+use provola_core::{run_process, AvailableTests, CoreReport, Error, Executable, ProcessOpt};
+
+mod report;
+
+fn make_available_tests(report: &CoreReport) -> AvailableTests {
+    let mut tests = AvailableTests::default();
+
+    for test_suite in report.testsuites.iter() {
+        for test_case in test_suite.testcases.iter() {
+            tests.push(&test_suite.name, &test_case.name);
+        }
+    }
+
+    tests
+}
+
+/// Run an executable built with Unity
+pub struct TestRunner {
+    executable: Executable,
+}
+
+impl TestRunner {
+    fn generate_report(&self) -> Result<CoreReport, Error> {
+        let out = run_process(&Vec::from(&self.executable), &ProcessOpt::default())?.out;
+        Ok(report::parse_report(&out))
+    }
+
+    fn generate_selected_report(&self, opt: &TestRunnerOpt) -> Result<CoreReport, Error> {
+        let mut report = self.generate_report()?;
+
+        let selection = match opt.only.selection() {
+            Some(selection) => selection,
+            None => return Ok(report),
+        };
+
+        let tests = make_available_tests(&report);
+        let ids: Vec<_> = selection.filter(&tests).iter().map(|x| x.id).collect();
+
+        if ids.is_empty() {
+            return Err(Error::NothingToDo);
+        }
+
+        // Unity can filter tests only when built with command line
+        // arguments support, so all tests are executed and only the selected
+        // one is kept in the report.
+        report.retain_selection(&Selection::from_ids(ids));
+
+        if report.testsuites.is_empty() {
+            return Err(Error::NothingToDo);
+        }
+
+        Ok(report)
+    }
+}
+
+impl From<Executable> for TestRunner {
+    fn from(executable: Executable) -> Self {
+        TestRunner { executable }
+    }
+}
+
+impl provola_core::test_runners::TestRunner for TestRunner {
+    fn run(&self, opt: &TestRunnerOpt) -> Result<provola_core::TestResult, provola_core::Error> {
+        let report = self.generate_selected_report(opt)?;
+        let result = report.into();
+        Ok(result)
+    }
+
+    fn list(&self, _opt: &TestRunnerOpt) -> Result<AvailableTests, Error> {
+        // Tests are known only after running them
+        let report = self.generate_report()?;
+        Ok(make_available_tests(&report))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::path::PathBuf;
+
+    fn make_exec() -> Executable {
+        let path = PathBuf::from("./examples/data/build/example");
+        Executable::from(path)
+    }
+
+    #[test]
+    fn list_tests_from_report() {
+        let s = std::fs::read_to_string("examples/data/test_output.txt").unwrap();
+        let list = make_available_tests(&report::parse_report(&s));
+        assert_eq!(list.len(), 3);
+        assert_eq!(list.get(1).unwrap().test_suite.0, "test_example");
+        assert_eq!(list.get(1).unwrap().test_case.0, "test_add_negative");
+    }
+
+    // Ignored because example must be built first
+    #[ignore]
+    #[test]
+    fn from_valid_executable() {
+        let tr = TestRunner::from(make_exec());
+        let tr: Box<dyn provola_core::test_runners::TestRunner> = Box::new(tr);
+        let tr_opt = TestRunnerOpt::default();
+        assert!(tr.run(&tr_opt).is_ok());
+    }
+}
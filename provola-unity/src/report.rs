@@ -0,0 +1,176 @@
+use provola_core::report::{CoreStatus, SourceLocation};
+use provola_core::test::xunit::FullyQualifiedTestCase;
+use provola_core::{CoreFailure, CoreReport, CoreTestCase, CoreTestSuite};
+use std::path::Path;
+use std::time::Duration;
+
+/// A line printed for each test, like
+/// `test/test_foo.c:12:test_foo:FAIL: Expected 1 Was 2`
+#[derive(Debug, PartialEq, Eq)]
+struct TestLine<'a> {
+    location: SourceLocation,
+    name: &'a str,
+    status: &'a str,
+    message: &'a str,
+    time: Option<Duration>,
+}
+
+/// With `UNITY_INCLUDE_EXEC_TIME`, the time is printed at the end of the
+/// line, like ` (12 ms)`
+fn split_time(s: &str) -> (&str, Option<Duration>) {
+    let parsed = s.strip_suffix(" ms)").and_then(|x| {
+        let index = x.rfind(" (")?;
+        let ms = x[index + 2..].parse().ok()?;
+        Some((&x[..index], Duration::from_millis(ms)))
+    });
+
+    match parsed {
+        Some((s, time)) => (s, Some(time)),
+        None => (s, None),
+    }
+}
+
+fn parse_test_line(line: &str) -> Option<TestLine<'_>> {
+    let (location, rest) = SourceLocation::split_prefix(line.trim_end())?;
+    let (rest, time) = split_time(rest);
+    let mut fields = rest.splitn(3, ':');
+    let name = fields.next()?;
+    let status = fields.next()?;
+    let message = fields.next().unwrap_or_default().trim();
+
+    if !matches!(status, "PASS" | "FAIL" | "IGNORE") {
+        return None;
+    }
+
+    Some(TestLine {
+        location,
+        name,
+        status,
+        message,
+        time,
+    })
+}
+
+/// Unity has no test suites, so each source file is a test suite
+fn suite_name(location: &SourceLocation) -> String {
+    Path::new(&location.file)
+        .file_stem()
+        .map(|x| x.to_string_lossy().to_string())
+        .unwrap_or_else(|| location.file.clone())
+}
+
+fn make_test_case(test_suite: &str, x: TestLine) -> CoreTestCase {
+    let fqtc = FullyQualifiedTestCase::from_test_suite_test_case(test_suite, x.name);
+
+    let (status, failures) = match x.status {
+        "PASS" => (CoreStatus::Pass, vec![]),
+        "IGNORE" => (CoreStatus::Skipped, vec![]),
+        _ => {
+            let failure = CoreFailure {
+                ttype: "failure".to_string(),
+                message: x.message.to_string(),
+                location: Some(x.location),
+            };
+            (CoreStatus::Fail, vec![failure])
+        }
+    };
+
+    CoreTestCase {
+        fqtc: Some(fqtc.id),
+        classname: Some(test_suite.to_string()),
+        name: x.name.to_string(),
+        status,
+        time: x.time,
+        failures,
+        system_out: None,
+        system_err: None,
+        repeat: None,
+        location: None,
+    }
+}
+
+/// Parse the output of a Unity test executable. Lines which are not test
+/// results (e.g. printed by the code under test) are ignored.
+pub fn parse_report(s: &str) -> CoreReport {
+    let mut testsuites: Vec<CoreTestSuite> = Vec::new();
+
+    for line in s.lines().filter_map(parse_test_line) {
+        let name = suite_name(&line.location);
+        let test_case = make_test_case(&name, line);
+
+        match testsuites.iter_mut().find(|x| x.name == name) {
+            Some(test_suite) => test_suite.testcases.push(test_case),
+            None => testsuites.push(CoreTestSuite {
+                name,
+                testcases: vec![test_case],
+                ..Default::default()
+            }),
+        }
+    }
+
+    for test_suite in testsuites.iter_mut() {
+        let count = |status: CoreStatus| {
+            let d = std::mem::discriminant(&status);
+            test_suite
+                .testcases
+                .iter()
+                .filter(|x| std::mem::discriminant(&x.status) == d)
+                .count()
+        };
+
+        test_suite.failures = Some(count(CoreStatus::Fail));
+        test_suite.skipped = Some(count(CoreStatus::Skipped));
+        test_suite.tests = test_suite.testcases.len();
+        test_suite.time = test_suite
+            .testcases
+            .iter()
+            .filter_map(|x| x.time)
+            .reduce(|x, y| x + y);
+    }
+
+    CoreReport {
+        failures: Some(testsuites.iter().filter_map(|x| x.failures).sum()),
+        tests: Some(testsuites.iter().map(|x| x.tests).sum()),
+        time: testsuites
+            .iter()
+            .filter_map(|x| x.time)
+            .reduce(|x, y| x + y),
+        testsuites,
+        ..Default::default()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn convert_test_output() {
+        let s = std::fs::read_to_string("examples/data/test_output.txt").unwrap();
+        let report = parse_report(&s);
+        insta::assert_debug_snapshot!(&report);
+    }
+
+    #[test]
+    fn parse_failed_test_line() {
+        let line = parse_test_line("test/foo.c:12:test_foo:FAIL: Expected 1 Was 2").unwrap();
+        assert_eq!(line.location.file, "test/foo.c");
+        assert_eq!(line.location.line, Some(12));
+        assert_eq!(line.name, "test_foo");
+        assert_eq!(line.status, "FAIL");
+        assert_eq!(line.message, "Expected 1 Was 2");
+    }
+
+    #[test]
+    fn parse_test_line_with_time() {
+        let line = parse_test_line("test/foo.c:12:test_foo:PASS (15 ms)").unwrap();
+        assert_eq!(line.status, "PASS");
+        assert_eq!(line.time, Some(Duration::from_millis(15)));
+    }
+
+    #[test]
+    fn ignore_other_lines() {
+        assert!(parse_test_line("foo.c:12: some output").is_none());
+        assert!(parse_test_line("3 Tests 1 Failures 1 Ignored").is_none());
+    }
+}
@@ -0,0 +1,173 @@
+use provola_core::test::xunit::AvailableTests;
+use provola_core::test_runners::{Repeat, TestRunnerOpt};
+use provola_core::{Error, Executable, TempReport, TestEvent, TestResult};
+use std::path::Path;
+
+mod report;
+
+pub use report::VALGRIND;
+
+type BoxedTestRunner = Box<dyn provola_core::test_runners::TestRunner>;
+
+/// Options making valgrind write XML somewhere else, not read by provola
+const XML_DESTINATIONS: [&str; 3] = ["--xml-file", "--xml-fd", "--xml-socket"];
+
+fn is_valgrind(wrapper: &[String]) -> bool {
+    let program = wrapper.first().and_then(|x| Path::new(x).file_name());
+    program.is_some_and(|x| x == "valgrind")
+}
+
+/// When the wrapper of `exec` is valgrind, the same executable writing
+/// errors as XML to a temporary directory, one file for each process (`%p`
+/// is replaced by valgrind with its pid).
+pub fn prepare(exec: &Executable) -> Result<Option<(Executable, TempReport)>, Error> {
+    let wrapper = exec.wrapper();
+    let has_destination = wrapper
+        .iter()
+        .any(|x| XML_DESTINATIONS.iter().any(|d| x.starts_with(d)));
+
+    if !is_valgrind(wrapper) || has_destination {
+        return Ok(None);
+    }
+
+    let dir = TempReport::dir("valgrind")?;
+    let mut wrapper: Vec<String> = wrapper
+        .iter()
+        .filter(|x| !x.starts_with("--xml="))
+        .cloned()
+        .collect();
+    wrapper.push("--xml=yes".to_string());
+    wrapper.push(format!("--xml-file={}", dir.join("%p.xml").display()));
+
+    let exec = exec.unwrapped().clone().wrap(wrapper);
+    Ok(Some((exec, dir)))
+}
+
+/// Run a test runner whose executable is wrapped by valgrind (see
+/// `prepare`). Errors found by valgrind fail the test cases they happened in.
+pub struct TestRunner {
+    inner: BoxedTestRunner,
+    xml_dir: TempReport,
+}
+
+impl TestRunner {
+    pub fn new(inner: BoxedTestRunner, xml_dir: TempReport) -> Self {
+        Self { inner, xml_dir }
+    }
+
+    /// Paths of the XML files written by valgrind
+    fn xml_files(&self) -> Vec<std::path::PathBuf> {
+        std::fs::read_dir(&*self.xml_dir)
+            .map(|entries| entries.filter_map(|x| x.ok()).map(|x| x.path()).collect())
+            .unwrap_or_default()
+    }
+
+    /// Remove files written by previous runs, or by listing tests
+    fn clear(&self) {
+        for path in self.xml_files() {
+            let _ = std::fs::remove_file(path);
+        }
+    }
+
+    fn errors(&self) -> Vec<report::ValgrindError> {
+        let mut errors = Vec::new();
+
+        for path in self.xml_files() {
+            // A process killed by a signal leaves its XML unfinished
+            let output = std::fs::read_to_string(&path)
+                .map_err(Error::from)
+                .and_then(|x| report::parse(&x));
+
+            match output {
+                Ok(x) => errors.extend(x),
+                Err(e) => log::warn!("Cannot read {}: {}", path.display(), e),
+            }
+        }
+
+        self.clear();
+        errors
+    }
+
+    fn add_errors(&self, result: TestResult) -> TestResult {
+        let errors = self.errors();
+
+        if errors.is_empty() {
+            return result;
+        }
+
+        let mut report = result.into_report("tests");
+        report::add_errors(&mut report, &errors);
+        report.into()
+    }
+}
+
+impl provola_core::test_runners::TestRunner for TestRunner {
+    fn run(&self, opt: &TestRunnerOpt) -> Result<TestResult, Error> {
+        self.clear();
+        let result = self.inner.run(opt)?;
+        Ok(self.add_errors(result))
+    }
+
+    fn run_with_events(
+        &self,
+        opt: &TestRunnerOpt,
+        events: &mut dyn FnMut(TestEvent),
+    ) -> Result<TestResult, Error> {
+        self.clear();
+        let result = self.inner.run_with_events(opt, events)?;
+        Ok(self.add_errors(result))
+    }
+
+    fn run_repeated(
+        &self,
+        opt: &TestRunnerOpt,
+        repeat: &Repeat,
+        events: &mut dyn FnMut(TestEvent),
+    ) -> Result<TestResult, Error> {
+        self.clear();
+        let result = self.inner.run_repeated(opt, repeat, events)?;
+        Ok(self.add_errors(result))
+    }
+
+    fn list(&self, opt: &TestRunnerOpt) -> Result<AvailableTests, Error> {
+        self.inner.list(opt)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::path::PathBuf;
+
+    fn make_exec(wrapper: &str) -> Executable {
+        let wrapper = wrapper.split_whitespace().map(String::from).collect();
+        Executable::from(PathBuf::from("build/tests")).wrap(wrapper)
+    }
+
+    #[test]
+    fn valgrind_writes_xml_to_temp_dir() {
+        let (exec, dir) = prepare(&make_exec("valgrind --xml=no --leak-check=full"))
+            .unwrap()
+            .unwrap();
+        let argv = Vec::from(&exec);
+        let xml_file = format!("--xml-file={}", dir.join("%p.xml").display());
+        assert_eq!(
+            argv,
+            vec![
+                "valgrind",
+                "--leak-check=full",
+                "--xml=yes",
+                &xml_file,
+                "build/tests"
+            ]
+        );
+    }
+
+    #[test]
+    fn other_wrappers_are_not_changed() {
+        assert!(prepare(&make_exec("taskset -c 2")).unwrap().is_none());
+        assert!(prepare(&make_exec("")).unwrap().is_none());
+        let exec = make_exec("/usr/bin/valgrind --xml=yes --xml-file=leaks.xml");
+        assert!(prepare(&exec).unwrap().is_none());
+    }
+}
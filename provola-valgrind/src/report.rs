@@ -0,0 +1,306 @@
+//! Parser for the XML written by valgrind (`--xml=yes`). Its errors, like
+//! leaks and invalid reads, are added to the report as failures of the test
+//! case they happened in.
+
+use provola_core::report::{CoreStatus, SourceLocation};
+use provola_core::test::xunit::FullyQualifiedTestCase;
+use provola_core::{CoreFailure, CoreReport, CoreTestCase, Error};
+use xml::reader::{EventReader, XmlEvent};
+
+/// Name of the test case failed by errors which happened outside of tests
+pub const VALGRIND: &str = "[valgrind]";
+
+/// An `<error>` element
+#[derive(Debug, Default)]
+pub struct ValgrindError {
+    /// Like `InvalidRead` or `Leak_DefinitelyLost`
+    pub kind: String,
+    /// From `<what>`, or from `<xwhat><text>` (e.g. for leaks)
+    pub what: String,
+    /// Frames of the first stack, where the error happened. The other
+    /// stacks (where the memory involved was allocated or freed) are
+    /// ignored.
+    pub frames: Vec<Frame>,
+}
+
+#[derive(Debug, Default)]
+pub struct Frame {
+    pub function: Option<String>,
+    pub dir: Option<String>,
+    pub file: Option<String>,
+    pub line: Option<usize>,
+}
+
+fn parse_error(e: xml::reader::Error) -> Error {
+    Error::ReportParseError(Box::new(e))
+}
+
+pub fn parse(s: &str) -> Result<Vec<ValgrindError>, Error> {
+    let mut errors = Vec::new();
+
+    let mut error: Option<ValgrindError> = None;
+    let mut stacks = 0;
+    let mut frame: Option<Frame> = None;
+    let mut text: Option<String> = None;
+
+    for event in EventReader::from_str(s) {
+        match event.map_err(parse_error)? {
+            XmlEvent::StartElement { name, .. } => match name.local_name.as_str() {
+                "error" => {
+                    error = Some(ValgrindError::default());
+                    stacks = 0;
+                }
+                "stack" => stacks += 1,
+                "frame" if error.is_some() && stacks == 1 => {
+                    frame = Some(Frame::default());
+                }
+                "kind" | "what" | "text" if error.is_some() => {
+                    text = Some(String::default());
+                }
+                "fn" | "dir" | "file" | "line" if frame.is_some() => {
+                    text = Some(String::default());
+                }
+                _ => {}
+            },
+            XmlEvent::Characters(s) | XmlEvent::CData(s) => {
+                if let Some(text) = &mut text {
+                    text.push_str(&s);
+                }
+            }
+            XmlEvent::EndElement { name } => {
+                let name = name.local_name.as_str();
+                let s = text.take().map(|x| x.trim().to_string());
+
+                match (name, &mut error, &mut frame) {
+                    ("kind", Some(x), _) => x.kind = s.unwrap_or_default(),
+                    // The first one, not the `<xauxwhat><text>` following it
+                    ("what" | "text", Some(x), _) if x.what.is_empty() => {
+                        x.what = s.unwrap_or_default()
+                    }
+                    ("fn", _, Some(x)) => x.function = s,
+                    ("dir", _, Some(x)) => x.dir = s,
+                    ("file", _, Some(x)) => x.file = s,
+                    ("line", _, Some(x)) => x.line = s.and_then(|x| x.parse().ok()),
+                    ("frame", Some(x), Some(_)) => x.frames.extend(frame.take()),
+                    ("error", Some(_), _) => errors.extend(error.take()),
+                    _ => {}
+                }
+            }
+            _ => {}
+        }
+    }
+
+    Ok(errors)
+}
+
+impl Frame {
+    fn location(&self) -> Option<SourceLocation> {
+        let file = self.file.as_ref()?;
+        let file = match &self.dir {
+            Some(dir) => format!("{}/{}", dir, file),
+            None => file.clone(),
+        };
+
+        Some(SourceLocation {
+            file,
+            line: self.line,
+        })
+    }
+}
+
+impl ValgrindError {
+    /// The innermost frame in the sources, skipping the ones of libraries
+    /// without debug information (like `malloc`)
+    fn location(&self) -> Option<SourceLocation> {
+        self.frames.iter().find_map(|x| x.location())
+    }
+
+    fn to_failure(&self) -> CoreFailure {
+        CoreFailure {
+            ttype: self.kind.clone(),
+            message: self.what.clone(),
+            location: self.location(),
+        }
+    }
+}
+
+/// Function running a test case: `Suite_Case_Test::TestBody()` with
+/// GoogleTest, a function with the same name of the test case with most C
+/// frameworks (e.g. Unity and CMocka)
+fn is_test_function(function: &str, test_suite: &str, test_case: &str) -> bool {
+    let name = function.split('(').next().unwrap_or(function);
+
+    if name.contains(&format!("{}_{}_Test::", test_suite, test_case)) {
+        return true;
+    }
+
+    name.rsplit("::").next() == Some(test_case)
+}
+
+/// Test suite and test case indexes of the innermost test function in the
+/// stack of the error
+fn find_test_case(report: &CoreReport, error: &ValgrindError) -> Option<(usize, usize)> {
+    error.frames.iter().find_map(|frame| {
+        let function = frame.function.as_deref()?;
+
+        report
+            .testsuites
+            .iter()
+            .enumerate()
+            .find_map(|(suite_index, test_suite)| {
+                let case_index = test_suite
+                    .testcases
+                    .iter()
+                    .position(|x| is_test_function(function, &test_suite.name, &x.name))?;
+                Some((suite_index, case_index))
+            })
+    })
+}
+
+/// Add errors to the report, as failures of the test case they happened
+/// in. The others, like leaks of memory allocated outside of tests, fail a
+/// `[valgrind]` test case.
+pub fn add_errors(report: &mut CoreReport, errors: &[ValgrindError]) {
+    let mut unknown = Vec::new();
+
+    for error in errors {
+        match find_test_case(report, error) {
+            Some((suite_index, case_index)) => {
+                let test_case = &mut report.testsuites[suite_index].testcases[case_index];
+                test_case.status = CoreStatus::Fail;
+                test_case.failures.push(error.to_failure());
+            }
+            None => unknown.push(error.to_failure()),
+        }
+    }
+
+    report.update_counts();
+
+    if !unknown.is_empty() {
+        let fqtc = FullyQualifiedTestCase::from_test_suite_test_case("valgrind", VALGRIND);
+        let test_case = CoreTestCase {
+            fqtc: Some(fqtc.id),
+            name: VALGRIND.to_string(),
+            status: CoreStatus::Fail,
+            failures: unknown,
+            ..Default::default()
+        };
+        report.push_test_case("valgrind", test_case);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const XML: &str = r#"<?xml version="1.0"?>
+<valgrindoutput>
+<protocolversion>4</protocolversion>
+<preamble>
+  <line>Memcheck, a memory error detector</line>
+</preamble>
+<pid>1234</pid>
+<status><state>RUNNING</state><time>00:00:00:00.044 </time></status>
+<error>
+  <unique>0x0</unique>
+  <tid>1</tid>
+  <kind>InvalidRead</kind>
+  <what>Invalid read of size 4</what>
+  <stack>
+    <frame><ip>0x10917A</ip><obj>/build/tests</obj><fn>read_value(int const*)</fn><dir>/src</dir><file>foo.cpp</file><line>4</line></frame>
+    <frame><ip>0x1091B5</ip><obj>/build/tests</obj><fn>Foo_Foo1_Test::TestBody()</fn><dir>/src</dir><file>tests.cpp</file><line>12</line></frame>
+  </stack>
+  <auxwhat>Address 0x4a4f040 is 0 bytes after a block of size 0 alloc'd</auxwhat>
+  <stack>
+    <frame><ip>0x483B7F3</ip><obj>/usr/lib/valgrind/vgpreload_memcheck.so</obj><fn>malloc</fn></frame>
+  </stack>
+</error>
+<status><state>FINISHED</state><time>00:00:00:00.512 </time></status>
+<error>
+  <unique>0x1</unique>
+  <tid>1</tid>
+  <kind>Leak_DefinitelyLost</kind>
+  <xwhat>
+    <text>8 bytes in 1 blocks are definitely lost in loss record 1 of 2</text>
+    <leakedbytes>8</leakedbytes>
+    <leakedblocks>1</leakedblocks>
+  </xwhat>
+  <stack>
+    <frame><ip>0x483B7F3</ip><obj>/usr/lib/valgrind/vgpreload_memcheck.so</obj><fn>malloc</fn></frame>
+    <frame><ip>0x109201</ip><obj>/build/tests</obj><fn>test_bar</fn><dir>/src</dir><file>bar.c</file><line>20</line></frame>
+  </stack>
+</error>
+<error>
+  <unique>0x2</unique>
+  <tid>1</tid>
+  <kind>Leak_PossiblyLost</kind>
+  <xwhat>
+    <text>16 bytes in 1 blocks are possibly lost in loss record 2 of 2</text>
+  </xwhat>
+  <stack>
+    <frame><ip>0x483B7F3</ip><obj>/usr/lib/valgrind/vgpreload_memcheck.so</obj><fn>malloc</fn></frame>
+    <frame><ip>0x109301</ip><obj>/build/tests</obj><fn>main</fn><dir>/src</dir><file>main.c</file><line>3</line></frame>
+  </stack>
+</error>
+<errorcounts>
+  <pair><count>1</count><unique>0x0</unique></pair>
+</errorcounts>
+<suppcounts>
+</suppcounts>
+</valgrindoutput>"#;
+
+    fn make_report() -> CoreReport {
+        let mut report = CoreReport::default();
+
+        for (test_suite, test_case) in [("Foo", "Foo1"), ("Foo", "Foo2"), ("Bar", "test_bar")] {
+            let test_case = CoreTestCase {
+                name: test_case.to_string(),
+                status: CoreStatus::Pass,
+                ..Default::default()
+            };
+            report.push_test_case(test_suite, test_case);
+        }
+
+        report
+    }
+
+    #[test]
+    fn parse_errors() {
+        let errors = parse(XML).unwrap();
+        assert_eq!(errors.len(), 3);
+        assert_eq!(errors[0].frames.len(), 2);
+
+        let failure = errors[0].to_failure();
+        assert_eq!(failure.ttype, "InvalidRead");
+        assert_eq!(failure.message, "Invalid read of size 4");
+        assert_eq!(failure.location.unwrap().to_string(), "/src/foo.cpp:4");
+
+        let failure = errors[1].to_failure();
+        assert_eq!(
+            failure.message,
+            "8 bytes in 1 blocks are definitely lost in loss record 1 of 2"
+        );
+        assert_eq!(failure.location.unwrap().to_string(), "/src/bar.c:20");
+    }
+
+    #[test]
+    fn errors_fail_their_test_cases() {
+        let errors = parse(XML).unwrap();
+        let mut report = make_report();
+        add_errors(&mut report, &errors);
+
+        assert_eq!(report.tests, Some(4));
+        assert_eq!(report.failures, Some(3));
+
+        let foo = &report.testsuites[0];
+        assert_eq!(foo.testcases[0].failures[0].ttype, "InvalidRead");
+        assert!(foo.testcases[1].failures.is_empty());
+
+        let bar = &report.testsuites[1];
+        assert_eq!(bar.testcases[0].failures[0].ttype, "Leak_DefinitelyLost");
+
+        let valgrind = &report.testsuites[2];
+        assert_eq!(valgrind.testcases[0].name, VALGRIND);
+        assert_eq!(valgrind.testcases[0].failures[0].ttype, "Leak_PossiblyLost");
+    }
+}
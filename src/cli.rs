@@ -1,18 +1,31 @@
+use super::session::Session;
 use super::Opt;
 use provola_core::*;
 use std::convert::TryFrom;
-use std::path::Path;
+use std::path::PathBuf;
 use std::time::Duration;
 
-fn run_forever(opt: &Opt, watch_file: &Path) -> Result<(), Error> {
+fn run_forever(opt: &Opt, watch_files: &[PathBuf], ignore: Vec<PathBuf>) -> Result<(), Error> {
     run_once_or_log_error(opt);
 
+    let (first, others) = match watch_files.split_first() {
+        Some(x) => x,
+        None => return Ok(()),
+    };
+
     let watch_opt = WatchOptions {
-        file: watch_file.to_path_buf(),
+        file: first.clone(),
         debounce_time: Duration::from_secs(1),
+        ignore,
     };
 
-    Watcher::try_from(watch_opt)?.watch(&mut || {
+    let mut watcher = Watcher::try_from(watch_opt)?;
+
+    for file in others {
+        watcher.add(file)?;
+    }
+
+    watcher.watch(&mut || {
         run_once_or_log_error(opt);
     })?;
 
@@ -27,9 +40,14 @@ fn run_once_or_log_error(opt: &Opt) {
 
 fn run_once(opt: &Opt) -> Result<(), Error> {
     let action = Action::try_from(opt)?;
-    let result = action.run()?;
     let reporter = opt.reporter()?;
 
+    let result = action.run_with_events(&mut |event| {
+        if let Err(e) = reporter.event(&event) {
+            log::warn!("{}", e);
+        }
+    })?;
+
     reporter.report(result)?;
 
     Ok(())
@@ -52,7 +70,7 @@ fn list_tests(opt: &Opt) -> Result<(), Error> {
         };
 
         println!(
-            "{:width$} {id} {test}",
+            "{index:width$} {id} {test}",
             index = index,
             width = width,
             id = test.id,
@@ -66,8 +84,16 @@ fn list_tests(opt: &Opt) -> Result<(), Error> {
 pub(crate) fn run(opt: &Opt) -> Result<(), Error> {
     if opt.list {
         list_tests(opt)
-    } else if let Some(watch_files) = &opt.watch {
-        run_forever(opt, watch_files)
+    } else if let Some(watch_file) = &opt.watch {
+        run_forever(opt, std::slice::from_ref(watch_file), opt.written_paths())
+    } else if let Some(path) = opt.session.as_ref().filter(|_| !opt.no_watch) {
+        // The session itself is watched too, it is loaded again at every run
+        let session = Session::load(path)?;
+        let mut watch_files = session.watch_paths()?;
+        watch_files.push(path.clone());
+        let mut ignore = session.written_paths()?;
+        ignore.extend(opt.written_paths());
+        run_forever(opt, &watch_files, ignore)
     } else {
         run_once(opt)
     }
@@ -1,14 +1,19 @@
 use clap::{App, IntoApp, Parser};
 use clap_generate::{generate, Generator, Shell};
-use provola_core::test_runners::{Only, TestRunnerOpt};
+use provola_core::test_runners::{
+    BuildCommand, CoverageOpt, CoverageTool, Only, Pattern, Repeat, RerunFailed,
+    RerunFailedTestRunner, Selection, TestRunnerOpt,
+};
 use provola_core::*;
 use provola_reporters::{ReporterType, DEFAULT_REPORTER_STR};
-use provola_testrunners::make_test_runner;
+use provola_testrunners::{detect_test_runner_type, make_test_runner};
 use provola_testrunners::{TestRunnerInfo, TestRunnerType};
 use std::convert::TryFrom;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
 
 mod cli;
+mod session;
 
 #[derive(Debug, Parser)]
 #[clap(name = "provola", about = "provola, the quick tester")]
@@ -41,9 +46,9 @@ struct Opt {
     #[clap(short, long, conflicts_with = "test-runner")]
     source: Option<PathBuf>,
     /// Execute a test runner
-    #[clap(short = 't', requires_all = &["test-runner-type"])]
+    #[clap(short = 't')]
     test_runner: Option<PathBuf>,
-    /// Select test runner type
+    /// Select test runner type. If not provided, it is automatically detected
     #[clap(short = 'T', requires_all = &["test-runner"])]
     test_runner_type: Option<TestRunnerType>,
     /// List available tests
@@ -52,9 +57,70 @@ struct Opt {
     /// Select reporter type
     #[clap(short = 'R', default_value = & DEFAULT_REPORTER_STR)]
     reporter: ReporterType,
-    /// Specify which test number to run. See --list for available tests
+    /// File written by reporters like JUnit and Json, instead of stdout
+    #[clap(long, parse(from_os_str))]
+    report_file: Option<PathBuf>,
+    /// Specify which tests to run: an index or an id (see --list), a glob like
+    /// `Foo.*` or a tag like `[fast]`. Tests starting with `-` or `~` are excluded
+    #[clap(long, requires_all = &["test-runner"], allow_hyphen_values = true)]
+    only: Vec<String>,
+    /// Run all the tests of a test suite
     #[clap(long, requires_all = &["test-runner"])]
-    only: Option<usize>,
+    suite: Vec<String>,
+    /// Specify which tests not to run, with the same syntax of --only
+    #[clap(long, requires_all = &["test-runner"])]
+    exclude: Vec<String>,
+    /// Run tests which failed in the last run: `Only` them until they pass, or `First`
+    #[clap(long, requires_all = &["test-runner"])]
+    rerun_failed: Option<RerunFailed>,
+    /// Path (or glob pattern) of the JUnit XML reports written by the test runner
+    #[clap(long, requires_all = &["test-runner"])]
+    junit_report: Option<String>,
+    /// Arguments needed to run a single test, with {suite}, {case} and {classname} placeholders
+    #[clap(long, requires_all = &["test-runner"])]
+    filter_template: Option<String>,
+    /// Maximum number of test runner processes running in parallel (GoogleTest only).
+    /// Defaults to the number of CPUs
+    #[clap(long, requires_all = &["test-runner"])]
+    shards: Option<usize>,
+    /// Run tests this number of times, to find flaky ones
+    #[clap(long, requires_all = &["test-runner"])]
+    repeat: Option<usize>,
+    /// Repeat tests until one of them fails
+    #[clap(long, requires_all = &["test-runner"])]
+    until_failure: bool,
+    /// Repeat tests for this number of seconds
+    #[clap(long, requires_all = &["test-runner"])]
+    repeat_for: Option<u64>,
+    /// Run tests in random order (GoogleTest only). The seed is printed in the report
+    #[clap(long, requires_all = &["test-runner"])]
+    shuffle: bool,
+    /// Seed of the random order of tests, to reproduce a run with --shuffle
+    #[clap(long, requires_all = &["test-runner"])]
+    seed: Option<u32>,
+    /// Command building the test runner before running it, like `cmake --build build`
+    #[clap(long, requires_all = &["test-runner"], validator = BuildCommand::parse)]
+    build: Option<String>,
+    /// Directory of a CMake, Cargo or Make project building the test runner before running it
+    #[clap(long, parse(from_os_str), requires_all = &["test-runner"], conflicts_with = "build")]
+    build_dir: Option<PathBuf>,
+    /// Collect code coverage of the test runner, instrumented with `Llvm` (clang, rustc) or
+    /// `Gcov` (gcc)
+    #[clap(long, requires_all = &["test-runner"])]
+    coverage: Option<CoverageTool>,
+    /// Write the code coverage of each run to this LCOV file
+    #[clap(long, parse(from_os_str), requires_all = &["coverage"])]
+    lcov: Option<PathBuf>,
+    /// Command running the test runner (or the data test program), like `valgrind` or
+    /// `qemu-aarch64 -L sysroot`
+    #[clap(long, validator = split_command_line)]
+    wrapper: Option<String>,
+    /// Run together the actions listed in a json session file, with a single report
+    #[clap(long, parse(from_os_str), conflicts_with_all = &["test-runner", "source"])]
+    session: Option<PathBuf>,
+    /// Arguments passed to the test runner
+    #[clap(last = true)]
+    test_runner_args: Vec<String>,
 }
 
 impl Opt {
@@ -64,11 +130,34 @@ impl Opt {
             .or_else(|| source.and_then(|x| Language::from_source(x)))
     }
 
+    fn test_runner_type_or_guess(&self) -> Option<TestRunnerType> {
+        let test_runner = self.test_runner.as_ref()?;
+        let trt = self
+            .test_runner_type
+            .or_else(|| detect_test_runner_type(test_runner));
+
+        if trt.is_none() {
+            log::warn!(
+                "Cannot detect test runner type of {}, select it with -T",
+                test_runner.display()
+            );
+        }
+
+        trt
+    }
+
     fn infer_options(mut self) -> Self {
         self.lang = self.lang_or_guess();
+        self.test_runner_type = self.test_runner_type_or_guess();
+
+        let test_runner = self.test_runner.as_ref().filter(|_| !self.no_watch);
 
-        if let Some(test_runner) = &self.test_runner {
-            if !self.no_watch {
+        if let Some(test_runner) = test_runner {
+            if let Some(build) = self.build() {
+                // Sources are watched, the test runner changes after the build
+                self.watch = self.watch.take().or_else(|| Some(build.source_dir()));
+            } else if test_runner.exists() {
+                // A command in PATH (e.g. npx) cannot be watched
                 self.watch = Some(test_runner.clone());
             }
         }
@@ -76,19 +165,168 @@ impl Opt {
         self
     }
 
+    /// Resolve relative paths from `dir`, like the directory of a session
+    /// file. The test runner may be a command in `PATH`, which is kept.
+    fn relative_to(mut self, dir: &Path) -> Self {
+        let paths = [
+            &mut self.watch,
+            &mut self.input,
+            &mut self.output,
+            &mut self.source,
+            &mut self.report_file,
+            &mut self.build_dir,
+            &mut self.lcov,
+        ];
+
+        for path in paths.into_iter().flatten() {
+            *path = dir.join(&*path);
+        }
+
+        if let Some(test_runner) = &mut self.test_runner {
+            let path = dir.join(&*test_runner);
+
+            if test_runner.components().count() > 1 || path.exists() {
+                *test_runner = path;
+            }
+        }
+
+        if let Some(junit_report) = &mut self.junit_report {
+            *junit_report = dir.join(&*junit_report).to_string_lossy().to_string();
+        }
+
+        self
+    }
+
+    /// Files and directories written by a run, whose changes must not
+    /// trigger another run
+    fn written_paths(&self) -> Vec<PathBuf> {
+        let mut paths: Vec<PathBuf> = [&self.report_file, &self.lcov]
+            .into_iter()
+            .flatten()
+            .cloned()
+            .collect();
+
+        if let Some(info) = self.test_runner_info() {
+            paths.extend(info.written_paths());
+        }
+
+        // The build directory may contain the sources (e.g. Cargo, Make or
+        // an in-source CMake build), then only its outputs are ignored
+        let mut build_dirs: Vec<PathBuf> = self.build_dir.iter().cloned().collect();
+
+        if let Some(build) = self.build() {
+            build_dirs.extend(build.output_dirs());
+        }
+
+        paths.extend(build_dirs.into_iter().filter(|x| !self.is_watched(x)));
+        paths
+    }
+
+    /// Check if `dir` is the watched directory, or contains it
+    fn is_watched(&self, dir: &Path) -> bool {
+        let cwd = std::env::current_dir().unwrap_or_default();
+        let absolute = |x: &Path| std::fs::canonicalize(x).unwrap_or_else(|_| cwd.join(x));
+
+        match &self.watch {
+            Some(watch) => absolute(watch).starts_with(absolute(dir)),
+            None => false,
+        }
+    }
+
+    fn build(&self) -> Option<BuildCommand> {
+        // Validated when parsed
+        if let Some(build) = &self.build {
+            return BuildCommand::parse(build).ok();
+        }
+
+        let dir = self.build_dir.as_ref()?;
+        let build = BuildCommand::detect(dir);
+
+        if build.is_none() {
+            log::warn!("Cannot detect how to build {}", dir.display());
+        }
+
+        build
+    }
+
+    fn wrapper(&self) -> Vec<String> {
+        // Validated when parsed
+        let wrapper = self.wrapper.as_deref().unwrap_or_default();
+        split_command_line(wrapper).unwrap_or_default()
+    }
+
     fn reporter(&self) -> Result<Box<dyn Reporter>, Error> {
-        provola_reporters::make_reporter(self.reporter)
+        provola_reporters::make_reporter(self.reporter, self.report_file.as_deref())
+    }
+
+    fn test_runner_info(&self) -> Option<TestRunnerInfo> {
+        let (exec, trt) = (self.test_runner.as_ref()?, self.test_runner_type?);
+
+        let exec = if self.test_runner_args.is_empty() {
+            Executable::from(exec.clone())
+        } else {
+            let exec = exec.to_string_lossy().to_string();
+            let argv = std::iter::once(exec)
+                .chain(self.test_runner_args.iter().cloned())
+                .collect();
+            Executable::Multiple(argv)
+        };
+
+        let exec = exec.wrap(self.wrapper());
+        let mut info = TestRunnerInfo::new(exec, trt);
+        info.report = self.junit_report.clone();
+        info.filter = self.filter_template.clone();
+        info.shards = self.shards;
+        info.build = self.build();
+        info.coverage = self.coverage.map(|tool| CoverageOpt {
+            tool,
+            lcov: self.lcov.clone(),
+        });
+        Some(info)
+    }
+
+    fn repeat(&self) -> Option<Repeat> {
+        let seed = match (self.seed, self.shuffle) {
+            (Some(seed), _) => Some(seed),
+            (None, true) => Some(Repeat::random_seed()),
+            (None, false) => None,
+        };
+
+        let repeat = Repeat {
+            count: self.repeat,
+            until_failure: self.until_failure,
+            duration: self.repeat_for.map(Duration::from_secs),
+            seed,
+        };
+
+        (repeat != Repeat::default()).then_some(repeat)
     }
 }
 
-impl From<&Opt> for TestRunnerOpt {
-    fn from(opt: &Opt) -> Self {
-        match opt.only {
-            None => TestRunnerOpt { only: Only::All },
-            Some(id) => TestRunnerOpt {
-                only: Only::SingleByIndex(id),
-            },
+impl TryFrom<&Opt> for TestRunnerOpt {
+    type Error = Error;
+
+    fn try_from(opt: &Opt) -> Result<Self, Error> {
+        let mut selection = Selection::parse(&opt.only)?;
+
+        for test_suite in opt.suite.iter() {
+            selection.include.push(Pattern::Suite(test_suite.clone()));
+        }
+
+        for pattern in opt.exclude.iter() {
+            selection.exclude.push(pattern.parse()?);
         }
+
+        let only = if selection == Selection::default() {
+            Only::All
+        } else {
+            Only::Selection(selection)
+        };
+
+        Ok(TestRunnerOpt {
+            only,
+            repeat: opt.repeat(),
+        })
     }
 }
 
@@ -97,20 +335,28 @@ impl TryFrom<&Opt> for provola_egui::ActionConfig {
     type Error = Error;
 
     fn try_from(opt: &Opt) -> Result<Self, Error> {
+        if let Some(path) = &opt.session {
+            let session = session::Session::load(path)?;
+            let actions = session
+                .opts()?
+                .into_iter()
+                .map(|(name, opt)| Ok((name, Self::try_from(&opt)?)))
+                .collect::<Result<_, Error>>()?;
+            return Ok(Self::Composite(actions, session.schedule));
+        }
+
         if let (Some(lang), Some(source), Some(input), Some(output)) =
             (opt.lang, &opt.source, &opt.input, &opt.output)
         {
             let source = Source::new(source.clone());
             let input = TestDataIn::new(input.clone());
             let output = TestDataOut::new(output.clone());
-            let a = Self::BuildTestInputOutput(lang, source, input, output);
+            let a = Self::BuildTestInputOutput(lang, source, input, output, opt.wrapper());
             return Ok(a);
         }
 
-        if let (Some(exec), Some(trt)) = (&opt.test_runner, opt.test_runner_type) {
-            let exec = exec.clone().into();
-            let info = TestRunnerInfo { exec, trt };
-            let a = Self::TestRunner(info, opt.into());
+        if let Some(info) = opt.test_runner_info() {
+            let a = Self::TestRunner(info, opt.try_into()?);
             return Ok(a);
         }
 
@@ -123,21 +369,35 @@ impl TryFrom<&Opt> for Action {
     type Error = Error;
 
     fn try_from(opt: &Opt) -> Result<Self, Error> {
+        if let Some(path) = &opt.session {
+            let session = session::Session::load(path)?;
+            let actions = session
+                .opts()?
+                .into_iter()
+                .map(|(name, opt)| Ok((name, Action::try_from(&opt)?)))
+                .collect::<Result<_, Error>>()?;
+            return Ok(Action::Composite(actions, session.schedule));
+        }
+
         if let (Some(lang), Some(source), Some(input), Some(output)) =
             (opt.lang, &opt.source, &opt.input, &opt.output)
         {
             let source = Source::new(source.clone());
             let input = TestDataIn::new(input.clone());
             let output = TestDataOut::new(output.clone());
-            let a = Action::BuildTestInputOutput(lang, source, input, output);
+            let a = Action::BuildTestInputOutput(lang, source, input, output, opt.wrapper());
             return Ok(a);
         }
 
-        if let (Some(exec), Some(trt)) = (&opt.test_runner, opt.test_runner_type) {
-            let exec = exec.clone().into();
-            let info = TestRunnerInfo { exec, trt };
-            let test_runner = make_test_runner(info);
-            let a = Action::TestRunner(test_runner?, opt.into());
+        if let Some(info) = opt.test_runner_info() {
+            let key = Vec::from(&info.exec).join(" ");
+            let mut test_runner = make_test_runner(info)?;
+
+            if let Some(mode) = opt.rerun_failed {
+                test_runner = Box::new(RerunFailedTestRunner::new(test_runner, mode, &key));
+            }
+
+            let a = Action::TestRunner(test_runner, opt.try_into()?);
             return Ok(a);
         }
 
@@ -156,6 +416,8 @@ impl TryFrom<Opt> for provola_egui::GuiConfig {
             watch_path,
             watch,
             action: (&opt).try_into().ok(),
+            rerun_failed: opt.rerun_failed,
+            editor: None,
         })
     }
 }
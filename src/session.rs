@@ -0,0 +1,77 @@
+use super::Opt;
+use clap::Parser;
+use provola_core::{Error, Schedule};
+use serde::Deserialize;
+use std::path::{Path, PathBuf};
+
+/// Many actions running together, with a single report. Each action is
+/// described by the arguments of provola, like `["-t", "build/tests"]`.
+#[derive(Debug, Deserialize)]
+pub(crate) struct Session {
+    #[serde(default)]
+    pub schedule: Schedule,
+    pub actions: Vec<SessionAction>,
+    /// Directory of the session file, relative paths of actions start here
+    #[serde(skip)]
+    dir: PathBuf,
+}
+
+#[derive(Debug, Deserialize)]
+pub(crate) struct SessionAction {
+    pub name: String,
+    pub args: Vec<String>,
+}
+
+impl Session {
+    pub fn load(path: &Path) -> Result<Self, Error> {
+        let s = std::fs::read_to_string(path)?;
+        let mut session: Session = serde_json::from_str(&s)
+            .map_err(|e| Error::GenericError(format!("Invalid {}: {}", path.display(), e)))?;
+        session.dir = path.parent().map(Path::to_path_buf).unwrap_or_default();
+        Ok(session)
+    }
+
+    /// Options of each action, by name
+    pub fn opts(&self) -> Result<Vec<(String, Opt)>, Error> {
+        self.actions
+            .iter()
+            .map(|action| {
+                let argv = std::iter::once("provola").chain(action.args.iter().map(|x| x.as_str()));
+                let opt = Opt::try_parse_from(argv).map_err(|e| {
+                    Error::GenericError(format!("Invalid arguments of {}: {}", action.name, e))
+                })?;
+
+                // A session including itself would never end
+                if opt.session.is_some() {
+                    return Err(Error::GenericError(format!(
+                        "Invalid arguments of {}: sessions cannot be nested",
+                        action.name
+                    )));
+                }
+
+                Ok((
+                    action.name.clone(),
+                    opt.relative_to(&self.dir).infer_options(),
+                ))
+            })
+            .collect()
+    }
+
+    /// Files to watch: the ones of every action
+    pub fn watch_paths(&self) -> Result<Vec<PathBuf>, Error> {
+        Ok(self
+            .opts()?
+            .into_iter()
+            .filter_map(|(_, x)| x.watch)
+            .collect())
+    }
+
+    /// Files written by every action, which are not watched
+    pub fn written_paths(&self) -> Result<Vec<PathBuf>, Error> {
+        Ok(self
+            .opts()?
+            .into_iter()
+            .flat_map(|(_, x)| x.written_paths())
+            .collect())
+    }
+}